@@ -1,39 +1,27 @@
-use std::{ops::RangeInclusive, rc::Rc};
+use std::{ops::RangeInclusive, sync::Arc};
 
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use datastructures_in_rust::intervals::{
-    brute_force::BruteForce,
-    segment_tree::{
+use datastructures_in_rust::{
+    bloom_filter::{
+        cuckoo_filter::CuckooFilter, standard_bloom_filter::BloomFilter, xor_filter::XorFilter,
+        ApproximateMembership,
+    },
+    intervals::segment_tree::{
         array_based_segment_tree::ArrayBasedSegmentTree, dynamic_segment_tree::DynamicSegmentTree,
         dynamic_segment_tree_with_range_updates::DynamicSegmentTreeWithRangeUpdates,
     },
+    suffix_array::{kasai::lcp_array, sa_is::suffix_array},
 };
 use rand::{thread_rng, Rng};
 
-trait Query<T> {
-    fn query(&self, range: RangeInclusive<usize>) -> Option<T>;
-}
-
-impl Query<i32> for ArrayBasedSegmentTree<i32> {
-    fn query(&self, range: RangeInclusive<usize>) -> Option<i32> {
-        self.query(range)
-    }
-}
-
-impl Query<i32> for BruteForce<i32> {
-    fn query(&self, range: RangeInclusive<usize>) -> Option<i32> {
-        self.query(range)
-    }
-}
-
 pub fn initializations(c: &mut Criterion) {
     let mut group = c.benchmark_group("Interval_Initialization");
     for i in [
-        (1..=10).collect::<Vec<i32>>(),
-        (1..=100).collect::<Vec<i32>>(),
-        (1..=1000).collect::<Vec<i32>>(),
-        (1..=10000).collect::<Vec<i32>>(),
-        (1..=100000).collect::<Vec<i32>>(),
+        (1..=10).collect::<Vec<i64>>(),
+        (1..=100).collect::<Vec<i64>>(),
+        (1..=1000).collect::<Vec<i64>>(),
+        (1..=10000).collect::<Vec<i64>>(),
+        (1..=100000).collect::<Vec<i64>>(),
     ]
     .iter()
     {
@@ -45,7 +33,7 @@ pub fn initializations(c: &mut Criterion) {
         group.bench_with_input(
             BenchmarkId::new("DynamicSegmentTree", i.len()),
             i,
-            |b, i| b.iter(|| DynamicSegmentTree::new_with_values(i, Rc::new(|a, b| a + b))),
+            |b, i| b.iter(|| DynamicSegmentTree::new_with_values(i, Arc::new(|a, b| a + b))),
         );
 
         group.bench_with_input(
@@ -53,7 +41,7 @@ pub fn initializations(c: &mut Criterion) {
             i,
             |b, i| {
                 b.iter(|| {
-                    DynamicSegmentTreeWithRangeUpdates::new_with_values(i, Rc::new(|a, b| a + b))
+                    DynamicSegmentTreeWithRangeUpdates::new_with_values(i, Arc::new(|a, b| a + b))
                 })
             },
         );
@@ -62,11 +50,12 @@ pub fn initializations(c: &mut Criterion) {
 
 pub fn queries(c: &mut Criterion) {
     const MAX: i32 = 1000000;
-    let values = (1..=MAX).collect::<Vec<i32>>();
+    // the sum of 1..=MAX does not fit an i32
+    let values = (1..=MAX as i64).collect::<Vec<i64>>();
     let st = ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
-    let dst = DynamicSegmentTree::new_with_values(&values, Rc::new(|a, b| a + b));
-    let mut dst_r =
-        DynamicSegmentTreeWithRangeUpdates::new_with_values(&values, Rc::new(|a, b| a + b));
+    let dst = DynamicSegmentTree::new_with_values(&values, Arc::new(|a, b| a + b));
+    let dst_r =
+        DynamicSegmentTreeWithRangeUpdates::new_with_values(&values, Arc::new(|a, b| a + b));
     let mut group = c.benchmark_group("Interval_Queries");
     for queries in [
         query_range(10, MAX),
@@ -116,14 +105,14 @@ pub fn queries(c: &mut Criterion) {
 
 pub fn updates(c: &mut Criterion) {
     const MAX: i32 = 1000000;
-    let values = (1..=MAX).collect::<Vec<i32>>();
+    let values = (1..=MAX as i64).collect::<Vec<i64>>();
     let mut st = ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
-    let mut dst: DynamicSegmentTree<i32> =
-        DynamicSegmentTree::new(0..=(values.len() - 1) as i64, Rc::new(|a, b| a + b));
-    let mut dst_r: DynamicSegmentTreeWithRangeUpdates<i32> =
+    let mut dst: DynamicSegmentTree<i64> =
+        DynamicSegmentTree::new(0..=(values.len() - 1) as i64, Arc::new(|a, b| a + b));
+    let mut dst_r: DynamicSegmentTreeWithRangeUpdates<i64> =
         DynamicSegmentTreeWithRangeUpdates::new(
             0..=(values.len() - 1) as i64,
-            Rc::new(|a, b| a + b),
+            Arc::new(|a, b| a + b),
         );
     let mut group = c.benchmark_group("Interval_Updates");
     for updates in [
@@ -173,14 +162,14 @@ pub fn updates(c: &mut Criterion) {
 
 pub fn updates_with_range(c: &mut Criterion) {
     const MAX: i32 = 1000000;
-    let values = (1..=MAX).collect::<Vec<i32>>();
+    let values = (1..=MAX as i64).collect::<Vec<i64>>();
     let mut st = ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
-    let mut dst: DynamicSegmentTree<i32> =
-        DynamicSegmentTree::new(0..=(values.len() - 1) as i64, Rc::new(|a, b| a + b));
-    let mut dst_r: DynamicSegmentTreeWithRangeUpdates<i32> =
+    let mut dst: DynamicSegmentTree<i64> =
+        DynamicSegmentTree::new(0..=(values.len() - 1) as i64, Arc::new(|a, b| a + b));
+    let mut dst_r: DynamicSegmentTreeWithRangeUpdates<i64> =
         DynamicSegmentTreeWithRangeUpdates::new(
             0..=(values.len() - 1) as i64,
-            Rc::new(|a, b| a + b),
+            Arc::new(|a, b| a + b),
         );
     let mut group = c.benchmark_group("Interval_Updates_With_Range");
     for updates in [query_range(10, MAX), query_range(100, MAX)].iter() {
@@ -222,6 +211,138 @@ pub fn updates_with_range(c: &mut Criterion) {
     }
 }
 
+/// Prints the heap bytes per element of each tree, the trees are built once per size
+pub fn memory_usage(_c: &mut Criterion) {
+    for size in [1_000, 10_000, 100_000, 1_000_000] {
+        let values = (1..=size).collect::<Vec<i64>>();
+        let range = 0..=(size - 1);
+        let st = ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
+        let dst = DynamicSegmentTree::new_with_values(&values, Arc::new(|a, b| a + b));
+        let dst_r =
+            DynamicSegmentTreeWithRangeUpdates::new_with_values(&values, Arc::new(|a, b| a + b));
+        let mut sparse_dst: DynamicSegmentTree<i64> =
+            DynamicSegmentTree::new(range.clone(), Arc::new(|a, b| a + b));
+        // a sparse tree, 1% of the elements are set
+        (0..size).step_by(100).for_each(|i| sparse_dst.insert(i, 1));
+        for (name, stats) in [
+            ("ArrayBasedSegmentTree", st.stats()),
+            ("DynamicSegmentTree", dst.stats()),
+            ("DynamicSegmentTreeWithRange", dst_r.stats()),
+            ("DynamicSegmentTree (1% set)", sparse_dst.stats()),
+        ] {
+            println!(
+                "Memory_Usage/{}/{}: {:.1} bytes per element ({} nodes, depth {})",
+                name,
+                size,
+                stats.bytes_per_element(&range),
+                stats.node_count,
+                stats.max_depth
+            );
+        }
+    }
+}
+
+/// The filters of the same keys, the Bloom filters sized for the false positive rates of the 8 and 16 bit fingerprints
+struct Filters {
+    bloom_8: BloomFilter,
+    bloom_16: BloomFilter,
+    cuckoo_8: CuckooFilter<u8>,
+    cuckoo_16: CuckooFilter<u16>,
+    xor_8: XorFilter<u8>,
+    xor_16: XorFilter<u16>,
+}
+
+impl Filters {
+    fn new(keys: u64) -> Self {
+        let mut filters = Filters {
+            bloom_8: BloomFilter::new(keys as usize, 1.0 / 256.0),
+            bloom_16: BloomFilter::new(keys as usize, 1.0 / 65_536.0),
+            cuckoo_8: CuckooFilter::new(keys as usize),
+            cuckoo_16: CuckooFilter::new(keys as usize),
+            xor_8: XorFilter::new(0..keys),
+            xor_16: XorFilter::new(0..keys),
+        };
+        for key in 0..keys {
+            filters.bloom_8.insert(&key);
+            filters.bloom_16.insert(&key);
+            filters.cuckoo_8.insert(&key).unwrap();
+            filters.cuckoo_16.insert(&key).unwrap();
+        }
+        filters
+    }
+
+    /// The name of each filter with the filter behind the shared trait
+    fn all(&self) -> [(&'static str, &dyn ApproximateMembership<u64>); 6] {
+        [
+            ("BloomFilter (1/256)", &self.bloom_8),
+            ("BloomFilter (1/65536)", &self.bloom_16),
+            ("CuckooFilter<u8>", &self.cuckoo_8),
+            ("CuckooFilter<u16>", &self.cuckoo_16),
+            ("XorFilter<u8>", &self.xor_8),
+            ("XorFilter<u16>", &self.xor_16),
+        ]
+    }
+}
+
+/// The number of items reported as present
+fn hits(filter: &dyn ApproximateMembership<u64>, items: &[u64]) -> usize {
+    items.iter().filter(|item| filter.contains(item)).count()
+}
+
+/// Prints the bits per key and the measured false positive rate of each filter, the filters are built once per size
+pub fn filter_memory_usage(_c: &mut Criterion) {
+    for keys in [10_000u64, 1_000_000] {
+        let filters = Filters::new(keys);
+        let absent: Vec<u64> = (keys..keys + 1_000_000).collect();
+        for (name, filter) in filters.all() {
+            println!(
+                "Filter_Memory_Usage/{}/{}: {:.2} bits per key, {:.6} false positive rate",
+                name,
+                keys,
+                filter.bit_count() as f64 / keys as f64,
+                hits(filter, &absent) as f64 / absent.len() as f64
+            )
+        }
+    }
+}
+
+/// Looks up keys of which half are present
+pub fn filter_lookups(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Filter_Lookup");
+    let mut rng = thread_rng();
+    for keys in [10_000u64, 1_000_000] {
+        let filters = Filters::new(keys);
+        let items: Vec<u64> = (0..10_000).map(|_| rng.gen_range(0..2 * keys)).collect();
+        for (name, filter) in filters.all() {
+            group.bench_with_input(BenchmarkId::new(name, keys), &items, |b, items| {
+                b.iter(|| hits(filter, items))
+            });
+        }
+    }
+}
+
+/// Builds the suffix and LCP arrays of random texts over 4 letters (like DNA) and over all the bytes
+pub fn suffix_arrays(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Suffix_Array");
+    group.sample_size(10);
+    let mut rng = thread_rng();
+    for size in [1_000, 10_000, 100_000, 1_000_000, 10_000_000] {
+        let dna: Vec<u8> = (0..size).map(|_| b"ACGT"[rng.gen_range(0..4)]).collect();
+        let bytes: Vec<u8> = (0..size).map(|_| rng.gen()).collect();
+        for (name, text) in [("SA-IS (ACGT)", &dna), ("SA-IS (bytes)", &bytes)] {
+            group.bench_with_input(BenchmarkId::new(name, size), text, |b, text| {
+                b.iter(|| suffix_array(text))
+            });
+        }
+        let suffixes = suffix_array(&dna);
+        group.bench_with_input(
+            BenchmarkId::new("Kasai (ACGT)", size),
+            &(dna, suffixes),
+            |b, (text, suffixes)| b.iter(|| lcp_array(text, suffixes)),
+        );
+    }
+}
+
 fn query_range(size: usize, max: i32) -> Vec<RangeInclusive<usize>> {
     let mut result = Vec::new();
     let max: usize = max as usize;
@@ -251,9 +372,13 @@ fn query_range_single_element(size: usize, max: i32) -> Vec<RangeInclusive<usize
 
 criterion_group!(
     benches,
+    memory_usage,
     // initializations,
     // queries,
     // updates,
-    updates_with_range
+    updates_with_range,
+    filter_memory_usage,
+    filter_lookups,
+    suffix_arrays
 );
 criterion_main!(benches);
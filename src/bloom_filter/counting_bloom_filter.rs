@@ -0,0 +1,342 @@
+//! Counting Bloom filter, a Bloom filter whose items can be removed.
+//! Every slot is a small counter instead of a bit: inserting an item increments its `k` counters
+//! and removing it decrements them. The width of the counters is chosen with the [`Counters`] type,
+//! 4 bits are enough for most uses (a counter only overflows past 15 items sharing it).
+//! Unlike a [`BloomFilter`](super::standard_bloom_filter::BloomFilter) it can't be combined with another filter
+//! or encoded as bytes.
+//! https://en.wikipedia.org/wiki/Counting_Bloom_filter
+#[cfg(debug_assertions)]
+use std::collections::HashMap;
+use std::{fmt::Display, hash::Hash};
+
+#[cfg(debug_assertions)]
+use super::hash_pair;
+use super::{false_positive_rate, optimal_parameters, positions};
+
+/// Error of an insertion or removal, the filter is left unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountingBloomError {
+    /// A counter of the item is at its maximum, the item was not inserted
+    CounterOverflow,
+    /// The item was never inserted (or was removed as many times as it was inserted).
+    /// Always detected when one of its counters is 0, and in debug builds for every item,
+    /// which keep track of the inserted items.
+    NotInserted,
+}
+
+impl Display for CountingBloomError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl std::error::Error for CountingBloomError {}
+
+/// The counters of a [`CountingBloomFilter`]
+pub trait Counters {
+    /// The largest count a counter holds
+    const MAX: u16;
+
+    /// The number of bits of a counter
+    const BITS: u64;
+
+    /// Creates `len` counters at 0
+    fn with_len(len: u64) -> Self;
+
+    fn get(&self, index: u64) -> u16;
+
+    fn set(&mut self, index: u64, count: u16);
+}
+
+/// 4 bit counters, packed two per byte
+#[derive(Debug, Clone, PartialEq)]
+pub struct FourBitCounters(Vec<u8>);
+
+impl Counters for FourBitCounters {
+    const MAX: u16 = 0xF;
+    const BITS: u64 = 4;
+
+    fn with_len(len: u64) -> Self {
+        FourBitCounters(vec![0; len.div_ceil(2) as usize])
+    }
+
+    fn get(&self, index: u64) -> u16 {
+        let byte = self.0[(index / 2) as usize];
+        ((byte >> (4 * (index % 2))) & 0xF) as u16
+    }
+
+    fn set(&mut self, index: u64, count: u16) {
+        let byte = &mut self.0[(index / 2) as usize];
+        let shift = 4 * (index % 2);
+        *byte = (*byte & !(0xF << shift)) | ((count as u8) << shift);
+    }
+}
+
+macro_rules! counters {
+    ($($t:ty),*) => {
+        $(
+            impl Counters for Vec<$t> {
+                const MAX: u16 = <$t>::MAX as u16;
+                const BITS: u64 = <$t>::BITS as u64;
+
+                fn with_len(len: u64) -> Self {
+                    vec![0; len as usize]
+                }
+
+                fn get(&self, index: u64) -> u16 {
+                    self[index as usize] as u16
+                }
+
+                fn set(&mut self, index: u64, count: u16) {
+                    self[index as usize] = count as $t;
+                }
+            }
+        )*
+    };
+}
+
+counters!(u8, u16);
+
+/// A Bloom filter supporting removals, `C` being the counters (4 bit by default, or `Vec<u8>` / `Vec<u16>`).
+/// ```
+/// # use datastructures_in_rust::bloom_filter::counting_bloom_filter::{CountingBloomError, CountingBloomFilter};
+/// let mut filter: CountingBloomFilter = CountingBloomFilter::new(1_000, 0.01);
+/// filter.insert("apple").unwrap();
+/// filter.insert("apple").unwrap();
+/// assert_eq!(filter.count("apple"), 2);
+/// filter.remove("apple").unwrap();
+/// assert!(filter.contains("apple"));
+/// filter.remove("apple").unwrap();
+/// assert!(!filter.contains("apple"));
+/// assert_eq!(filter.remove("apple"), Err(CountingBloomError::NotInserted));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CountingBloomFilter<C: Counters = FourBitCounters> {
+    counters: C,
+    size: u64,
+    hash_count: u32,
+    /// Seeds the hashes, filters with different seeds use different counters for the same item
+    seed: u64,
+    /// The number of counters above 0
+    nonzero: u64,
+    /// The number of times each item (by its hashes) is present, to detect the removal of items never inserted
+    #[cfg(debug_assertions)]
+    inserted: HashMap<(u64, u64), u64>,
+}
+
+impl<C: Counters> CountingBloomFilter<C> {
+    /// Creates a filter sized for `expected_items` with the given false positive rate,
+    /// see [`optimal_parameters`](super::optimal_parameters).
+    /// Panics if the rate is not within `(0, 1)`.
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        CountingBloomFilter::new_with_seed(expected_items, false_positive_rate, 0)
+    }
+
+    /// Creates a filter sized for `expected_items` with the given false positive rate and seed
+    pub fn new_with_seed(expected_items: usize, false_positive_rate: f64, seed: u64) -> Self {
+        let (size, hash_count) = optimal_parameters(expected_items, false_positive_rate);
+        CountingBloomFilter::with_parameters(size, hash_count, seed)
+    }
+
+    /// Creates a filter of `size` counters incrementing `hash_count` counters per item.
+    /// Panics if either is 0.
+    pub fn with_parameters(size: u64, hash_count: u32, seed: u64) -> Self {
+        assert!(
+            size > 0 && hash_count > 0,
+            "a filter needs counters and hashes"
+        );
+        CountingBloomFilter {
+            counters: C::with_len(size),
+            size,
+            hash_count,
+            seed,
+            nonzero: 0,
+            #[cfg(debug_assertions)]
+            inserted: HashMap::new(),
+        }
+    }
+
+    /// The number of counters of the filter
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The number of counters incremented per item
+    pub fn hash_count(&self) -> u32 {
+        self.hash_count
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Inserts an occurrence of the item.
+    /// Fails without changing anything if one of its counters is at its maximum.
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) -> Result<(), CountingBloomError> {
+        let positions: Vec<u64> = self.positions(item).collect();
+        if positions.iter().any(|p| self.counters.get(*p) == C::MAX) {
+            return Err(CountingBloomError::CounterOverflow);
+        }
+        for position in positions {
+            let count = self.counters.get(position);
+            if count == 0 {
+                self.nonzero += 1;
+            }
+            self.counters.set(position, count + 1);
+        }
+        #[cfg(debug_assertions)]
+        {
+            *self.inserted.entry(hash_pair(item, self.seed)).or_insert(0) += 1;
+        }
+        Ok(())
+    }
+
+    /// Removes an occurrence of the item.
+    /// Fails without changing anything if the item is detected as never inserted, see [`CountingBloomError::NotInserted`].
+    /// In release builds removing an item that was never inserted (but looks present) corrupts the filter,
+    /// the items sharing its counters may then be missed.
+    pub fn remove<T: Hash + ?Sized>(&mut self, item: &T) -> Result<(), CountingBloomError> {
+        let positions: Vec<u64> = self.positions(item).collect();
+        if positions.iter().any(|p| self.counters.get(*p) == 0) {
+            return Err(CountingBloomError::NotInserted);
+        }
+        #[cfg(debug_assertions)]
+        {
+            let key = hash_pair(item, self.seed);
+            match self.inserted.get_mut(&key) {
+                Some(1) => {
+                    self.inserted.remove(&key);
+                }
+                Some(count) => *count -= 1,
+                None => return Err(CountingBloomError::NotInserted),
+            }
+        }
+        for position in positions {
+            let count = self.counters.get(position) - 1;
+            if count == 0 {
+                self.nonzero -= 1;
+            }
+            self.counters.set(position, count);
+        }
+        Ok(())
+    }
+
+    /// True if the item is possibly present, false if it is definitely not
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        self.count(item) > 0
+    }
+
+    /// An upper bound of the number of occurrences of the item, the smallest of its counters
+    pub fn count<T: Hash + ?Sized>(&self, item: &T) -> u16 {
+        self.positions(item)
+            .map(|position| self.counters.get(position))
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// The fraction of the counters above 0
+    pub fn fill_ratio(&self) -> f64 {
+        self.nonzero as f64 / self.size as f64
+    }
+
+    /// The probability that an item not present is reported as present, given the counters above 0
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        false_positive_rate(self.fill_ratio(), self.hash_count)
+    }
+
+    fn positions<'a, T: Hash + ?Sized>(&self, item: &'a T) -> impl Iterator<Item = u64> + 'a {
+        positions(item, self.seed, self.hash_count, self.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Counters, CountingBloomError, CountingBloomFilter, FourBitCounters};
+
+    #[test]
+    fn four_bit_counters_are_packed() {
+        let mut counters = FourBitCounters::with_len(5);
+        assert_eq!(counters.0.len(), 3);
+        counters.set(1, 15);
+        counters.set(2, 7);
+        counters.set(3, 1);
+        assert_eq!(
+            (0..5).map(|i| counters.get(i)).collect::<Vec<_>>(),
+            vec![0, 15, 7, 1, 0]
+        );
+        counters.set(1, 0);
+        assert_eq!(counters.get(1), 0);
+        assert_eq!(counters.get(0), 0);
+    }
+
+    #[test]
+    fn removed_items_are_absent() {
+        let mut filter: CountingBloomFilter = CountingBloomFilter::new(1_000, 0.01);
+        for i in 0..1_000u32 {
+            filter.insert(&i).unwrap();
+        }
+        for i in (0..1_000u32).step_by(2) {
+            filter.remove(&i).unwrap();
+        }
+        assert!((1..1_000u32).step_by(2).all(|i| filter.contains(&i)));
+        let false_positives = (0..1_000u32)
+            .step_by(2)
+            .filter(|i| filter.contains(i))
+            .count();
+        assert!(false_positives < 10, "{}", false_positives);
+        for i in (1..1_000u32).step_by(2) {
+            filter.remove(&i).unwrap();
+        }
+        assert_eq!(filter.fill_ratio(), 0.0);
+        assert!(!filter.contains(&1u32));
+    }
+
+    #[test]
+    fn counts_are_upper_bounds() {
+        let mut filter: CountingBloomFilter<Vec<u8>> = CountingBloomFilter::new(100, 0.01);
+        for _ in 0..3 {
+            filter.insert("a").unwrap();
+        }
+        filter.insert("b").unwrap();
+        assert!(filter.count("a") >= 3);
+        assert!(filter.count("b") >= 1);
+        assert_eq!(filter.count("c"), 0);
+    }
+
+    #[test]
+    fn overflows_are_detected() {
+        let mut filter: CountingBloomFilter = CountingBloomFilter::new(100, 0.01);
+        for _ in 0..15 {
+            filter.insert("a").unwrap();
+        }
+        assert_eq!(filter.insert("a"), Err(CountingBloomError::CounterOverflow));
+        assert_eq!(filter.count("a"), 15);
+        let mut filter: CountingBloomFilter<Vec<u8>> = CountingBloomFilter::new(100, 0.01);
+        for _ in 0..255 {
+            filter.insert("a").unwrap();
+        }
+        assert_eq!(filter.insert("a"), Err(CountingBloomError::CounterOverflow));
+        let mut filter: CountingBloomFilter<Vec<u16>> = CountingBloomFilter::new(100, 0.01);
+        for _ in 0..256 {
+            filter.insert("a").unwrap();
+        }
+        assert_eq!(filter.count("a"), 256);
+    }
+
+    #[test]
+    fn removing_items_never_inserted_fails() {
+        let mut filter: CountingBloomFilter = CountingBloomFilter::with_parameters(4, 1, 0);
+        assert_eq!(filter.remove("a"), Err(CountingBloomError::NotInserted));
+        // with 4 counters every other item shares a counter with "a"
+        filter.insert("a").unwrap();
+        let other = (0..100u32)
+            .find(|i| filter.contains(i))
+            .expect("a false positive");
+        if cfg!(debug_assertions) {
+            assert_eq!(filter.remove(&other), Err(CountingBloomError::NotInserted));
+            assert!(filter.contains("a"));
+        }
+        filter.remove("a").unwrap();
+        assert_eq!(filter.remove("a"), Err(CountingBloomError::NotInserted));
+    }
+}
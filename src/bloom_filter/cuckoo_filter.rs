@@ -0,0 +1,324 @@
+//! Cuckoo filter, a cuckoo hash table of fingerprints.
+//! Every item has a fingerprint and two candidate buckets of [`BUCKET_SIZE`] slots, the second one being
+//! `bucket ^ hash(fingerprint)` so that either bucket can be found from the other and the fingerprint alone.
+//! An item goes into a free slot of its buckets, or evicts a fingerprint to the other bucket of that fingerprint,
+//! and so on. Unlike a Bloom filter the items can be removed, and it takes less space below a false positive rate
+//! of about 3%: `(log2(1 / p) + 3) / α` bits per item for a load factor `α`.
+//! https://www.cs.cmu.edu/~dga/papers/cuckoo-conext2014.pdf
+use std::{fmt::Display, hash::Hash};
+
+use super::{hash_pair, mix, ApproximateMembership, Fingerprint};
+
+/// The number of fingerprints of a bucket
+pub const BUCKET_SIZE: usize = 4;
+
+/// The load factor the filters are sized for, the insertions start failing around 95%
+const MAX_LOAD_FACTOR: f64 = 0.95;
+
+/// The number of fingerprints evicted before the filter is considered full
+const MAX_EVICTIONS: usize = 500;
+
+/// Error of an insertion into a filter that is full, the filter is left unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CuckooFilterFull;
+
+impl Display for CuckooFilterFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl std::error::Error for CuckooFilterFull {}
+
+/// A cuckoo filter of the items implementing [`Hash`], `F` being the fingerprints (16 bits by default).
+/// ```
+/// # use datastructures_in_rust::bloom_filter::cuckoo_filter::CuckooFilter;
+/// let mut filter: CuckooFilter = CuckooFilter::new(1_000);
+/// filter.insert("apple").unwrap();
+/// assert!(filter.contains("apple"));
+/// assert!(filter.remove("apple"));
+/// assert!(!filter.contains("apple"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CuckooFilter<F: Fingerprint = u16> {
+    /// The slots of the bucket `b` are `b * BUCKET_SIZE..(b + 1) * BUCKET_SIZE`, the empty ones hold the default
+    slots: Vec<F>,
+    /// The number of buckets minus one, the number of buckets is a power of 2
+    bucket_mask: u64,
+    len: usize,
+    /// Seeds the hashes of the items
+    seed: u64,
+    /// The fingerprint evicted by the insertion that filled the filter, and one of its buckets.
+    /// Kept aside so that no item is lost, the filter is full while there is one.
+    victim: Option<(usize, F)>,
+    /// The state of the generator choosing the fingerprints to evict
+    random: u64,
+}
+
+impl<F: Fingerprint> CuckooFilter<F> {
+    /// Creates a filter with room for `capacity` items
+    pub fn new(capacity: usize) -> Self {
+        CuckooFilter::new_with_seed(capacity, 0)
+    }
+
+    /// Creates a filter with room for `capacity` items, filters with different seeds use different buckets for the same item
+    pub fn new_with_seed(capacity: usize, seed: u64) -> Self {
+        let buckets = (capacity as f64 / (BUCKET_SIZE as f64 * MAX_LOAD_FACTOR)).ceil() as usize;
+        let buckets = buckets.max(1).next_power_of_two();
+        CuckooFilter {
+            slots: vec![F::default(); buckets * BUCKET_SIZE],
+            bucket_mask: buckets as u64 - 1,
+            len: 0,
+            seed,
+            victim: None,
+            random: mix(seed),
+        }
+    }
+
+    /// The number of items
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of slots, more than the items the filter can hold
+    pub fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The fraction of the slots holding a fingerprint
+    pub fn load_factor(&self) -> f64 {
+        self.len as f64 / self.slots.len() as f64
+    }
+
+    /// The number of bits of the slots
+    pub fn bit_count(&self) -> u64 {
+        self.slots.len() as u64 * F::BITS
+    }
+
+    /// The probability that an item not present has the fingerprint of one of the items of its two buckets
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        let compared = 2.0 * BUCKET_SIZE as f64 * self.load_factor();
+        1.0 - (1.0 - 0.5f64.powi(F::BITS as i32)).powf(compared)
+    }
+
+    /// Inserts an occurrence of the item, an item can be inserted up to `2 * BUCKET_SIZE` times.
+    /// Fails if the filter is full.
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) -> Result<(), CuckooFilterFull> {
+        if self.victim.is_some() {
+            return Err(CuckooFilterFull);
+        }
+        let (bucket, fingerprint) = self.bucket_and_fingerprint(item);
+        self.insert_fingerprint(bucket, fingerprint);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Removes an occurrence of the item, returns false if it is not present.
+    /// Removing an item that was never inserted (but looks present) removes another item sharing its fingerprint.
+    pub fn remove<T: Hash + ?Sized>(&mut self, item: &T) -> bool {
+        let (first, fingerprint) = self.bucket_and_fingerprint(item);
+        let second = self.alternate(first, fingerprint);
+        if let Some((bucket, victim)) = self.victim {
+            if victim == fingerprint && (bucket == first || bucket == second) {
+                self.victim = None;
+                self.len -= 1;
+                return true;
+            }
+        }
+        let slot = self
+            .slots_of(first)
+            .chain(self.slots_of(second))
+            .find(|slot| self.slots[*slot] == fingerprint);
+        match slot {
+            Some(slot) => {
+                self.slots[slot] = F::default();
+                self.len -= 1;
+                // there is room for the victim now
+                if let Some((bucket, victim)) = self.victim.take() {
+                    self.insert_fingerprint(bucket, victim);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// True if the item is possibly present, false if it is definitely not
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        let (first, fingerprint) = self.bucket_and_fingerprint(item);
+        let second = self.alternate(first, fingerprint);
+        self.victim.is_some_and(|(bucket, victim)| {
+            victim == fingerprint && (bucket == first || bucket == second)
+        }) || self
+            .slots_of(first)
+            .chain(self.slots_of(second))
+            .any(|slot| self.slots[slot] == fingerprint)
+    }
+
+    /// Stores the fingerprint in the bucket or its alternate, evicting fingerprints to their alternate buckets if both are full.
+    /// The last fingerprint evicted becomes the victim if there is still no room.
+    fn insert_fingerprint(&mut self, bucket: usize, fingerprint: F) {
+        let (mut bucket, mut fingerprint) = (bucket, fingerprint);
+        if self.put(bucket, fingerprint) {
+            return;
+        }
+        bucket = self.alternate(bucket, fingerprint);
+        for _ in 0..MAX_EVICTIONS {
+            if self.put(bucket, fingerprint) {
+                return;
+            }
+            let slot = bucket * BUCKET_SIZE + (self.next_random() % BUCKET_SIZE as u64) as usize;
+            std::mem::swap(&mut fingerprint, &mut self.slots[slot]);
+            bucket = self.alternate(bucket, fingerprint);
+        }
+        self.victim = Some((bucket, fingerprint));
+    }
+
+    /// Stores the fingerprint in a free slot of the bucket, returns false if there is none
+    fn put(&mut self, bucket: usize, fingerprint: F) -> bool {
+        let empty = F::default();
+        match self
+            .slots_of(bucket)
+            .find(|slot| self.slots[*slot] == empty)
+        {
+            Some(slot) => {
+                self.slots[slot] = fingerprint;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The first bucket of the item and its fingerprint, never the default that marks the empty slots
+    fn bucket_and_fingerprint<T: Hash + ?Sized>(&self, item: &T) -> (usize, F) {
+        let (bucket, fingerprint) = hash_pair(item, self.seed);
+        let mut fingerprint = F::from_hash(fingerprint);
+        if fingerprint == F::default() {
+            fingerprint = F::from_hash(1);
+        }
+        ((bucket & self.bucket_mask) as usize, fingerprint)
+    }
+
+    /// The other bucket of the fingerprint, the alternate of the alternate is the bucket itself
+    fn alternate(&self, bucket: usize, fingerprint: F) -> usize {
+        ((bucket as u64 ^ mix(fingerprint.to_u64())) & self.bucket_mask) as usize
+    }
+
+    fn slots_of(&self, bucket: usize) -> std::ops::Range<usize> {
+        bucket * BUCKET_SIZE..(bucket + 1) * BUCKET_SIZE
+    }
+
+    /// xorshift64
+    fn next_random(&mut self) -> u64 {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 7;
+        self.random ^= self.random << 17;
+        self.random
+    }
+}
+
+impl<T: Hash + ?Sized, F: Fingerprint> ApproximateMembership<T> for CuckooFilter<F> {
+    fn contains(&self, item: &T) -> bool {
+        CuckooFilter::contains(self, item)
+    }
+
+    fn bit_count(&self) -> u64 {
+        CuckooFilter::bit_count(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CuckooFilter, CuckooFilterFull, BUCKET_SIZE};
+
+    #[test]
+    fn inserted_items_are_present_until_removed() {
+        let mut filter: CuckooFilter = CuckooFilter::new(10_000);
+        for i in 0..10_000u32 {
+            filter.insert(&i).unwrap();
+        }
+        assert_eq!(filter.len(), 10_000);
+        assert!(filter.load_factor() > 0.6);
+        assert!((0..10_000u32).all(|i| filter.contains(&i)));
+        for i in (0..10_000u32).step_by(2) {
+            assert!(filter.remove(&i));
+        }
+        assert!((1..10_000u32).step_by(2).all(|i| filter.contains(&i)));
+        let false_positives = (0..10_000u32)
+            .step_by(2)
+            .filter(|i| filter.contains(i))
+            .count();
+        assert!(false_positives < 5, "{}", false_positives);
+        assert_eq!(filter.len(), 5_000);
+    }
+
+    #[test]
+    fn empirical_false_positive_rate_matches_the_fingerprints() {
+        let items = 20_000u64;
+        let trials = 200_000u64;
+        let mut small: CuckooFilter<u8> = CuckooFilter::new(items as usize);
+        let mut large: CuckooFilter<u16> = CuckooFilter::new(items as usize);
+        for i in 0..items {
+            small.insert(&i).unwrap();
+            large.insert(&i).unwrap();
+        }
+        for (rate, estimated) in [
+            (
+                (items..items + trials)
+                    .filter(|i| small.contains(i))
+                    .count(),
+                small.estimated_false_positive_rate(),
+            ),
+            (
+                (items..items + trials)
+                    .filter(|i| large.contains(i))
+                    .count(),
+                large.estimated_false_positive_rate(),
+            ),
+        ] {
+            let rate = rate as f64 / trials as f64;
+            assert!(
+                rate < 1.3 * estimated && rate > 0.7 * estimated,
+                "measured {} for {}",
+                rate,
+                estimated
+            );
+        }
+    }
+
+    #[test]
+    fn full_filters_keep_their_items() {
+        let mut filter: CuckooFilter<u16> = CuckooFilter::new(8);
+        let slots = filter.slot_count();
+        let inserted = (0..100u32).take_while(|i| filter.insert(i).is_ok()).count();
+        assert!(
+            inserted > slots / 2 && inserted <= slots + 1,
+            "{}",
+            inserted
+        );
+        assert_eq!(filter.insert(&1_000u32), Err(CuckooFilterFull));
+        assert_eq!(filter.len(), inserted);
+        assert!((0..inserted as u32).all(|i| filter.contains(&i)));
+        // the victim moves into the room made
+        assert!(filter.remove(&0u32));
+        assert_eq!(filter.len(), inserted - 1);
+        assert!((1..inserted as u32).all(|i| filter.contains(&i)));
+    }
+
+    #[test]
+    fn duplicates_fill_both_buckets() {
+        let mut filter: CuckooFilter = CuckooFilter::new(1_000);
+        for _ in 0..2 * BUCKET_SIZE {
+            filter.insert("a").unwrap();
+        }
+        for _ in 0..2 * BUCKET_SIZE {
+            assert!(filter.remove("a"));
+        }
+        assert!(!filter.remove("a"));
+        assert!(filter.is_empty());
+    }
+}
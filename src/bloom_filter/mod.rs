@@ -0,0 +1,171 @@
+//! Bloom filter module
+//! Approximate set membership: `contains` may return true for an item that was never inserted (a false positive),
+//! but never returns false for an inserted one.
+//! Besides the Bloom filters, the cuckoo and XOR filters store a fingerprint of each item instead of setting bits,
+//! all of them implement [`ApproximateMembership`].
+//!
+//! The items are hashed with SipHash-2-4, which doesn't change between Rust releases, and the integers are
+//! written little endian and `usize` / `isize` as 64 bits: the same items set the same bits on every platform.
+//! That only holds if the `Hash` implementation of the items writes the same data everywhere, e.g. a type
+//! hashing a pointer or a `HashMap` in iteration order sets different bits in every process.
+//! https://en.wikipedia.org/wiki/Bloom_filter
+use std::{
+    f64::consts::LN_2,
+    hash::{Hash, Hasher},
+};
+
+pub mod counting_bloom_filter;
+pub mod cuckoo_filter;
+pub mod scalable_bloom_filter;
+mod sip_hasher;
+pub mod standard_bloom_filter;
+pub mod xor_filter;
+
+use counting_bloom_filter::{Counters, CountingBloomFilter};
+use scalable_bloom_filter::ScalableBloomFilter;
+use sip_hasher::SipHasher24;
+use standard_bloom_filter::BloomFilter;
+
+/// A filter answering whether an item of type `T` is possibly present, so that the filters can be swapped.
+/// Only the lookups are shared, the filters are built differently (the XOR filter can't insert).
+/// The item type is a parameter of the trait rather than of `contains`, so that a filter can be used as a
+/// `&dyn ApproximateMembership<T>`.
+pub trait ApproximateMembership<T: Hash + ?Sized> {
+    /// True if the item is possibly present, false if it is definitely not
+    fn contains(&self, item: &T) -> bool;
+
+    /// The number of bits the filter takes to store its items
+    fn bit_count(&self) -> u64;
+}
+
+impl<T: Hash + ?Sized> ApproximateMembership<T> for BloomFilter {
+    fn contains(&self, item: &T) -> bool {
+        BloomFilter::contains(self, item)
+    }
+
+    fn bit_count(&self) -> u64 {
+        BloomFilter::bit_count(self)
+    }
+}
+
+impl<T: Hash + ?Sized, C: Counters> ApproximateMembership<T> for CountingBloomFilter<C> {
+    fn contains(&self, item: &T) -> bool {
+        CountingBloomFilter::contains(self, item)
+    }
+
+    fn bit_count(&self) -> u64 {
+        self.size() * C::BITS
+    }
+}
+
+impl<T: Hash + ?Sized> ApproximateMembership<T> for ScalableBloomFilter {
+    fn contains(&self, item: &T) -> bool {
+        ScalableBloomFilter::contains(self, item)
+    }
+
+    fn bit_count(&self) -> u64 {
+        ScalableBloomFilter::bit_count(self)
+    }
+}
+
+/// The fingerprint of an item in a cuckoo or XOR filter, a false positive happens when two fingerprints collide.
+/// Wider fingerprints lower the false positive rate by 2 for every bit.
+pub trait Fingerprint: Copy + Default + Eq {
+    /// The number of bits of the fingerprint
+    const BITS: u64;
+
+    /// The low bits of the hash
+    fn from_hash(hash: u64) -> Self;
+
+    fn to_u64(self) -> u64;
+
+    /// Xor of the two fingerprints
+    fn xor(self, other: Self) -> Self;
+}
+
+macro_rules! fingerprint {
+    ($($t:ty),*) => {
+        $(
+            impl Fingerprint for $t {
+                const BITS: u64 = <$t>::BITS as u64;
+
+                fn from_hash(hash: u64) -> Self {
+                    hash as $t
+                }
+
+                fn to_u64(self) -> u64 {
+                    self as u64
+                }
+
+                fn xor(self, other: Self) -> Self {
+                    self ^ other
+                }
+            }
+        )*
+    };
+}
+
+fingerprint!(u8, u16, u32);
+
+/// The number of bits and hashes minimizing the size of a filter holding `expected_items`
+/// with the given false positive rate: `m = -n ln(p) / ln(2)²` bits and `k = m / n ln(2)` hashes.
+/// Panics if the rate is not within `(0, 1)`.
+/// ```
+/// # use datastructures_in_rust::bloom_filter::optimal_parameters;
+/// assert_eq!(optimal_parameters(1_000, 0.01), (9_586, 7));
+/// ```
+pub fn optimal_parameters(expected_items: usize, false_positive_rate: f64) -> (u64, u32) {
+    assert!(
+        false_positive_rate > 0.0 && false_positive_rate < 1.0,
+        "the false positive rate {} is not within (0, 1)",
+        false_positive_rate
+    );
+    let items = expected_items.max(1) as f64;
+    let bits = (-items * false_positive_rate.ln() / (LN_2 * LN_2))
+        .ceil()
+        .max(1.0);
+    let hashes = (bits / items * LN_2).round().max(1.0);
+    (bits as u64, hashes as u32)
+}
+
+/// The false positive rate of a filter setting `hashes` bits per item, `fill_ratio` of its bits being set
+fn false_positive_rate(fill_ratio: f64, hashes: u32) -> f64 {
+    fill_ratio.powi(hashes as i32)
+}
+
+/// Mixes the bits of the hash, the finalizer of splitmix64.
+/// http://xorshift.di.unimi.it/splitmix64.c
+fn mix(hash: u64) -> u64 {
+    let mut z = hash.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A hash of the item seeded with `seed`, different for every `salt`: SipHash-2-4 keyed with both
+fn hash<T: Hash + ?Sized>(item: &T, seed: u64, salt: u64) -> u64 {
+    let mut hasher = SipHasher24::new_with_keys(seed, salt);
+    item.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Two independent hashes of the item seeded with `seed`
+fn hash_pair<T: Hash + ?Sized>(item: &T, seed: u64) -> (u64, u64) {
+    (hash(item, seed, 0), hash(item, seed, 1))
+}
+
+/// The positions of the item in a filter of `size` slots, `count` of them by double hashing:
+/// `h1 + i * h2` for two hashes of the item seeded with `seed`.
+/// https://www.eecs.harvard.edu/~michaelm/postscripts/rsa2008.pdf
+fn positions<T: Hash + ?Sized>(
+    item: &T,
+    seed: u64,
+    count: u32,
+    size: u64,
+) -> impl Iterator<Item = u64> {
+    let (first, second) = hash_pair(item, seed);
+    let first = (first % size) as u128;
+    // never 0, so that the positions are not all the same
+    let second = (second % size).max(1) as u128;
+    (0..count as u128).map(move |i| ((first + i * second) % size as u128) as u64)
+}
@@ -0,0 +1,267 @@
+//! Scalable Bloom filter, a chain of Bloom filters growing with the number of items.
+//! Once the last filter holds its capacity a larger one is added, with a tighter false positive rate:
+//! layer `i` holds `n0 sⁱ` items at the rate `P (1 - r) rⁱ`, so the compounded rate `1 - Π(1 - Pᵢ)`
+//! stays under `Σ Pᵢ < P` however many layers are added.
+//! The layers grow independently in every filter, so unlike a [`BloomFilter`] it can't be combined with another
+//! filter or encoded as bytes.
+//! https://gsd.di.uminho.pt/members/cbm/ps/dbloom.pdf
+use std::hash::Hash;
+
+use super::standard_bloom_filter::BloomFilter;
+
+/// The growth of the capacity from one layer to the next when not given
+pub const DEFAULT_GROWTH: usize = 2;
+
+/// The ratio of the false positive rates of consecutive layers when not given
+pub const DEFAULT_TIGHTENING: f64 = 0.85;
+
+/// A Bloom filter of any number of items whose false positive rate stays under a bound.
+/// ```
+/// # use datastructures_in_rust::bloom_filter::scalable_bloom_filter::ScalableBloomFilter;
+/// let mut filter = ScalableBloomFilter::new(100, 0.01);
+/// for i in 0..1_000 {
+///     filter.insert(&i);
+/// }
+/// assert!((0..1_000).all(|i| filter.contains(&i)));
+/// assert_eq!(filter.layer_count(), 4);
+/// assert!(filter.estimated_false_positive_rate() < 0.01);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScalableBloomFilter {
+    layers: Vec<Layer>,
+    initial_capacity: usize,
+    /// The bound of the compounded false positive rate
+    false_positive_rate: f64,
+    growth: usize,
+    tightening: f64,
+    seed: u64,
+}
+
+/// A filter of the chain and the number of items it holds, only the last layer receives items
+#[derive(Debug, Clone)]
+struct Layer {
+    filter: BloomFilter,
+    capacity: usize,
+    false_positive_rate: f64,
+    len: usize,
+}
+
+/// The state of a layer of a [`ScalableBloomFilter`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerStats {
+    /// The number of items the layer was sized for
+    pub capacity: usize,
+    /// The number of items inserted into the layer
+    pub len: usize,
+    /// The fraction of the bits of the layer that are set
+    pub fill_ratio: f64,
+    /// The false positive rate the layer was sized for
+    pub target_false_positive_rate: f64,
+    /// The probability that the layer reports an item never inserted, given its bits set so far
+    pub false_positive_rate: f64,
+}
+
+impl ScalableBloomFilter {
+    /// Creates a filter whose first layer holds `initial_capacity` items, keeping the compounded
+    /// false positive rate under the bound with the [`DEFAULT_GROWTH`] and [`DEFAULT_TIGHTENING`].
+    /// Panics if the rate is not within `(0, 1)`.
+    pub fn new(initial_capacity: usize, false_positive_rate: f64) -> Self {
+        ScalableBloomFilter::with_parameters(
+            initial_capacity,
+            false_positive_rate,
+            DEFAULT_GROWTH,
+            DEFAULT_TIGHTENING,
+            0,
+        )
+    }
+
+    /// Creates a filter whose layers grow `growth` times in capacity and `tightening` times in false positive rate.
+    /// Panics if the initial capacity is 0, the growth is below 1, or the rate or the tightening are not within `(0, 1)`.
+    pub fn with_parameters(
+        initial_capacity: usize,
+        false_positive_rate: f64,
+        growth: usize,
+        tightening: f64,
+        seed: u64,
+    ) -> Self {
+        assert!(initial_capacity > 0, "the initial capacity is 0");
+        assert!(growth >= 1, "the layers can't shrink");
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "the false positive rate {} is not within (0, 1)",
+            false_positive_rate
+        );
+        assert!(
+            tightening > 0.0 && tightening < 1.0,
+            "the tightening ratio {} is not within (0, 1)",
+            tightening
+        );
+        let mut filter = ScalableBloomFilter {
+            layers: vec![],
+            initial_capacity,
+            false_positive_rate,
+            growth,
+            tightening,
+            seed,
+        };
+        filter.add_layer();
+        filter
+    }
+
+    /// The bound of the compounded false positive rate
+    pub fn false_positive_rate(&self) -> f64 {
+        self.false_positive_rate
+    }
+
+    /// Inserts the item, returns false if it was (possibly) present already.
+    /// Adds a layer when the last one holds its capacity.
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) -> bool {
+        if self.contains(item) {
+            return false;
+        }
+        if self.last().len >= self.last().capacity {
+            self.add_layer();
+        }
+        let last = self.layers.last_mut().expect("a filter has a layer");
+        last.filter.insert(item);
+        last.len += 1;
+        true
+    }
+
+    /// True if the item is possibly present, false if it was definitely never inserted
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        // the latest layers hold the most items
+        self.layers
+            .iter()
+            .rev()
+            .any(|layer| layer.filter.contains(item))
+    }
+
+    /// The number of items inserted, not counting those reported as present already
+    pub fn len(&self) -> usize {
+        self.layers.iter().map(|layer| layer.len).sum()
+    }
+
+    /// True if nothing was inserted
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of filters of the chain
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// The number of bits of all the layers
+    pub fn bit_count(&self) -> u64 {
+        self.layers
+            .iter()
+            .map(|layer| layer.filter.bit_count())
+            .sum()
+    }
+
+    /// The state of each layer, oldest first
+    pub fn layer_stats(&self) -> Vec<LayerStats> {
+        self.layers
+            .iter()
+            .map(|layer| LayerStats {
+                capacity: layer.capacity,
+                len: layer.len,
+                fill_ratio: layer.filter.fill_ratio(),
+                target_false_positive_rate: layer.false_positive_rate,
+                false_positive_rate: layer.filter.estimated_false_positive_rate(),
+            })
+            .collect()
+    }
+
+    /// The probability that an item never inserted is reported as present by any of the layers
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        1.0 - self
+            .layers
+            .iter()
+            .map(|layer| 1.0 - layer.filter.estimated_false_positive_rate())
+            .product::<f64>()
+    }
+
+    fn last(&self) -> &Layer {
+        self.layers.last().expect("a filter has a layer")
+    }
+
+    /// Adds a layer `growth` times larger than the last one, with a `tightening` times smaller rate
+    fn add_layer(&mut self) {
+        let index = self.layers.len();
+        let (capacity, false_positive_rate) = match self.layers.last() {
+            Some(last) => (
+                last.capacity.saturating_mul(self.growth),
+                last.false_positive_rate * self.tightening,
+            ),
+            None => (
+                self.initial_capacity,
+                self.false_positive_rate * (1.0 - self.tightening),
+            ),
+        };
+        // a different seed per layer, so that the layers don't share their false positives
+        let seed = self.seed.wrapping_add(index as u64);
+        self.layers.push(Layer {
+            filter: BloomFilter::new_with_seed(capacity, false_positive_rate, seed),
+            capacity,
+            false_positive_rate,
+            len: 0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScalableBloomFilter;
+
+    #[test]
+    fn layers_grow_and_tighten() {
+        let mut filter = ScalableBloomFilter::with_parameters(100, 0.01, 2, 0.5, 0);
+        assert!(filter.is_empty());
+        let inserted = (0..1_500u32).filter(|i| filter.insert(i)).count();
+        assert_eq!(filter.len(), inserted);
+        assert!((0..1_500u32).all(|i| filter.contains(&i)));
+        let stats = filter.layer_stats();
+        assert_eq!(
+            stats.iter().map(|s| s.capacity).collect::<Vec<_>>(),
+            vec![100, 200, 400, 800]
+        );
+        assert!(stats[..3].iter().all(|s| s.len == s.capacity));
+        for (i, s) in stats.iter().enumerate() {
+            let target = 0.005 * 0.5f64.powi(i as i32);
+            assert!((s.target_false_positive_rate - target).abs() < 1e-12);
+        }
+        // the full layers are about half full and close to their targets
+        for s in &stats[..3] {
+            assert!((s.fill_ratio - 0.5).abs() < 0.1, "{:?}", s);
+            assert!(s.false_positive_rate < 2.0 * s.target_false_positive_rate);
+        }
+    }
+
+    #[test]
+    fn compounded_false_positive_rate_stays_under_the_bound() {
+        for bound in [0.1, 0.01] {
+            let mut filter = ScalableBloomFilter::new(1_000, bound);
+            let items = 100_000u64;
+            for i in 0..items {
+                filter.insert(&i);
+            }
+            assert!(filter.layer_count() > 5);
+            let estimated = filter.estimated_false_positive_rate();
+            assert!(estimated < bound, "estimated {} for {}", estimated, bound);
+            let trials = 100_000u64;
+            let false_positives = (items..items + trials)
+                .filter(|i| filter.contains(i))
+                .count();
+            let rate = false_positives as f64 / trials as f64;
+            assert!(rate < bound, "measured {} for {}", rate, bound);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_tightening_panics() {
+        ScalableBloomFilter::with_parameters(100, 0.01, 2, 1.0, 0);
+    }
+}
@@ -0,0 +1,189 @@
+//! SipHash-2-4, the hash of the items of the filters.
+//! Unlike the `DefaultHasher` of the standard library, whose algorithm may change in any release,
+//! it is specified, so a filter encoded by one process sets the same bits in another one.
+//! The integers are written little endian and `usize` / `isize` as 64 bits, so that the standard `Hash`
+//! implementations of the integers, strings, slices and tuples write the same bytes on every platform.
+//! https://www.aumasson.jp/siphash/siphash.pdf
+use std::{convert::TryInto, hash::Hasher};
+
+/// A SipHash-2-4 hasher with explicit keys
+#[derive(Debug, Clone)]
+pub(crate) struct SipHasher24 {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    /// The bytes written since the last full word, in its low bytes
+    tail: u64,
+    /// The number of bytes in `tail`
+    tail_len: usize,
+    /// The number of bytes written
+    len: u64,
+}
+
+impl SipHasher24 {
+    pub(crate) fn new_with_keys(key0: u64, key1: u64) -> Self {
+        SipHasher24 {
+            v0: key0 ^ 0x736f_6d65_7073_6575,
+            v1: key1 ^ 0x646f_7261_6e64_6f6d,
+            v2: key0 ^ 0x6c79_6765_6e65_7261,
+            v3: key1 ^ 0x7465_6462_7974_6573,
+            tail: 0,
+            tail_len: 0,
+            len: 0,
+        }
+    }
+
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    /// Absorbs a word of the message, 2 rounds
+    fn compress(&mut self, word: u64) {
+        self.v3 ^= word;
+        self.round();
+        self.round();
+        self.v0 ^= word;
+    }
+}
+
+impl Hasher for SipHasher24 {
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.len += bytes.len() as u64;
+        // complete the pending word first
+        while self.tail_len > 0 && !bytes.is_empty() {
+            self.tail |= (bytes[0] as u64) << (8 * self.tail_len);
+            self.tail_len = (self.tail_len + 1) % 8;
+            bytes = &bytes[1..];
+            if self.tail_len == 0 {
+                self.compress(self.tail);
+                self.tail = 0;
+            }
+        }
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.compress(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        for (i, byte) in words.remainder().iter().enumerate() {
+            self.tail |= (*byte as u64) << (8 * i);
+        }
+        self.tail_len += words.remainder().len();
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16)
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32)
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64)
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128)
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64)
+    }
+
+    /// The length and the last bytes, then 4 rounds
+    fn finish(&self) -> u64 {
+        let mut state = self.clone();
+        let last = (self.len << 56) | self.tail;
+        state.compress(last);
+        state.v2 ^= 0xff;
+        (0..4).for_each(|_| state.round());
+        state.v0 ^ state.v1 ^ state.v2 ^ state.v3
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+
+    use super::SipHasher24;
+
+    /// The keys `00 01 .. 0f` of the reference implementation
+    fn reference_hasher() -> SipHasher24 {
+        SipHasher24::new_with_keys(0x0706_0504_0302_0100, 0x0f0e_0d0c_0b0a_0908)
+    }
+
+    #[test]
+    fn matches_the_reference_vectors() {
+        // the hash of the messages `00 01 .. len - 1`, from vectors.h of the reference implementation
+        let expected = [
+            (0, 0x726f_db47_dd0e_0e31),
+            (1, 0x74f8_39c5_93dc_67fd),
+            (7, 0xab02_00f5_8b01_d137),
+            (8, 0x93f5_f579_9a93_2462),
+            (15, 0xa129_ca61_49be_45e5),
+            (63, 0x958a_324c_eb06_4572),
+        ];
+        let message: Vec<u8> = (0..64).collect();
+        for (len, hash) in expected {
+            let mut hasher = reference_hasher();
+            hasher.write(&message[..len]);
+            assert_eq!(hasher.finish(), hash, "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn the_hash_does_not_depend_on_how_the_bytes_are_split() {
+        let message: Vec<u8> = (0..40).collect();
+        let mut whole = reference_hasher();
+        whole.write(&message);
+        for split in [1, 3, 8, 13] {
+            let mut hasher = reference_hasher();
+            message.chunks(split).for_each(|chunk| hasher.write(chunk));
+            assert_eq!(hasher.finish(), whole.finish());
+        }
+    }
+
+    #[test]
+    fn integers_are_written_little_endian_in_64_bits() {
+        let mut bytes = reference_hasher();
+        bytes.write(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        for write in [
+            |h: &mut SipHasher24| h.write_u64(1),
+            |h: &mut SipHasher24| h.write_usize(1),
+            |h: &mut SipHasher24| h.write_isize(1),
+        ] {
+            let mut hasher = reference_hasher();
+            write(&mut hasher);
+            assert_eq!(hasher.finish(), bytes.finish());
+        }
+    }
+}
@@ -0,0 +1,501 @@
+//! Bloom filter over a bit array.
+//! Every item sets `k` bits chosen by double hashing, an item is reported as present if all of its bits are set.
+//!
+//! [`BloomFilter::to_bytes`] encodes a filter as
+//! ```text
+//! magic      4 bytes  b"BLMF"
+//! version    u16      currently 1
+//! hash       u8       the hash of the items, 1 for SipHash-2-4
+//! bit count  u64
+//! hash count u32
+//! seed       u64
+//! bits       u64      ceil(bit count / 64) words, the bit `i` being the bit `i % 64` of the word `i / 64`
+//! checksum   u32      CRC-32 of all the preceding bytes
+//! ```
+//! All the integers are little endian.
+//!
+//! The items must hash the same on every platform, see the [module](super).
+//!
+//! Only [`BloomFilter`] can be combined and encoded, the counting and scalable filters can't.
+use std::{convert::TryInto, fmt::Display, hash::Hash};
+
+use super::{false_positive_rate, optimal_parameters, positions};
+use crate::intervals::segment_tree::snapshot::update_crc;
+
+const MAGIC: &[u8; 4] = b"BLMF";
+const VERSION: u16 = 1;
+/// The id of SipHash-2-4 in the header, the only hash so far
+const SIP_HASH_2_4: u8 = 1;
+/// The bytes before the bits
+const HEADER_LEN: usize = 4 + 2 + 1 + 8 + 4 + 8;
+
+/// The parameters deciding the bits set for an item, two filters can only be combined if they have the same
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parameters {
+    pub bit_count: u64,
+    pub hash_count: u32,
+    pub seed: u64,
+}
+
+/// Error while combining or decoding filters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BloomFilterError {
+    /// The filters don't set the same bits for the same item
+    IncompatibleParameters {
+        expected: Parameters,
+        found: Parameters,
+    },
+    /// The bytes ended before the filter
+    Truncated,
+    /// Not an encoded filter
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    /// The items were hashed with a hash this version doesn't know
+    UnsupportedHash(u8),
+    ChecksumMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    InvalidData(String),
+}
+
+impl Display for BloomFilterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl std::error::Error for BloomFilterError {}
+
+/// A Bloom filter of the items implementing [`Hash`].
+/// ```
+/// # use datastructures_in_rust::bloom_filter::standard_bloom_filter::BloomFilter;
+/// let mut filter = BloomFilter::new(1_000, 0.01);
+/// filter.insert("apple");
+/// assert!(filter.contains("apple"));
+/// assert!(!filter.contains("banana"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    bit_count: u64,
+    hash_count: u32,
+    /// Seeds the hashes, filters with different seeds set different bits for the same item
+    seed: u64,
+    /// The number of bits set
+    ones: u64,
+}
+
+impl BloomFilter {
+    /// Creates a filter sized for `expected_items` with the given false positive rate, see [`optimal_parameters`].
+    /// Panics if the rate is not within `(0, 1)`.
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        BloomFilter::new_with_seed(expected_items, false_positive_rate, 0)
+    }
+
+    /// Creates a filter sized for `expected_items` with the given false positive rate and seed
+    pub fn new_with_seed(expected_items: usize, false_positive_rate: f64, seed: u64) -> Self {
+        let (bit_count, hash_count) = optimal_parameters(expected_items, false_positive_rate);
+        BloomFilter::with_parameters(bit_count, hash_count, seed)
+    }
+
+    /// Creates a filter of `bit_count` bits setting `hash_count` bits per item.
+    /// Panics if either is 0.
+    pub fn with_parameters(bit_count: u64, hash_count: u32, seed: u64) -> Self {
+        assert!(
+            bit_count > 0 && hash_count > 0,
+            "a filter needs bits and hashes"
+        );
+        BloomFilter {
+            bits: vec![0; bit_count.div_ceil(64) as usize],
+            bit_count,
+            hash_count,
+            seed,
+            ones: 0,
+        }
+    }
+
+    /// The number of bits of the filter
+    pub fn bit_count(&self) -> u64 {
+        self.bit_count
+    }
+
+    /// The number of bits set per item
+    pub fn hash_count(&self) -> u32 {
+        self.hash_count
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn parameters(&self) -> Parameters {
+        Parameters {
+            bit_count: self.bit_count,
+            hash_count: self.hash_count,
+            seed: self.seed,
+        }
+    }
+
+    /// Inserts the item, returns false if it was (possibly) present already
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) -> bool {
+        let mut inserted = false;
+        for position in positions(item, self.seed, self.hash_count, self.bit_count) {
+            let (word, mask) = ((position / 64) as usize, 1 << (position % 64));
+            if self.bits[word] & mask == 0 {
+                self.bits[word] |= mask;
+                self.ones += 1;
+                inserted = true;
+            }
+        }
+        inserted
+    }
+
+    /// True if the item is possibly present, false if it was definitely never inserted
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        positions(item, self.seed, self.hash_count, self.bit_count)
+            .all(|position| self.bits[(position / 64) as usize] & (1 << (position % 64)) != 0)
+    }
+
+    /// The fraction of the bits that are set
+    pub fn fill_ratio(&self) -> f64 {
+        self.ones as f64 / self.bit_count as f64
+    }
+
+    /// The probability that an item never inserted is reported as present, given the bits set so far
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        false_positive_rate(self.fill_ratio(), self.hash_count)
+    }
+
+    /// The approximate number of distinct items inserted, `-m / k ln(1 - X / m)` for `X` bits set
+    pub fn estimated_len(&self) -> f64 {
+        let m = self.bit_count as f64;
+        -m / self.hash_count as f64 * (1.0 - self.ones as f64 / m).ln()
+    }
+
+    /// True if nothing was inserted
+    pub fn is_empty(&self) -> bool {
+        self.ones == 0
+    }
+
+    /// Removes every item
+    pub fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|word| *word = 0);
+        self.ones = 0;
+    }
+
+    /// Adds the items of the other filter, as if they had been inserted in this one.
+    /// Fails without changing anything if the filters have different [`Parameters`].
+    pub fn union(&mut self, other: &BloomFilter) -> Result<(), BloomFilterError> {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Keeps the items present in both filters.
+    /// The result may report more items than a filter of the common items alone: a bit set by different items
+    /// in the two filters stays set. Fails without changing anything if the filters have different [`Parameters`].
+    pub fn intersection(&mut self, other: &BloomFilter) -> Result<(), BloomFilterError> {
+        self.combine(other, |a, b| a & b)
+    }
+
+    fn combine(
+        &mut self,
+        other: &BloomFilter,
+        combine_fn: fn(u64, u64) -> u64,
+    ) -> Result<(), BloomFilterError> {
+        if self.parameters() != other.parameters() {
+            return Err(BloomFilterError::IncompatibleParameters {
+                expected: self.parameters(),
+                found: other.parameters(),
+            });
+        }
+        for (word, other) in self.bits.iter_mut().zip(other.bits.iter()) {
+            *word = combine_fn(*word, *other);
+        }
+        self.ones = self.bits.iter().map(|word| word.count_ones() as u64).sum();
+        Ok(())
+    }
+
+    /// Encodes the filter with its parameters, see the [module](self) for the format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 8 * self.bits.len() + 4);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.push(SIP_HASH_2_4);
+        bytes.extend_from_slice(&self.bit_count.to_le_bytes());
+        bytes.extend_from_slice(&self.hash_count.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        for word in &self.bits {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        let crc = update_crc(0, &bytes);
+        bytes.extend_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes a filter encoded by [`BloomFilter::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BloomFilterError> {
+        if bytes.len() < MAGIC.len() {
+            return Err(BloomFilterError::Truncated);
+        }
+        if &bytes[..MAGIC.len()] != MAGIC {
+            return Err(BloomFilterError::InvalidMagic);
+        }
+        if bytes.len() < HEADER_LEN + 4 {
+            return Err(BloomFilterError::Truncated);
+        }
+        let version = u16::from_le_bytes(bytes[4..6].try_into().unwrap());
+        if version != VERSION {
+            return Err(BloomFilterError::UnsupportedVersion(version));
+        }
+        if bytes[6] != SIP_HASH_2_4 {
+            return Err(BloomFilterError::UnsupportedHash(bytes[6]));
+        }
+        let bit_count = u64::from_le_bytes(bytes[7..15].try_into().unwrap());
+        let hash_count = u32::from_le_bytes(bytes[15..19].try_into().unwrap());
+        let seed = u64::from_le_bytes(bytes[19..27].try_into().unwrap());
+        if bit_count == 0 || hash_count == 0 {
+            return Err(BloomFilterError::InvalidData(format!(
+                "{} bits and {} hashes",
+                bit_count, hash_count
+            )));
+        }
+        let words = bit_count.div_ceil(64);
+        let len = words
+            .checked_mul(8)
+            .and_then(|len| len.checked_add((HEADER_LEN + 4) as u64));
+        match len {
+            Some(len) if len == bytes.len() as u64 => {}
+            Some(len) if len > bytes.len() as u64 => return Err(BloomFilterError::Truncated),
+            _ => {
+                return Err(BloomFilterError::InvalidData(format!(
+                    "{} bytes for {} bits",
+                    bytes.len(),
+                    bit_count
+                )))
+            }
+        }
+        let (body, checksum) = bytes.split_at(bytes.len() - 4);
+        let expected = u32::from_le_bytes(checksum.try_into().unwrap());
+        let actual = update_crc(0, body);
+        if expected != actual {
+            return Err(BloomFilterError::ChecksumMismatch { expected, actual });
+        }
+        let bits: Vec<u64> = body[HEADER_LEN..]
+            .chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        // the bits past the bit count are never set
+        if bit_count % 64 != 0 && bits[bits.len() - 1] >> (bit_count % 64) != 0 {
+            return Err(BloomFilterError::InvalidData(
+                "bits set past the bit count".to_string(),
+            ));
+        }
+        let ones = bits.iter().map(|word| word.count_ones() as u64).sum();
+        Ok(BloomFilter {
+            bits,
+            bit_count,
+            hash_count,
+            seed,
+            ones,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::{BloomFilter, BloomFilterError, Parameters};
+
+    #[test]
+    fn inserted_items_are_present() {
+        let mut filter = BloomFilter::new(1_000, 0.01);
+        assert!(filter.is_empty());
+        assert_eq!((filter.bit_count(), filter.hash_count()), (9_586, 7));
+        // an item whose bits were all set by the others looks present already
+        let inserted = (0..1_000u32).filter(|i| filter.insert(i)).count();
+        assert!(inserted > 980, "{}", inserted);
+        assert!(!filter.insert(&10u32));
+        assert!((0..1_000u32).all(|i| filter.contains(&i)));
+        let estimated = filter.estimated_len();
+        assert!((950.0..1_050.0).contains(&estimated), "{}", estimated);
+        filter.clear();
+        assert!(!filter.contains(&10u32));
+    }
+
+    #[test]
+    fn empirical_false_positive_rate_matches_the_target() {
+        for target in [0.1, 0.01, 0.001] {
+            let items = 10_000u64;
+            let mut filter = BloomFilter::new(items as usize, target);
+            for i in 0..items {
+                filter.insert(&i);
+            }
+            // the optimal filter is about half full once it holds the expected items
+            assert!(
+                (filter.fill_ratio() - 0.5).abs() < 0.05,
+                "{}",
+                filter.fill_ratio()
+            );
+            let estimated = filter.estimated_false_positive_rate();
+            assert!(
+                estimated < 1.2 * target && estimated > 0.8 * target,
+                "estimated {} for {}",
+                estimated,
+                target
+            );
+            let trials = 200_000u64;
+            let false_positives = (items..items + trials)
+                .filter(|i| filter.contains(i))
+                .count();
+            let rate = false_positives as f64 / trials as f64;
+            assert!(
+                rate < 1.25 * target && rate > 0.75 * target,
+                "measured {} for {}",
+                rate,
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn seeds_change_the_bits() {
+        let mut a = BloomFilter::new_with_seed(100, 0.01, 1);
+        let mut b = BloomFilter::new_with_seed(100, 0.01, 2);
+        a.insert("item");
+        b.insert("item");
+        assert_ne!(a, b);
+        assert!(a.contains("item") && b.contains("item"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_rates_panic() {
+        BloomFilter::new(100, 1.0);
+    }
+
+    #[test]
+    fn union_and_intersection_combine_the_items() {
+        let mut evens = BloomFilter::new(1_000, 0.01);
+        let mut small = BloomFilter::new(1_000, 0.01);
+        (0..1_000u32).step_by(2).for_each(|i| {
+            evens.insert(&i);
+        });
+        (0..500u32).for_each(|i| {
+            small.insert(&i);
+        });
+        let mut union = evens.clone();
+        union.union(&small).unwrap();
+        assert!((0..500u32)
+            .chain((500..1_000).step_by(2))
+            .all(|i| union.contains(&i)));
+        let mut direct = BloomFilter::new(1_000, 0.01);
+        (0..500u32).chain((500..1_000).step_by(2)).for_each(|i| {
+            direct.insert(&i);
+        });
+        // the union is exactly the filter of all the items
+        assert_eq!(union, direct);
+        let mut intersection = evens.clone();
+        intersection.intersection(&small).unwrap();
+        assert!((0..500u32).step_by(2).all(|i| intersection.contains(&i)));
+        let false_positives = (500..1_000u32)
+            .step_by(2)
+            .filter(|i| intersection.contains(i))
+            .count();
+        assert!(false_positives < 10, "{}", false_positives);
+        assert!(intersection.fill_ratio() < evens.fill_ratio());
+    }
+
+    #[test]
+    fn combining_incompatible_filters_fails() {
+        let mut filter = BloomFilter::new_with_seed(1_000, 0.01, 1);
+        filter.insert("a");
+        let copy = filter.clone();
+        for other in [
+            BloomFilter::new_with_seed(1_000, 0.01, 2),
+            BloomFilter::new_with_seed(2_000, 0.01, 1),
+            BloomFilter::with_parameters(9_586, 6, 1),
+        ] {
+            assert_eq!(
+                filter.union(&other),
+                Err(BloomFilterError::IncompatibleParameters {
+                    expected: filter.parameters(),
+                    found: other.parameters(),
+                })
+            );
+            assert!(filter.intersection(&other).is_err());
+            assert_eq!(filter, copy);
+        }
+        assert_eq!(
+            filter.parameters(),
+            Parameters {
+                bit_count: 9_586,
+                hash_count: 7,
+                seed: 1
+            }
+        );
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut filter = BloomFilter::new_with_seed(1_000, 0.01, 42);
+        (0..700u32).for_each(|i| {
+            filter.insert(&i);
+        });
+        let bytes = filter.to_bytes();
+        assert_eq!(bytes.len(), 27 + 8 * 9_586usize.div_ceil(64) + 4);
+        let decoded = BloomFilter::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, filter);
+        assert_eq!(decoded.fill_ratio(), filter.fill_ratio());
+        assert!((0..700u32).all(|i| decoded.contains(&i)));
+    }
+
+    /// The bits of an item are part of the format, they must not change between releases or platforms
+    #[test]
+    fn the_bits_of_items_are_pinned() {
+        let mut filter = BloomFilter::with_parameters(64, 3, 7);
+        filter.insert(&42u64);
+        filter.insert("apple");
+        filter.insert(&[1usize, 2, 3][..]);
+        let bytes = filter.to_bytes();
+        let bits = u64::from_le_bytes(bytes[27..35].try_into().unwrap());
+        assert_eq!(bits, 0x2100_4024_8810_0800, "{:#x}", bits);
+    }
+
+    #[test]
+    fn decoding_invalid_bytes_fails() {
+        let bytes = BloomFilter::new(100, 0.01).to_bytes();
+        assert_eq!(
+            BloomFilter::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BloomFilterError::Truncated)
+        );
+        assert_eq!(
+            BloomFilter::from_bytes(b"DSST"),
+            Err(BloomFilterError::InvalidMagic)
+        );
+        let mut version = bytes.clone();
+        version[4] = 2;
+        assert_eq!(
+            BloomFilter::from_bytes(&version),
+            Err(BloomFilterError::UnsupportedVersion(2))
+        );
+        let mut hash = bytes.clone();
+        hash[6] = 2;
+        assert_eq!(
+            BloomFilter::from_bytes(&hash),
+            Err(BloomFilterError::UnsupportedHash(2))
+        );
+        let mut flipped = bytes.clone();
+        flipped[30] ^= 1;
+        assert!(matches!(
+            BloomFilter::from_bytes(&flipped),
+            Err(BloomFilterError::ChecksumMismatch { .. })
+        ));
+        let mut longer = bytes;
+        longer.push(0);
+        assert!(matches!(
+            BloomFilter::from_bytes(&longer),
+            Err(BloomFilterError::InvalidData(_))
+        ));
+    }
+}
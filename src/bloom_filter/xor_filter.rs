@@ -0,0 +1,202 @@
+//! XOR filter, a static filter built once from a set of keys.
+//! Every key has three positions, one in each third of an array of fingerprints, chosen so that the xor of
+//! the three fingerprints is the fingerprint of the key. The array is `1.23 n + 32` long and is filled by peeling:
+//! a position used by a single key is set last, after the other keys are placed, and the positions are redrawn
+//! with a new seed in the rare case the keys can't be peeled. About `1.23 log2(1 / p)` bits per key,
+//! the false positive rate being `2^-bits` of the fingerprints.
+//! https://arxiv.org/abs/1912.08258
+use std::hash::Hash;
+
+use super::{hash, mix, ApproximateMembership, Fingerprint};
+
+/// A filter of a fixed set of keys implementing [`Hash`], `F` being the fingerprints (8 bits by default).
+/// ```
+/// # use datastructures_in_rust::bloom_filter::xor_filter::XorFilter;
+/// let filter: XorFilter = XorFilter::new(["apple", "pear"]);
+/// assert!(filter.contains("apple") && filter.contains("pear"));
+/// assert!(!filter.contains("banana"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct XorFilter<F: Fingerprint = u8> {
+    fingerprints: Vec<F>,
+    /// The length of each third of the fingerprints
+    block_length: usize,
+    /// Seeds the positions of the keys, the first seed whose positions could be peeled
+    seed: u64,
+    len: usize,
+}
+
+impl<F: Fingerprint> XorFilter<F> {
+    /// Builds the filter of the keys, the duplicates are ignored
+    pub fn new<I>(keys: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Hash,
+    {
+        let mut keys: Vec<u64> = keys.into_iter().map(|key| hash(&key, 0, 0)).collect();
+        keys.sort_unstable();
+        keys.dedup();
+        let block_length = ((1.23 * keys.len() as f64) as usize + 32).div_ceil(3);
+        let mut seed = 0;
+        loop {
+            seed = mix(seed);
+            let mut filter = XorFilter {
+                fingerprints: vec![F::default(); 3 * block_length],
+                block_length,
+                seed,
+                len: keys.len(),
+            };
+            if let Some(order) = filter.peel(&keys) {
+                // the keys peeled last are set first, their positions are not used by the keys peeled before
+                for (key, position) in order.into_iter().rev() {
+                    let [a, b, c] = filter.positions(key);
+                    let fingerprints = &filter.fingerprints;
+                    filter.fingerprints[position] = fingerprint::<F>(key)
+                        .xor(fingerprints[a])
+                        .xor(fingerprints[b])
+                        .xor(fingerprints[c]);
+                }
+                return filter;
+            }
+        }
+    }
+
+    /// The number of distinct keys
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of bits of the fingerprints
+    pub fn bit_count(&self) -> u64 {
+        self.fingerprints.len() as u64 * F::BITS
+    }
+
+    /// The probability that an item not present has the xor of its fingerprints equal to its fingerprint
+    pub fn false_positive_rate(&self) -> f64 {
+        0.5f64.powi(F::BITS as i32)
+    }
+
+    /// True if the item is possibly one of the keys, false if it is definitely not
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        let key = hash(item, 0, 0);
+        let [a, b, c] = self.positions(key);
+        fingerprint::<F>(key)
+            == self.fingerprints[a]
+                .xor(self.fingerprints[b])
+                .xor(self.fingerprints[c])
+    }
+
+    /// Peels the keys: repeatedly removes a key whose position is used by no other key.
+    /// Returns the keys with their positions in the order they were removed, or None if some keys can't be.
+    fn peel(&self, keys: &[u64]) -> Option<Vec<(u64, usize)>> {
+        // the number of keys using each position and the xor of those keys,
+        // the xor being the key itself when there is a single one
+        let mut counts = vec![0u32; self.fingerprints.len()];
+        let mut xors = vec![0u64; self.fingerprints.len()];
+        for key in keys {
+            for position in self.positions(*key) {
+                counts[position] += 1;
+                xors[position] ^= key;
+            }
+        }
+        let mut alone: Vec<usize> = (0..counts.len()).filter(|p| counts[*p] == 1).collect();
+        let mut order = Vec::with_capacity(keys.len());
+        while let Some(position) = alone.pop() {
+            // the key may have been removed through another position since
+            if counts[position] != 1 {
+                continue;
+            }
+            let key = xors[position];
+            order.push((key, position));
+            for other in self.positions(key) {
+                counts[other] -= 1;
+                xors[other] ^= key;
+                if counts[other] == 1 {
+                    alone.push(other);
+                }
+            }
+        }
+        (order.len() == keys.len()).then_some(order)
+    }
+
+    /// The three positions of the key, one in each third of the fingerprints
+    fn positions(&self, key: u64) -> [usize; 3] {
+        let hash = mix(key.wrapping_add(self.seed));
+        let reduce = |hash: u64| ((hash as u32 as u64 * self.block_length as u64) >> 32) as usize;
+        [
+            reduce(hash),
+            self.block_length + reduce(hash.rotate_left(21)),
+            2 * self.block_length + reduce(hash.rotate_left(42)),
+        ]
+    }
+}
+
+/// The fingerprint of the hash of a key
+fn fingerprint<F: Fingerprint>(key: u64) -> F {
+    F::from_hash(key ^ (key >> 32))
+}
+
+impl<T: Hash + ?Sized, F: Fingerprint> ApproximateMembership<T> for XorFilter<F> {
+    fn contains(&self, item: &T) -> bool {
+        XorFilter::contains(self, item)
+    }
+
+    fn bit_count(&self) -> u64 {
+        XorFilter::bit_count(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::XorFilter;
+
+    #[test]
+    fn keys_are_present() {
+        for n in [0u64, 1, 10, 1_000, 100_000] {
+            let filter: XorFilter = XorFilter::new(0..n);
+            assert_eq!(filter.len(), n as usize);
+            assert!((0..n).all(|i| filter.contains(&i)));
+        }
+        let words = vec!["a".to_string(), "b".to_string(), "a".to_string()];
+        let filter: XorFilter<u16> = XorFilter::new(&words);
+        assert_eq!(filter.len(), 2);
+        assert!(filter.contains("a") && filter.contains(&"b".to_string()));
+    }
+
+    #[test]
+    fn empirical_false_positive_rate_matches_the_fingerprints() {
+        let items = 20_000u64;
+        let trials = 500_000u64;
+        let small: XorFilter<u8> = XorFilter::new(0..items);
+        let large: XorFilter<u16> = XorFilter::new(0..items);
+        // 1.23 fingerprints per key
+        let bits_per_key = small.bit_count() as f64 / items as f64;
+        assert!((9.8..10.0).contains(&bits_per_key), "{}", bits_per_key);
+        for (false_positives, rate) in [
+            (
+                (items..items + trials)
+                    .filter(|i| small.contains(i))
+                    .count(),
+                small.false_positive_rate(),
+            ),
+            (
+                (items..items + trials)
+                    .filter(|i| large.contains(i))
+                    .count(),
+                large.false_positive_rate(),
+            ),
+        ] {
+            let measured = false_positives as f64 / trials as f64;
+            assert!(
+                measured < 1.5 * rate && measured > 0.5 * rate,
+                "measured {} for {}",
+                measured,
+                rate
+            );
+        }
+    }
+}
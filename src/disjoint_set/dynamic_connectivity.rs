@@ -0,0 +1,204 @@
+//! Offline dynamic connectivity: answers "are u and v connected" over a log of edge additions and removals.
+//! Every edge is alive during an interval of the queries, the interval is assigned to the nodes of a segment tree
+//! over the queries (laid out like an [`ArrayBasedSegmentTree`]) covering it.
+//! A depth first traversal of the tree unions the edges of each node on the way down and rolls them back on the way up,
+//! so at a leaf the [`RollbackDisjointSet`] holds exactly the edges alive at that query.
+//! O((M + Q) log Q log N) for M edges and Q queries.
+//!
+//! [`ArrayBasedSegmentTree`]: crate::intervals::segment_tree::array_based_segment_tree::ArrayBasedSegmentTree
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use super::rollback_disjoint_set::RollbackDisjointSet;
+use crate::intervals::segment_tree::split;
+
+/// An entry of the log of a graph over the vertices `0..n`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Adds an undirected edge, the same edge can be added several times
+    Add(usize, usize),
+    /// Removes one of the copies of an edge that was added before
+    Remove(usize, usize),
+    /// Asks whether the two vertices are connected
+    Connected(usize, usize),
+}
+
+/// Answers the [`Operation::Connected`] queries of the log in order, given the edges alive at the time of each query.
+/// Panics if an edge is removed more times than it was added, or if a vertex is not in `0..n`.
+/// ```
+/// # use datastructures_in_rust::disjoint_set::dynamic_connectivity::{connectivity, Operation::*};
+/// let log = [Add(0, 1), Add(1, 2), Connected(0, 2), Remove(1, 2), Connected(0, 2), Connected(1, 0)];
+/// assert_eq!(connectivity(3, &log), vec![true, false, true]);
+/// ```
+pub fn connectivity(n: usize, log: &[Operation]) -> Vec<bool> {
+    let mut queries = vec![];
+    // the edges with the interval of the queries they are alive at
+    let mut intervals: Vec<((usize, usize), RangeInclusive<usize>)> = vec![];
+    // the number of queries before each copy of the edges was added
+    let mut alive: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for operation in log {
+        match *operation {
+            Operation::Add(u, v) => alive.entry(edge(n, u, v)).or_default().push(queries.len()),
+            Operation::Remove(u, v) => {
+                let edge = edge(n, u, v);
+                let added = alive
+                    .get_mut(&edge)
+                    .and_then(|added| added.pop())
+                    .unwrap_or_else(|| panic!("removing the edge {:?} that was not added", edge));
+                if added < queries.len() {
+                    intervals.push((edge, added..=queries.len() - 1));
+                }
+            }
+            Operation::Connected(u, v) => queries.push(edge(n, u, v)),
+        }
+    }
+    if queries.is_empty() {
+        return vec![];
+    }
+    // the edges still alive at the end of the log
+    for (edge, added) in alive {
+        for added in added.into_iter().filter(|added| *added < queries.len()) {
+            intervals.push((edge, added..=queries.len() - 1));
+        }
+    }
+    let timeline = 0..=queries.len() - 1;
+    let mut edges = vec![vec![]; 4 * queries.len()];
+    for (edge, interval) in intervals {
+        assign(&mut edges, &interval, timeline.clone(), 0, edge);
+    }
+    let mut answers = vec![false; queries.len()];
+    let mut set = RollbackDisjointSet::new(n);
+    answer(&edges, &queries, timeline, 0, &mut set, &mut answers);
+    answers
+}
+
+/// The edge with its vertices in order.
+/// Panics if a vertex is not in `0..n`.
+fn edge(n: usize, u: usize, v: usize) -> (usize, usize) {
+    assert!(
+        u < n && v < n,
+        "({}, {}) is not an edge of {} vertices",
+        u,
+        v,
+        n
+    );
+    (u.min(v), u.max(v))
+}
+
+/// Adds the edge to the nodes covering the interval, the same nodes a range update of an
+/// [`ArrayBasedSegmentTree`](crate::intervals::segment_tree::array_based_segment_tree::ArrayBasedSegmentTree) stops at
+fn assign(
+    edges: &mut [Vec<(usize, usize)>],
+    interval: &RangeInclusive<usize>,
+    current: RangeInclusive<usize>,
+    index: usize,
+    edge: (usize, usize),
+) {
+    let (start, end) = (*current.start(), *current.end());
+    // no overlap
+    if *interval.start() > end || *interval.end() < start {
+        return;
+    }
+    if *interval.start() <= start && end <= *interval.end() {
+        edges[index].push(edge);
+        return;
+    }
+    let (left, right) = split(start, end);
+    assign(edges, interval, left, 2 * index + 1, edge);
+    assign(edges, interval, right, 2 * index + 2, edge);
+}
+
+/// Answers the queries of the current range, the set holds the edges of the ancestors
+fn answer(
+    edges: &[Vec<(usize, usize)>],
+    queries: &[(usize, usize)],
+    current: RangeInclusive<usize>,
+    index: usize,
+    set: &mut RollbackDisjointSet,
+    answers: &mut [bool],
+) {
+    let checkpoint = set.checkpoint();
+    for (u, v) in edges[index].iter() {
+        set.union(*u, *v);
+    }
+    let (start, end) = (*current.start(), *current.end());
+    if start == end {
+        let (u, v) = queries[start];
+        answers[start] = set.same_set(u, v);
+    } else {
+        let (left, right) = split(start, end);
+        answer(edges, queries, left, 2 * index + 1, set, answers);
+        answer(edges, queries, right, 2 * index + 2, set, answers);
+    }
+    set.rollback(checkpoint);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+
+    use super::{connectivity, Operation};
+    use crate::disjoint_set::indexed_disjoint_set::DisjointSet;
+
+    #[test]
+    fn connectivity_works() {
+        use Operation::*;
+        let log = [
+            Connected(0, 0),
+            Add(0, 1),
+            Add(0, 1),
+            Add(2, 3),
+            Connected(0, 1),
+            Remove(1, 0),
+            Connected(0, 1),
+            Add(1, 2),
+            Connected(0, 3),
+            Remove(0, 1),
+            Connected(0, 3),
+            Connected(1, 3),
+        ];
+        assert_eq!(
+            connectivity(4, &log),
+            vec![true, true, true, true, false, true]
+        );
+        assert_eq!(connectivity(4, &[Add(0, 1), Remove(0, 1)]), vec![]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn removing_an_edge_that_was_not_added_panics() {
+        connectivity(3, &[Operation::Add(0, 1), Operation::Remove(1, 2)]);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = thread_rng();
+        let n = 12;
+        for _ in 0..20 {
+            let mut log = vec![];
+            let mut edges: Vec<(usize, usize)> = vec![];
+            let mut expected = vec![];
+            for _ in 0..300 {
+                let (u, v) = (rng.gen_range(0..n), rng.gen_range(0..n));
+                match rng.gen_range(0..3) {
+                    0 => {
+                        log.push(Operation::Add(u, v));
+                        edges.push((u, v));
+                    }
+                    1 if !edges.is_empty() => {
+                        let (u, v) = edges.swap_remove(rng.gen_range(0..edges.len()));
+                        log.push(Operation::Remove(v, u));
+                    }
+                    _ => {
+                        log.push(Operation::Connected(u, v));
+                        let mut set = DisjointSet::new(n);
+                        edges.iter().for_each(|(a, b)| {
+                            set.union(*a, *b);
+                        });
+                        expected.push(set.same_set(u, v));
+                    }
+                }
+            }
+            assert_eq!(connectivity(n, &log), expected);
+        }
+    }
+}
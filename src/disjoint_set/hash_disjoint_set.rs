@@ -0,0 +1,207 @@
+//! Disjoint set keyed by arbitrary `Hash + Eq` values, growing as new keys show up.
+//! The keys are mapped to dense indices of a [`DisjointSet`], so the operations keep its O(α(n)) bounds
+//! plus a hash lookup.
+use std::{collections::HashMap, hash::Hash};
+
+use super::indexed_disjoint_set::DisjointSet;
+
+/// A partition of the keys inserted so far into components, every key starting in its own component.
+/// ```
+/// # use datastructures_in_rust::disjoint_set::hash_disjoint_set::HashDisjointSet;
+/// let mut set = HashDisjointSet::new();
+/// set.union("alice", "bob");
+/// set.union("carol", "dave");
+/// set.insert("eve");
+/// assert!(set.same_set(&"alice", &"bob"));
+/// assert!(!set.same_set(&"bob", &"carol"));
+/// assert_eq!(set.size(&"dave"), Some(2));
+/// assert_eq!(set.count(), 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct HashDisjointSet<K: Hash + Eq + Clone> {
+    /// The index of each key
+    indices: HashMap<K, usize>,
+    /// The key of each index
+    keys: Vec<K>,
+    set: DisjointSet,
+}
+
+impl<K: Hash + Eq + Clone> Default for HashDisjointSet<K> {
+    fn default() -> Self {
+        HashDisjointSet::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone> HashDisjointSet<K> {
+    /// Creates an empty instance
+    pub fn new() -> Self {
+        HashDisjointSet {
+            indices: HashMap::new(),
+            keys: vec![],
+            set: DisjointSet::default(),
+        }
+    }
+
+    /// The number of keys
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// True if the key was inserted
+    pub fn contains(&self, key: &K) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    /// Inserts the key in a component of its own, returns false if it was already present
+    pub fn insert(&mut self, key: K) -> bool {
+        if self.contains(&key) {
+            return false;
+        }
+        self.index_of(key);
+        true
+    }
+
+    /// Returns the representative of the component of the key, None if the key was not inserted
+    pub fn find(&mut self, key: &K) -> Option<&K> {
+        let index = *self.indices.get(key)?;
+        let root = self.set.find(index);
+        Some(&self.keys[root])
+    }
+
+    /// Merges the components of the two keys, inserting the keys that were not present.
+    /// Returns false if they were already in the same component.
+    pub fn union(&mut self, a: K, b: K) -> bool {
+        let (a, b) = (self.index_of(a), self.index_of(b));
+        self.set.union(a, b)
+    }
+
+    /// True if the two keys are present and in the same component
+    pub fn same_set(&mut self, a: &K, b: &K) -> bool {
+        match (self.indices.get(a), self.indices.get(b)) {
+            (Some(a), Some(b)) => self.set.same_set(*a, *b),
+            _ => false,
+        }
+    }
+
+    /// The number of keys in the component of the key, None if the key was not inserted
+    pub fn size(&mut self, key: &K) -> Option<usize> {
+        let index = *self.indices.get(key)?;
+        Some(self.set.size(index))
+    }
+
+    /// The number of components
+    pub fn count(&self) -> usize {
+        self.set.count()
+    }
+
+    /// The keys in the component of the key, starting with it. Empty if the key was not inserted.
+    pub fn members(&self, key: &K) -> impl Iterator<Item = &K> + '_ {
+        let index = self.indices.get(key).copied();
+        index
+            .into_iter()
+            .flat_map(move |index| self.set.members(index))
+            .map(move |index| &self.keys[index])
+    }
+
+    /// The keys of each component, starting with the representative
+    pub fn components(&self) -> impl Iterator<Item = Vec<&K>> + '_ {
+        self.set
+            .components()
+            .map(move |component| component.into_iter().map(|i| &self.keys[i]).collect())
+    }
+
+    /// The index of the key, inserting it if needed
+    fn index_of(&mut self, key: K) -> usize {
+        if let Some(index) = self.indices.get(&key) {
+            return *index;
+        }
+        let index = self.set.add();
+        self.keys.push(key.clone());
+        self.indices.insert(key, index);
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HashDisjointSet;
+
+    #[test]
+    fn union_and_find_work() {
+        let mut set: HashDisjointSet<String> = HashDisjointSet::new();
+        assert!(set.is_empty());
+        assert_eq!(set.find(&"a".to_string()), None);
+        assert!(set.insert("a".to_string()));
+        assert!(!set.insert("a".to_string()));
+        assert_eq!(set.find(&"a".to_string()), Some(&"a".to_string()));
+        assert!(set.union("b".to_string(), "c".to_string()));
+        assert!(set.union("c".to_string(), "a".to_string()));
+        assert!(!set.union("a".to_string(), "b".to_string()));
+        assert!(!set.union("d".to_string(), "d".to_string()));
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.count(), 2);
+        assert!(set.same_set(&"a".to_string(), &"b".to_string()));
+        assert!(!set.same_set(&"a".to_string(), &"d".to_string()));
+        assert!(!set.same_set(&"a".to_string(), &"z".to_string()));
+        assert_eq!(set.size(&"c".to_string()), Some(3));
+        assert_eq!(set.size(&"z".to_string()), None);
+        let root = set.find(&"a".to_string()).cloned();
+        assert_eq!(root, set.find(&"b".to_string()).cloned());
+        let mut members: Vec<&String> = set.members(&"b".to_string()).collect();
+        members.sort();
+        assert_eq!(members, vec!["a", "b", "c"]);
+        assert_eq!(set.members(&"z".to_string()).count(), 0);
+        let mut components: Vec<Vec<&String>> = set
+            .components()
+            .map(|mut component| {
+                component.sort();
+                component
+            })
+            .collect();
+        components.sort();
+        assert_eq!(components, vec![vec!["a", "b", "c"], vec!["d"]]);
+    }
+
+    #[test]
+    fn accounts_merge_works() {
+        // LeetCode 721, the accounts sharing an email belong to the same person
+        let accounts = [
+            vec!["John", "johnsmith@mail.com", "john_newyork@mail.com"],
+            vec!["John", "johnsmith@mail.com", "john00@mail.com"],
+            vec!["Mary", "mary@mail.com"],
+            vec!["John", "johnnybravo@mail.com"],
+        ];
+        let mut set = HashDisjointSet::new();
+        for account in accounts.iter() {
+            set.insert(account[1]);
+            for email in account[2..].iter() {
+                set.union(account[1], *email);
+            }
+        }
+        let mut merged: Vec<Vec<&str>> = set
+            .components()
+            .map(|component| {
+                let mut emails: Vec<&str> = component.into_iter().copied().collect();
+                emails.sort_unstable();
+                emails
+            })
+            .collect();
+        merged.sort();
+        assert_eq!(
+            merged,
+            vec![
+                vec![
+                    "john00@mail.com",
+                    "john_newyork@mail.com",
+                    "johnsmith@mail.com"
+                ],
+                vec!["johnnybravo@mail.com"],
+                vec!["mary@mail.com"],
+            ]
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Disjoint set over the indices `0..n`, with path compression and union by size.
+//! Together they make `find` and `union` O(α(n)) amortized, α being the inverse Ackermann function.
+//! https://cp-algorithms.com/data_structures/disjoint_set_union.html
+
+/// A partition of the indices `0..n` into components, every index starting in its own component.
+/// ```
+/// # use datastructures_in_rust::disjoint_set::indexed_disjoint_set::DisjointSet;
+/// let mut set = DisjointSet::new(5);
+/// set.union(0, 1);
+/// set.union(3, 4);
+/// set.union(1, 4);
+/// assert!(set.same_set(0, 3));
+/// assert_eq!(set.size(4), 4);
+/// assert_eq!(set.count(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DisjointSet {
+    /// The parent of each index, the roots are their own parents
+    parent: Vec<usize>,
+    /// The size of the component, only meaningful for the roots
+    size: Vec<usize>,
+    /// The members of each component form a cycle, `next` is the following member
+    next: Vec<usize>,
+    /// The number of components
+    count: usize,
+}
+
+impl DisjointSet {
+    /// Creates an instance where each of the indices `0..n` is a component of its own
+    pub fn new(n: usize) -> Self {
+        DisjointSet {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+            next: (0..n).collect(),
+            count: n,
+        }
+    }
+
+    /// The number of indices
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Adds the next index in a component of its own, returns the index
+    pub fn add(&mut self) -> usize {
+        let index = self.len();
+        self.parent.push(index);
+        self.size.push(1);
+        self.next.push(index);
+        self.count += 1;
+        index
+    }
+
+    /// Returns the representative of the component of the index.
+    /// Every index on the way to it is linked directly to it (path compression).
+    /// Panics if the index is out of bounds.
+    pub fn find(&mut self, index: usize) -> usize {
+        let mut root = index;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut current = index;
+        while self.parent[current] != root {
+            current = std::mem::replace(&mut self.parent[current], root);
+        }
+        root
+    }
+
+    /// Merges the components of the two indices, the smaller one is linked under the larger one (union by size).
+    /// Returns false if they were already in the same component.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        // splicing the two cycles of members into one
+        self.next.swap(a, b);
+        self.count -= 1;
+        true
+    }
+
+    /// True if the two indices are in the same component
+    pub fn same_set(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// The number of indices in the component of the index
+    pub fn size(&mut self, index: usize) -> usize {
+        let root = self.find(index);
+        self.size[root]
+    }
+
+    /// The number of components
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The indices in the component of the index, starting with it. O(size of the component).
+    pub fn members(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut current = Some(index);
+        std::iter::from_fn(move || {
+            let member = current?;
+            let next = self.next[member];
+            current = if next == index { None } else { Some(next) };
+            Some(member)
+        })
+    }
+
+    /// The indices of each component, starting with the representative. O(n).
+    pub fn components(&self) -> impl Iterator<Item = Vec<usize>> + '_ {
+        (0..self.len())
+            .filter(move |index| self.parent[*index] == *index)
+            .map(move |root| self.members(root).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+
+    use super::DisjointSet;
+
+    /// The components as sorted lists of indices
+    fn sorted_components(set: &DisjointSet) -> Vec<Vec<usize>> {
+        let mut components: Vec<Vec<usize>> = set
+            .components()
+            .map(|mut component| {
+                component.sort_unstable();
+                component
+            })
+            .collect();
+        components.sort();
+        components
+    }
+
+    #[test]
+    fn union_and_find_work() {
+        let mut set = DisjointSet::new(6);
+        assert_eq!(set.count(), 6);
+        assert!(set.union(0, 1));
+        assert!(set.union(2, 3));
+        assert!(set.union(1, 3));
+        assert!(!set.union(0, 2));
+        assert!(set.same_set(0, 3));
+        assert!(!set.same_set(0, 4));
+        assert_eq!(set.size(2), 4);
+        assert_eq!(set.size(5), 1);
+        assert_eq!(set.count(), 3);
+        assert_eq!(
+            sorted_components(&set),
+            vec![vec![0, 1, 2, 3], vec![4], vec![5]]
+        );
+        assert_eq!(set.members(5).collect::<Vec<_>>(), vec![5]);
+        assert_eq!(set.members(1).next(), Some(1));
+        let index = set.add();
+        assert_eq!(index, 6);
+        set.union(6, 5);
+        assert_eq!(set.len(), 7);
+        assert_eq!(set.count(), 3);
+        assert_eq!(set.size(5), 2);
+    }
+
+    #[test]
+    fn empty_set_works() {
+        let set = DisjointSet::new(0);
+        assert!(set.is_empty());
+        assert_eq!(set.count(), 0);
+        assert_eq!(set.components().count(), 0);
+    }
+
+    #[test]
+    fn long_chains_are_compressed() {
+        let n = 1_000_000;
+        let mut set = DisjointSet::new(n);
+        for i in 1..n {
+            set.union(i - 1, i);
+        }
+        assert_eq!(set.count(), 1);
+        assert_eq!(set.size(0), n);
+        let root = set.find(n - 1);
+        assert!((0..n).all(|i| set.find(i) == root));
+        assert!((0..n).all(|i| set.parent[i] == root));
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = thread_rng();
+        let n = 50;
+        let mut set = DisjointSet::new(n);
+        // the label of the component of each index
+        let mut labels: Vec<usize> = (0..n).collect();
+        for _ in 0..200 {
+            let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            let (label_a, label_b) = (labels[a], labels[b]);
+            assert_eq!(set.union(a, b), label_a != label_b);
+            labels
+                .iter_mut()
+                .filter(|label| **label == label_b)
+                .for_each(|label| *label = label_a);
+            let c = rng.gen_range(0..n);
+            assert_eq!(set.same_set(a, c), labels[a] == labels[c]);
+            assert_eq!(
+                set.size(c),
+                labels.iter().filter(|label| **label == labels[c]).count()
+            );
+            let mut expected: Vec<Vec<usize>> = (0..n)
+                .filter(|label| labels.contains(label))
+                .map(|label| (0..n).filter(|i| labels[*i] == label).collect())
+                .collect();
+            expected.sort();
+            assert_eq!(set.count(), expected.len());
+            assert_eq!(sorted_components(&set), expected);
+        }
+    }
+}
@@ -0,0 +1,9 @@
+//! Disjoint set (union-find) module
+//! Contains two implementations - 1) over the dense indices `0..n` and 2) a growable one keyed by hashable values,
+//! along with a weighted variant keeping the value of each index relative to its component and a variant whose unions
+//! can be rolled back, used by the offline dynamic connectivity solver
+pub mod dynamic_connectivity;
+pub mod hash_disjoint_set;
+pub mod indexed_disjoint_set;
+pub mod rollback_disjoint_set;
+pub mod weighted_disjoint_set;
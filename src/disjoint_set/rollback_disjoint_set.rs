@@ -0,0 +1,323 @@
+//! Disjoint set whose unions can be undone, most recent first.
+//! Union by rank without path compression keeps every tree O(log n) deep, so `find` is O(log n)
+//! and undoing a union only has to unlink a root.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The id of the next set, so that a checkpoint is only ever valid for the set it came from
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A partition of the indices `0..n` into components whose unions can be rolled back.
+/// ```
+/// # use datastructures_in_rust::disjoint_set::rollback_disjoint_set::RollbackDisjointSet;
+/// let mut set = RollbackDisjointSet::new(4);
+/// set.union(0, 1);
+/// let checkpoint = set.checkpoint();
+/// set.union(1, 2);
+/// set.union(2, 3);
+/// assert!(set.same_set(0, 3));
+/// set.rollback(checkpoint);
+/// assert!(!set.same_set(0, 2));
+/// assert!(set.same_set(0, 1));
+/// ```
+#[derive(Debug)]
+pub struct RollbackDisjointSet {
+    id: u64,
+    /// The parent of each index, the roots are their own parents
+    parent: Vec<usize>,
+    /// An upper bound of the depth of the tree, only meaningful for the roots
+    rank: Vec<u8>,
+    /// The size of the component, only meaningful for the roots
+    size: Vec<usize>,
+    /// The unions that merged two components, most recent last
+    history: Vec<Union>,
+    /// The number of unions that merged two components so far, including the undone ones
+    union_count: u64,
+    /// The number of components
+    count: usize,
+}
+
+/// A union to undo
+#[derive(Debug, Clone, Copy)]
+struct Union {
+    /// The root linked under the other one
+    child: usize,
+    /// True if the rank of the new root grew
+    rank_grew: bool,
+    /// The number of unions before this one, tells it apart from an undone union it replaced
+    sequence: u64,
+}
+
+/// A state of a [`RollbackDisjointSet`] to roll back to, see [`RollbackDisjointSet::checkpoint`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    set: u64,
+    /// The number of unions that could be undone when the checkpoint was taken
+    position: usize,
+    /// The sequence of the most recent of these unions
+    last: Option<u64>,
+}
+
+/// The clone is a set of its own: the checkpoints of the original are not valid for it, as the two histories
+/// diverge with the next union
+impl Clone for RollbackDisjointSet {
+    fn clone(&self) -> Self {
+        RollbackDisjointSet {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            parent: self.parent.clone(),
+            rank: self.rank.clone(),
+            size: self.size.clone(),
+            history: self.history.clone(),
+            union_count: self.union_count,
+            count: self.count,
+        }
+    }
+}
+
+impl Default for RollbackDisjointSet {
+    fn default() -> Self {
+        RollbackDisjointSet::new(0)
+    }
+}
+
+impl RollbackDisjointSet {
+    /// Creates an instance where each of the indices `0..n` is a component of its own
+    pub fn new(n: usize) -> Self {
+        RollbackDisjointSet {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            parent: (0..n).collect(),
+            rank: vec![0; n],
+            size: vec![1; n],
+            history: vec![],
+            union_count: 0,
+            count: n,
+        }
+    }
+
+    /// The number of indices
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Returns the representative of the component of the index, O(log n).
+    /// Panics if the index is out of bounds.
+    pub fn find(&self, index: usize) -> usize {
+        let mut root = index;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        root
+    }
+
+    /// Merges the components of the two indices, the root of lower rank is linked under the other one.
+    /// Returns false (and records nothing to undo) if they were already in the same component.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.rank[a] < self.rank[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        let rank_grew = self.rank[a] == self.rank[b];
+        if rank_grew {
+            self.rank[a] += 1;
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        self.count -= 1;
+        self.history.push(Union {
+            child: b,
+            rank_grew,
+            sequence: self.union_count,
+        });
+        self.union_count += 1;
+        true
+    }
+
+    /// Undoes the most recent union that merged two components, returns false if there is none
+    pub fn undo(&mut self) -> bool {
+        let Union {
+            child, rank_grew, ..
+        } = match self.history.pop() {
+            Some(union) => union,
+            None => return false,
+        };
+        let root = self.parent[child];
+        self.parent[child] = child;
+        self.size[root] -= self.size[child];
+        if rank_grew {
+            self.rank[root] -= 1;
+        }
+        self.count += 1;
+        true
+    }
+
+    /// The current state, pass it to [`RollbackDisjointSet::rollback`] to return to it
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            set: self.id,
+            position: self.history.len(),
+            last: self.history.last().map(|union| union.sequence),
+        }
+    }
+
+    /// Undoes the unions since the checkpoint was taken, it can be rolled back to again.
+    /// Panics if the unions of the checkpoint were already undone (even if newer unions took their place),
+    /// or if the checkpoint is of another set.
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        let Checkpoint {
+            set,
+            position,
+            last,
+        } = checkpoint;
+        assert!(
+            set == self.id
+                && position <= self.history.len()
+                && last == position.checked_sub(1).map(|i| self.history[i].sequence),
+            "the checkpoint {:?} was rolled back past",
+            checkpoint
+        );
+        while self.history.len() > position {
+            self.undo();
+        }
+    }
+
+    /// True if the two indices are in the same component
+    pub fn same_set(&self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// The number of indices in the component of the index
+    pub fn size(&self, index: usize) -> usize {
+        self.size[self.find(index)]
+    }
+
+    /// The number of components
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+
+    use super::{Checkpoint, RollbackDisjointSet};
+
+    #[test]
+    fn undo_restores_the_components() {
+        let mut set = RollbackDisjointSet::new(5);
+        assert!(!set.undo());
+        assert!(set.union(0, 1));
+        assert!(set.union(2, 3));
+        assert!(!set.union(1, 0));
+        let checkpoint = set.checkpoint();
+        assert!(set.union(1, 3));
+        assert!(set.union(4, 0));
+        assert_eq!(set.count(), 1);
+        assert_eq!(set.size(2), 5);
+        assert!(set.undo());
+        assert_eq!(set.size(2), 4);
+        assert!(!set.same_set(4, 0));
+        set.rollback(checkpoint);
+        assert_eq!(set.count(), 3);
+        assert!(set.same_set(0, 1));
+        assert!(!set.same_set(1, 2));
+        assert_eq!(set.size(3), 2);
+        while set.undo() {}
+        assert_eq!(set.count(), 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rolling_back_past_the_checkpoint_panics() {
+        let mut set = RollbackDisjointSet::new(3);
+        let start = set.checkpoint();
+        set.union(0, 1);
+        let checkpoint = set.checkpoint();
+        set.rollback(start);
+        set.rollback(checkpoint);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rolling_back_to_a_replaced_checkpoint_panics() {
+        let mut set = RollbackDisjointSet::new(3);
+        let start = set.checkpoint();
+        set.union(0, 1);
+        let checkpoint = set.checkpoint();
+        set.rollback(start);
+        // as many unions as when the checkpoint was taken, but not the same ones
+        set.union(1, 2);
+        set.rollback(checkpoint);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rolling_back_to_the_checkpoint_of_another_set_panics() {
+        let mut set = RollbackDisjointSet::new(3);
+        let other = RollbackDisjointSet::new(3);
+        set.union(0, 1);
+        set.rollback(other.checkpoint());
+    }
+
+    #[test]
+    #[should_panic]
+    fn rolling_back_a_clone_to_a_checkpoint_of_the_original_panics() {
+        let mut set = RollbackDisjointSet::new(4);
+        let checkpoint = set.checkpoint();
+        let mut clone = set.clone();
+        // the same number of unions on both sides, but different ones
+        set.union(0, 1);
+        clone.union(2, 3);
+        clone.rollback(checkpoint);
+    }
+
+    #[test]
+    fn checkpoints_can_be_rolled_back_to_again() {
+        let mut set = RollbackDisjointSet::new(4);
+        set.union(0, 1);
+        let checkpoint = set.checkpoint();
+        set.union(2, 3);
+        set.rollback(checkpoint);
+        set.union(1, 2);
+        set.rollback(checkpoint);
+        assert_eq!(set.count(), 3);
+        assert!(set.same_set(0, 1));
+    }
+
+    #[test]
+    fn rollback_matches_the_recorded_states() {
+        let mut rng = thread_rng();
+        let n = 40;
+        let mut set = RollbackDisjointSet::new(n);
+        // the representative of every index at each checkpoint
+        let mut states: Vec<(Checkpoint, Vec<usize>, usize)> = vec![];
+        for _ in 0..50 {
+            for _ in 0..rng.gen_range(0..5) {
+                set.union(rng.gen_range(0..n), rng.gen_range(0..n));
+            }
+            let roots = (0..n).map(|i| set.find(i)).collect();
+            states.push((set.checkpoint(), roots, set.count()));
+            // the trees stay shallow without path compression
+            assert!((0..n).all(|i| {
+                let mut depth = 0;
+                let mut current = i;
+                while set.parent[current] != current {
+                    current = set.parent[current];
+                    depth += 1;
+                }
+                depth <= 6
+            }));
+        }
+        while let Some((checkpoint, roots, count)) = states.pop() {
+            set.rollback(checkpoint);
+            assert_eq!((0..n).map(|i| set.find(i)).collect::<Vec<_>>(), roots);
+            assert_eq!(set.count(), count);
+        }
+    }
+}
@@ -0,0 +1,313 @@
+//! Disjoint set where every index also has a value relative to the other indices of its component,
+//! e.g. offsets, parities or ratios (LeetCode 399 "evaluate division").
+//! The relative values form a [`Group`], each index stores its value relative to its parent and
+//! `find` folds the values along the path while compressing it. The operations are O(α(n)) amortized.
+//! https://cp-algorithms.com/data_structures/disjoint_set_union.html
+use std::fmt::{Debug, Display};
+
+use crate::intervals::ops::Group;
+
+/// The error of a union contradicting the relative value already known between the two indices
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contradiction<G> {
+    /// The relative value following from the earlier unions
+    pub known: G,
+    /// The relative value of the rejected union
+    pub requested: G,
+}
+
+impl<G: Debug> Display for Contradiction<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl<G: Debug> std::error::Error for Contradiction<G> {}
+
+/// A partition of the indices `0..n` into components, along with the value of each index relative
+/// to the others of its component. `diff(a, b)` is the value `w` such that `value(b) = value(a).combine(w)`.
+/// ```
+/// # use datastructures_in_rust::disjoint_set::weighted_disjoint_set::WeightedDisjointSet;
+/// # use datastructures_in_rust::intervals::ops::Sum;
+/// let mut set = WeightedDisjointSet::new(3);
+/// // 1 is 5 more than 0, 2 is 3 less than 1
+/// assert_eq!(set.union(0, 1, Sum(5)), Ok(true));
+/// assert_eq!(set.union(1, 2, Sum(-3)), Ok(true));
+/// assert_eq!(set.diff(0, 2), Some(Sum(2)));
+/// assert_eq!(set.diff(2, 0), Some(Sum(-2)));
+/// assert!(set.union(0, 2, Sum(4)).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct WeightedDisjointSet<G: Group> {
+    /// The parent of each index, the roots are their own parents
+    parent: Vec<usize>,
+    /// The size of the component, only meaningful for the roots
+    size: Vec<usize>,
+    /// The value of each index relative to its parent, `value(index) = value(parent).combine(weight[index])`
+    weight: Vec<G>,
+    /// The number of components
+    count: usize,
+}
+
+impl<G: Group + PartialEq> WeightedDisjointSet<G> {
+    /// Creates an instance where each of the indices `0..n` is a component of its own
+    pub fn new(n: usize) -> Self {
+        WeightedDisjointSet {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+            weight: vec![G::identity(); n],
+            count: n,
+        }
+    }
+
+    /// The number of indices
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Adds the next index in a component of its own, returns the index
+    pub fn add(&mut self) -> usize {
+        let index = self.len();
+        self.parent.push(index);
+        self.size.push(1);
+        self.weight.push(G::identity());
+        self.count += 1;
+        index
+    }
+
+    /// Returns the representative of the component of the index.
+    /// Panics if the index is out of bounds.
+    pub fn find(&mut self, index: usize) -> usize {
+        self.find_with_weight(index).0
+    }
+
+    /// Returns the representative of the component of the index along with the value of the index relative to it.
+    /// Every index on the way is linked directly to the representative (path compression).
+    fn find_with_weight(&mut self, index: usize) -> (usize, G) {
+        let mut path = vec![];
+        let mut root = index;
+        while self.parent[root] != root {
+            path.push(root);
+            root = self.parent[root];
+        }
+        // from the representative down, so that the parent of each index is already linked to it
+        for &current in path.iter().rev() {
+            let parent = self.parent[current];
+            if parent != root {
+                self.weight[current] = self.weight[parent]
+                    .clone()
+                    .combine(self.weight[current].clone());
+                self.parent[current] = root;
+            }
+        }
+        let weight = match index == root {
+            true => G::identity(),
+            false => self.weight[index].clone(),
+        };
+        (root, weight)
+    }
+
+    /// Records that `value(b) = value(a).combine(weight)`, merging the components of the two indices.
+    /// Returns false if they were already in the same component with the same relative value,
+    /// and an error without changing anything if the relative value contradicts the known one.
+    /// The values are compared with [`Group::approx_eq`], so floating point ratios that only differ by
+    /// their rounding errors are consistent.
+    pub fn union(&mut self, a: usize, b: usize, weight: G) -> Result<bool, Contradiction<G>> {
+        let (root_a, weight_a) = self.find_with_weight(a);
+        let (root_b, weight_b) = self.find_with_weight(b);
+        if root_a == root_b {
+            let known = weight_a.inverse().combine(weight_b);
+            return match known.approx_eq(&weight) {
+                true => Ok(false),
+                false => Err(Contradiction {
+                    known,
+                    requested: weight,
+                }),
+            };
+        }
+        // value(root_b) = value(root_a).combine(link)
+        let link = weight_a.combine(weight).combine(weight_b.inverse());
+        if self.size[root_a] >= self.size[root_b] {
+            self.parent[root_b] = root_a;
+            self.weight[root_b] = link;
+            self.size[root_a] += self.size[root_b];
+        } else {
+            self.parent[root_a] = root_b;
+            self.weight[root_a] = link.inverse();
+            self.size[root_b] += self.size[root_a];
+        }
+        self.count -= 1;
+        Ok(true)
+    }
+
+    /// The value `w` such that `value(b) = value(a).combine(w)`, None if the indices are in different components
+    pub fn diff(&mut self, a: usize, b: usize) -> Option<G> {
+        let (root_a, weight_a) = self.find_with_weight(a);
+        let (root_b, weight_b) = self.find_with_weight(b);
+        match root_a == root_b {
+            true => Some(weight_a.inverse().combine(weight_b)),
+            false => None,
+        }
+    }
+
+    /// True if the two indices are in the same component
+    pub fn same_set(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// The number of indices in the component of the index
+    pub fn size(&mut self, index: usize) -> usize {
+        let root = self.find(index);
+        self.size[root]
+    }
+
+    /// The number of components
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+
+    use super::{Contradiction, WeightedDisjointSet};
+    use crate::intervals::ops::{Group, Monoid, Product, Sum, Xor};
+
+    /// The permutations of 3 elements, a non commutative group
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Permutation([usize; 3]);
+
+    impl Monoid for Permutation {
+        fn identity() -> Self {
+            Permutation([0, 1, 2])
+        }
+
+        /// Applies self then other
+        fn combine(self, other: Self) -> Self {
+            Permutation([other.0[self.0[0]], other.0[self.0[1]], other.0[self.0[2]]])
+        }
+    }
+
+    impl Group for Permutation {
+        fn inverse(self) -> Self {
+            let mut inverse = [0; 3];
+            for (i, p) in self.0.iter().enumerate() {
+                inverse[*p] = i;
+            }
+            Permutation(inverse)
+        }
+    }
+
+    #[test]
+    fn offsets_work() {
+        let mut set = WeightedDisjointSet::new(5);
+        assert_eq!(set.diff(0, 0), Some(Sum(0)));
+        assert_eq!(set.diff(0, 1), None);
+        assert_eq!(set.union(0, 1, Sum(3)), Ok(true));
+        assert_eq!(set.union(2, 3, Sum(-1)), Ok(true));
+        assert_eq!(set.union(3, 1, Sum(10)), Ok(true));
+        assert_eq!(set.diff(0, 2), Some(Sum(-6)));
+        assert_eq!(set.diff(2, 0), Some(Sum(6)));
+        assert_eq!(set.union(2, 0, Sum(6)), Ok(false));
+        assert_eq!(
+            set.union(0, 3, Sum(0)),
+            Err(Contradiction {
+                known: Sum(-7),
+                requested: Sum(0)
+            })
+        );
+        // a rejected union changes nothing
+        assert_eq!(set.diff(0, 3), Some(Sum(-7)));
+        assert_eq!(set.count(), 2);
+        assert_eq!(set.size(1), 4);
+        assert!(!set.same_set(4, 0));
+        let index = set.add();
+        assert_eq!(set.union(index, 4, Sum(1)), Ok(true));
+        assert_eq!(set.diff(4, index), Some(Sum(-1)));
+    }
+
+    #[test]
+    fn parities_work() {
+        // bipartiteness: the ends of every edge are on different sides
+        let edges = [(0, 1), (1, 2), (2, 3), (3, 0), (3, 4)];
+        let mut set = WeightedDisjointSet::new(5);
+        let results: Vec<_> = edges
+            .iter()
+            .map(|(a, b)| set.union(*a, *b, Xor(1u8)))
+            .collect();
+        assert_eq!(
+            results,
+            vec![Ok(true), Ok(true), Ok(true), Ok(false), Ok(true)]
+        );
+        assert_eq!(set.diff(0, 2), Some(Xor(0)));
+        assert_eq!(set.diff(4, 1), Some(Xor(1)));
+        // an odd cycle
+        assert!(set.union(0, 2, Xor(1)).is_err());
+    }
+
+    #[test]
+    fn inexact_ratios_are_consistent() {
+        let ratios = [0.1, 0.3, 0.7, 1.1, 2.9, 1.0 / 3.0];
+        for a_b in ratios {
+            for b_c in ratios {
+                // value(b) = value(a) * a_b and value(c) = value(b) * b_c
+                let mut set = WeightedDisjointSet::new(3);
+                assert_eq!(set.union(0, 1, Product(a_b)), Ok(true));
+                assert_eq!(set.union(1, 2, Product(b_c)), Ok(true));
+                // redundant, the products are rounded differently
+                assert_eq!(set.union(0, 2, Product(a_b * b_c)), Ok(false));
+                assert_eq!(set.union(2, 0, Product(1.0 / b_c / a_b)), Ok(false));
+                assert!(set.union(0, 2, Product(a_b * b_c * 1.001)).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn matches_brute_force_for_a_non_commutative_group() {
+        let mut rng = thread_rng();
+        let n = 30;
+        let permutations = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        // a hidden value for every index, the unions are consistent with them unless corrupted on purpose
+        let values: Vec<Permutation> = (0..n)
+            .map(|_| Permutation(permutations[rng.gen_range(0..6)]))
+            .collect();
+        let mut labels: Vec<usize> = (0..n).collect();
+        let mut set = WeightedDisjointSet::new(n);
+        for _ in 0..300 {
+            let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            let mut weight = values[a].inverse().combine(values[b]);
+            let (label_a, label_b) = (labels[a], labels[b]);
+            if label_a == label_b {
+                let corrupt = rng.gen_bool(0.5);
+                if corrupt {
+                    weight = weight.combine(Permutation([1, 2, 0]));
+                }
+                assert_eq!(set.union(a, b, weight).is_err(), corrupt);
+            } else {
+                assert_eq!(set.union(a, b, weight), Ok(true));
+                labels
+                    .iter_mut()
+                    .filter(|label| **label == label_b)
+                    .for_each(|label| *label = label_a);
+            }
+            let (c, d) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            let expected = match labels[c] == labels[d] {
+                true => Some(values[c].inverse().combine(values[d])),
+                false => None,
+            };
+            assert_eq!(set.diff(c, d), expected);
+        }
+    }
+}
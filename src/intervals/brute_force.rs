@@ -4,11 +4,11 @@ use std::ops::RangeInclusive;
 
 pub struct BruteForce<T: Clone> {
     pub values: Vec<T>,
-    merge_fn: Box<dyn Fn(T, T) -> T>,
+    merge_fn: Box<dyn Fn(T, T) -> T + Send + Sync>,
 }
 
-impl<'a, T: Clone> BruteForce<T> {
-    pub fn new(values: &[T], merge_fn: Box<dyn Fn(T, T) -> T>) -> Self {
+impl<T: Clone> BruteForce<T> {
+    pub fn new(values: &[T], merge_fn: Box<dyn Fn(T, T) -> T + Send + Sync>) -> Self {
         BruteForce {
             values: values.to_vec(),
             merge_fn,
@@ -0,0 +1,161 @@
+//! A calendar of bookings with a maximum number of overlapping bookings (LeetCode 729, 731 and 732).
+//! Backed by a [`DynamicSegmentTreeWithRangeUpdates`] over time holding the number of bookings at each instant:
+//! booking adds 1 to its range and the overlap of a range is its max. Only the nodes on the paths to the ends
+//! of the bookings are created, so the times can span the whole `i64` range: `start..end` books the instants
+//! of `i64::MIN..i64::MAX`.
+use std::{cmp, sync::Arc};
+
+use super::segment_tree::dynamic_segment_tree_with_range_updates::DynamicSegmentTreeWithRangeUpdates;
+
+/// Bookings of the half open time ranges `start..end`, rejecting those that would overlap too many others.
+/// ```
+/// # use datastructures_in_rust::intervals::calendar::Calendar;
+/// // a room that can hold two meetings at once
+/// let mut calendar = Calendar::new(Some(2));
+/// assert!(calendar.book(10, 20));
+/// assert!(calendar.book(15, 25));
+/// assert!(!calendar.book(18, 30));
+/// assert!(calendar.book(20, 30));
+/// assert_eq!(calendar.max_overlap(), 2);
+/// ```
+pub struct Calendar {
+    /// The number of bookings at each instant
+    bookings: DynamicSegmentTreeWithRangeUpdates<u32>,
+    /// The maximum number of bookings at any instant, None if unbounded
+    limit: Option<u32>,
+}
+
+impl Calendar {
+    /// Creates an empty calendar allowing at most `limit` overlapping bookings at any instant, or any number if None.
+    /// `Some(1)` rejects double bookings (MyCalendar I), `Some(2)` triple bookings (MyCalendar II).
+    pub fn new(limit: Option<u32>) -> Self {
+        Calendar {
+            bookings: DynamicSegmentTreeWithRangeUpdates::new_with_add(
+                i64::MIN..=i64::MAX - 1,
+                Arc::new(cmp::max),
+                Arc::new(|a, b| a + b),
+            ),
+            limit,
+        }
+    }
+
+    /// The maximum number of overlapping bookings, None if unbounded
+    pub fn limit(&self) -> Option<u32> {
+        self.limit
+    }
+
+    /// Books `start..end` if it would not exceed the limit of overlapping bookings, returns whether it was booked.
+    /// Panics if the range is empty.
+    pub fn book(&mut self, start: i64, end: i64) -> bool {
+        assert!(start < end, "invalid booking {}..{}", start, end);
+        if let Some(limit) = self.limit {
+            if self.overlap(start, end) >= limit {
+                return false;
+            }
+        }
+        self.bookings.add(start..=end - 1, 1);
+        true
+    }
+
+    /// The maximum number of bookings at an instant of `start..end`, 0 for an empty range
+    pub fn overlap(&self, start: i64, end: i64) -> u32 {
+        if start >= end {
+            return 0;
+        }
+        self.bookings.query(start..=end - 1).unwrap_or(0)
+    }
+
+    /// The maximum number of bookings at any instant (MyCalendar III)
+    pub fn max_overlap(&self) -> u32 {
+        self.overlap(i64::MIN, i64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+
+    use super::Calendar;
+
+    #[test]
+    fn my_calendar_works() {
+        // LeetCode 729
+        let mut calendar = Calendar::new(Some(1));
+        assert!(calendar.book(10, 20));
+        assert!(!calendar.book(15, 25));
+        assert!(calendar.book(20, 30));
+        assert_eq!(calendar.max_overlap(), 1);
+    }
+
+    #[test]
+    fn my_calendar_two_works() {
+        // LeetCode 731
+        let mut calendar = Calendar::new(Some(2));
+        let bookings = [(10, 20), (50, 60), (10, 40), (5, 15), (5, 10), (25, 55)];
+        assert_eq!(
+            bookings.map(|(start, end)| calendar.book(start, end)),
+            [true, true, true, false, true, true]
+        );
+        assert_eq!(calendar.max_overlap(), 2);
+    }
+
+    #[test]
+    fn my_calendar_three_works() {
+        // LeetCode 732
+        let mut calendar = Calendar::new(None);
+        let bookings = [(10, 20), (50, 60), (10, 40), (5, 15), (5, 10), (25, 55)];
+        let max_overlaps = bookings.map(|(start, end)| {
+            assert!(calendar.book(start, end));
+            calendar.max_overlap()
+        });
+        assert_eq!(max_overlaps, [1, 1, 2, 3, 3, 3]);
+    }
+
+    #[test]
+    fn extreme_times_work() {
+        let mut calendar = Calendar::new(Some(1));
+        assert!(calendar.book(i64::MAX - 1, i64::MAX));
+        assert!(calendar.book(0, 1));
+        assert!(!calendar.book(0, i64::MAX));
+        assert!(calendar.book(1, i64::MAX - 1));
+        assert_eq!(calendar.overlap(0, i64::MAX), 1);
+        // times before 0, e.g. relative to now
+        assert!(calendar.book(i64::MIN, i64::MIN + 1));
+        assert!(calendar.book(-10, 0));
+        assert!(!calendar.book(-1, 1));
+        assert!(!calendar.book(i64::MIN, 0));
+        assert_eq!(calendar.max_overlap(), 1);
+        let mut calendar = Calendar::new(None);
+        calendar.book(i64::MIN, i64::MAX);
+        calendar.book(-5, 5);
+        assert_eq!(calendar.overlap(i64::MIN, -5), 1);
+        assert_eq!(calendar.max_overlap(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_bookings_panic() {
+        Calendar::new(None).book(5, 5);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = thread_rng();
+        for limit in [Some(0), Some(1), Some(2), Some(3), None] {
+            let mut calendar = Calendar::new(limit);
+            let mut counts = [0u32; 100];
+            for _ in 0..300 {
+                let start = rng.gen_range(0..99);
+                let end = rng.gen_range(start + 1..=100);
+                let overlap = counts[start..end].iter().copied().max().unwrap();
+                let expected = limit.is_none_or(|limit| overlap < limit);
+                // the instants of -50..50
+                assert_eq!(calendar.book(start as i64 - 50, end as i64 - 50), expected);
+                if expected {
+                    counts[start..end].iter_mut().for_each(|c| *c += 1);
+                }
+                assert_eq!(calendar.max_overlap(), *counts.iter().max().unwrap());
+            }
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! A set of integers stored as the half open ranges they cover (LeetCode 715).
+//! Backed by a [`DynamicSegmentTreeWithRangeUpdates`] over the domain: adding or removing a range assigns
+//! covered / uncovered to it, and every node counts the covered elements of its range.
+//! Only the nodes on the paths to the ends of the ranges are created, so the domain spans the `i64`s
+//! of `i64::MIN..i64::MAX` (`i64::MAX` itself can only end a half open range).
+use std::{
+    ops::{Range, RangeInclusive},
+    sync::Arc,
+};
+
+use super::segment_tree::dynamic_segment_tree_with_range_updates::DynamicSegmentTreeWithRangeUpdates;
+
+/// The number of covered elements among the elements of a range
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Coverage {
+    covered: u64,
+    length: u64,
+}
+
+const COVERED: Coverage = Coverage {
+    covered: 1,
+    length: 1,
+};
+const UNCOVERED: Coverage = Coverage {
+    covered: 0,
+    length: 1,
+};
+
+/// A set of the integers in `i64::MIN..i64::MAX`, added and removed a range at a time.
+/// ```
+/// # use datastructures_in_rust::intervals::interval_set::IntervalSet;
+/// let mut set = IntervalSet::new();
+/// set.add_range(10..20);
+/// set.remove_range(14..16);
+/// assert!(set.query_range(10..14));
+/// assert!(!set.query_range(13..15));
+/// assert_eq!(set.runs().collect::<Vec<_>>(), vec![10..14, 16..20]);
+/// assert_eq!(set.covered_length(), 8);
+/// ```
+pub struct IntervalSet {
+    coverage: DynamicSegmentTreeWithRangeUpdates<Coverage>,
+}
+
+impl Default for IntervalSet {
+    fn default() -> Self {
+        IntervalSet::new()
+    }
+}
+
+impl IntervalSet {
+    /// The integers the set can hold
+    pub const DOMAIN: Range<i64> = i64::MIN..i64::MAX;
+
+    /// Creates an empty set
+    pub fn new() -> Self {
+        let mut coverage = DynamicSegmentTreeWithRangeUpdates::new(
+            IntervalSet::DOMAIN.start..=IntervalSet::DOMAIN.end - 1,
+            Arc::new(|a: Coverage, b: Coverage| Coverage {
+                covered: a.covered + b.covered,
+                length: a.length + b.length,
+            }),
+        );
+        coverage.update(coverage.range(), UNCOVERED);
+        IntervalSet { coverage }
+    }
+
+    /// Adds every integer of the range
+    pub fn add_range(&mut self, range: Range<i64>) {
+        if let Some(range) = IntervalSet::inclusive(range) {
+            self.coverage.update(range, COVERED);
+        }
+    }
+
+    /// Removes every integer of the range
+    pub fn remove_range(&mut self, range: Range<i64>) {
+        if let Some(range) = IntervalSet::inclusive(range) {
+            self.coverage.update(range, UNCOVERED);
+        }
+    }
+
+    /// True if every integer of the range is in the set, an empty range is always covered
+    pub fn query_range(&self, range: Range<i64>) -> bool {
+        match IntervalSet::inclusive(range) {
+            Some(range) => self.coverage(range).is_some_and(|c| c.covered == c.length),
+            None => true,
+        }
+    }
+
+    /// True if the integer is in the set
+    pub fn contains(&self, value: i64) -> bool {
+        IntervalSet::DOMAIN.contains(&value) && self.query_range(value..value + 1)
+    }
+
+    /// The number of integers in the set
+    pub fn covered_length(&self) -> u64 {
+        self.coverage(self.coverage.range())
+            .map_or(0, |c| c.covered)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.covered_length() == 0
+    }
+
+    /// The maximal ranges of the set, in increasing order. O(R log²V) for R ranges.
+    pub fn runs(&self) -> impl Iterator<Item = Range<i64>> + '_ {
+        let mut from = IntervalSet::DOMAIN.start;
+        std::iter::from_fn(move || {
+            // the elements before `from` that are covered
+            let covered_before = match from {
+                i64::MIN => 0,
+                _ => self.coverage(IntervalSet::DOMAIN.start..=from - 1)?.covered,
+            };
+            let start = self.coverage.find_prefix(|c| c.covered > covered_before)?;
+            // every element between `from` and `start` is uncovered
+            let uncovered_before = start.abs_diff(IntervalSet::DOMAIN.start) - covered_before;
+            let end = self
+                .coverage
+                .find_prefix(|c| c.length - c.covered > uncovered_before)
+                .unwrap_or(IntervalSet::DOMAIN.end);
+            from = end;
+            Some(start..end)
+        })
+    }
+
+    /// The maximal ranges of the domain that are not in the set, in increasing order
+    pub fn gaps(&self) -> impl Iterator<Item = Range<i64>> + '_ {
+        let mut runs = self.runs();
+        let mut from = Some(IntervalSet::DOMAIN.start);
+        std::iter::from_fn(move || loop {
+            let start = from?;
+            let gap = match runs.next() {
+                Some(run) => {
+                    from = Some(run.end);
+                    start..run.start
+                }
+                None => {
+                    from = None;
+                    start..IntervalSet::DOMAIN.end
+                }
+            };
+            if !gap.is_empty() {
+                return Some(gap);
+            }
+        })
+    }
+
+    /// The integers in either set
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        let mut result = IntervalSet::new();
+        self.runs()
+            .chain(other.runs())
+            .for_each(|run| result.add_range(run));
+        result
+    }
+
+    /// The integers in both sets
+    pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        let mut result = IntervalSet::new();
+        self.runs().for_each(|run| result.add_range(run));
+        other.gaps().for_each(|gap| result.remove_range(gap));
+        result
+    }
+
+    /// The integers in self but not in the other set
+    pub fn difference(&self, other: &IntervalSet) -> IntervalSet {
+        let mut result = IntervalSet::new();
+        self.runs().for_each(|run| result.add_range(run));
+        other.runs().for_each(|run| result.remove_range(run));
+        result
+    }
+
+    fn coverage(&self, range: RangeInclusive<i64>) -> Option<Coverage> {
+        self.coverage.query(range)
+    }
+
+    /// The inclusive range of the integers in the range, None if it is empty.
+    /// Every non empty range is within the domain.
+    fn inclusive(range: Range<i64>) -> Option<RangeInclusive<i64>> {
+        if range.is_empty() {
+            return None;
+        }
+        Some(range.start..=range.end - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use rand::{thread_rng, Rng};
+
+    use super::IntervalSet;
+
+    /// The maximal runs of the true values
+    fn runs(values: &[bool]) -> Vec<Range<i64>> {
+        let mut runs = vec![];
+        let mut start = None;
+        for (i, v) in values.iter().chain([false].iter()).enumerate() {
+            match (start, v) {
+                (None, true) => start = Some(i as i64),
+                (Some(s), false) => {
+                    runs.push(s..i as i64);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        runs
+    }
+
+    fn random_set(values: &mut [bool], rng: &mut impl Rng) -> IntervalSet {
+        let mut set = IntervalSet::new();
+        for _ in 0..rng.gen_range(0..10) {
+            let start = rng.gen_range(0..values.len());
+            let end = rng.gen_range(start..=values.len());
+            let add = rng.gen_bool(0.6);
+            if add {
+                set.add_range(start as i64..end as i64);
+            } else {
+                set.remove_range(start as i64..end as i64);
+            }
+            values[start..end].iter_mut().for_each(|v| *v = add);
+        }
+        set
+    }
+
+    #[test]
+    fn range_module_works() {
+        // LeetCode 715
+        let mut set = IntervalSet::new();
+        set.add_range(10..20);
+        set.remove_range(14..16);
+        assert!(set.query_range(10..14));
+        assert!(!set.query_range(13..15));
+        assert!(set.query_range(16..17));
+        assert!(!set.is_empty());
+        assert!(set.contains(19));
+        assert!(!set.contains(20));
+        assert!(!set.contains(-1));
+        assert!(!set.contains(i64::MAX));
+    }
+
+    #[test]
+    fn extreme_ranges_work() {
+        let mut set = IntervalSet::new();
+        assert!(set.is_empty());
+        assert_eq!(set.runs().count(), 0);
+        assert_eq!(set.gaps().collect::<Vec<_>>(), vec![IntervalSet::DOMAIN]);
+        set.add_range(IntervalSet::DOMAIN);
+        assert_eq!(set.covered_length(), u64::MAX);
+        assert_eq!(set.runs().collect::<Vec<_>>(), vec![IntervalSet::DOMAIN]);
+        assert_eq!(set.gaps().count(), 0);
+        set.remove_range(i64::MIN + 1..i64::MAX - 1);
+        assert_eq!(
+            set.runs().collect::<Vec<_>>(),
+            vec![i64::MIN..i64::MIN + 1, i64::MAX - 1..i64::MAX]
+        );
+        assert_eq!(
+            set.gaps().collect::<Vec<_>>(),
+            vec![i64::MIN + 1..i64::MAX - 1]
+        );
+        // empty ranges are ignored
+        set.add_range(5..5);
+        assert_eq!(set.covered_length(), 2);
+    }
+
+    #[test]
+    fn negative_ranges_work() {
+        let mut set = IntervalSet::new();
+        set.add_range(-5..5);
+        set.remove_range(-2..0);
+        assert_eq!(set.runs().collect::<Vec<_>>(), vec![-5..-2, 0..5]);
+        assert_eq!(
+            set.gaps().collect::<Vec<_>>(),
+            vec![i64::MIN..-5, -2..0, 5..i64::MAX]
+        );
+        assert!(set.query_range(-5..-2));
+        assert!(!set.query_range(-3..1));
+        assert_eq!(set.covered_length(), 8);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let mut values = vec![false; 40];
+            let set = random_set(&mut values, &mut rng);
+            assert_eq!(set.runs().collect::<Vec<_>>(), runs(&values));
+            assert_eq!(
+                set.covered_length(),
+                values.iter().filter(|v| **v).count() as u64
+            );
+            let start = rng.gen_range(0..40);
+            let end = rng.gen_range(start..=40);
+            assert_eq!(
+                set.query_range(start as i64..end as i64),
+                values[start..end].iter().all(|v| *v)
+            );
+        }
+    }
+
+    #[test]
+    fn set_operations_match_brute_force() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let (mut a, mut b) = (vec![false; 40], vec![false; 40]);
+            let (set_a, set_b) = (random_set(&mut a, &mut rng), random_set(&mut b, &mut rng));
+            let combine = |f: fn(bool, bool) -> bool| {
+                runs(
+                    &a.iter()
+                        .zip(b.iter())
+                        .map(|(x, y)| f(*x, *y))
+                        .collect::<Vec<_>>(),
+                )
+            };
+            assert_eq!(
+                set_a.union(&set_b).runs().collect::<Vec<_>>(),
+                combine(|x, y| x || y)
+            );
+            assert_eq!(
+                set_a.intersection(&set_b).runs().collect::<Vec<_>>(),
+                combine(|x, y| x && y)
+            );
+            assert_eq!(
+                set_a.difference(&set_b).runs().collect::<Vec<_>>(),
+                combine(|x, y| x && !y)
+            );
+        }
+    }
+}
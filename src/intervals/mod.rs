@@ -2,4 +2,9 @@
 //! This module implements data structures to perform queries and updates on intervals.
 
 pub mod brute_force;
+pub mod calendar;
+pub mod interval_set;
+pub mod ops;
+pub mod ordered_multiset;
 pub mod segment_tree;
+pub mod sparse_table;
@@ -0,0 +1,756 @@
+//! Ready-made aggregation operations for the interval structures.
+//!
+//! Each operation is a value type implementing [`Monoid`], its `Default` is the identity of the operation
+//! (the value of the elements the dynamic trees have not seen yet). The merge function of a tree is built with
+//! [`boxed`] (for [`ArrayBasedSegmentTree`](super::segment_tree::array_based_segment_tree::ArrayBasedSegmentTree))
+//! or [`shared`] (for the dynamic trees).
+//! ```
+//! # use datastructures_in_rust::intervals::ops::{self, Min};
+//! # use datastructures_in_rust::intervals::segment_tree::array_based_segment_tree::ArrayBasedSegmentTree;
+//! # use datastructures_in_rust::intervals::segment_tree::dynamic_segment_tree_with_range_updates::DynamicSegmentTreeWithRangeUpdates;
+//! let values: Vec<Min<i64>> = [5, 3, 8].iter().map(|v| Min(*v)).collect();
+//! let st = ArrayBasedSegmentTree::new(&values, ops::boxed());
+//! assert_eq!(st.query(1..=2), Some(Min(3)));
+//!
+//! let mut dst = DynamicSegmentTreeWithRangeUpdates::new(0..=1_000_000, ops::shared());
+//! dst.update(10..=20, Min(7));
+//! assert_eq!(dst.query(0..=15), Some(Min(7)));
+//! ```
+//! The arithmetic operations (`Sum`, `Product`, `Lcm`, `MaxSubarraySum`) use the standard operators,
+//! so on overflow they panic in debug builds and wrap in release builds.
+//! To choose the behaviour deliberately use [`SaturatingSum`], [`WrappingSum`] or the fallible
+//! [`CheckedSum`] (with [`Checked`] and the `try_query` / `try_update` methods of the trees).
+use std::{fmt::Debug, fmt::Display, sync::Arc};
+
+use super::segment_tree::MergeFn;
+
+/// An associative operation with an identity element.
+/// The operation need not be commutative, the trees always combine the values left to right by index.
+pub trait Monoid: Clone {
+    /// The value that leaves any other value unchanged when combined with it
+    fn identity() -> Self;
+
+    /// Combines the aggregate of a segment with the aggregate of the segment right after it
+    fn combine(self, other: Self) -> Self;
+}
+
+/// A monoid whose values all have an inverse, e.g. the relative values of a
+/// [`WeightedDisjointSet`](crate::disjoint_set::weighted_disjoint_set::WeightedDisjointSet).
+pub trait Group: Monoid {
+    /// The value that gives the identity when combined with self, on either side
+    fn inverse(self) -> Self;
+
+    /// True if the two values are equal, up to the rounding errors for floating point values.
+    /// A weighted disjoint set compares the relative values with it to tell redundant unions from contradictions.
+    fn approx_eq(&self, other: &Self) -> bool
+    where
+        Self: PartialEq,
+    {
+        self == other
+    }
+}
+
+/// A monoid whose values combined with themselves are unchanged, so a value counted twice doesn't change
+/// an aggregate, e.g. the overlapping ranges of a [`SparseTable`](super::sparse_table::SparseTable).
+pub trait Idempotent: Monoid {}
+
+/// The merge function of an [`ArrayBasedSegmentTree`](super::segment_tree::array_based_segment_tree::ArrayBasedSegmentTree)
+pub fn boxed<M: Monoid + 'static>() -> Box<dyn Fn(M, M) -> M + Send + Sync> {
+    Box::new(M::combine)
+}
+
+/// The merge function of the dynamic segment trees
+pub fn shared<M: Monoid + 'static>() -> MergeFn<M> {
+    Arc::new(M::combine)
+}
+
+/// Implements `Default` as the identity of the monoid
+macro_rules! default_is_identity {
+    ($($op:ident<$t:ty>),*) => {
+        $(
+            impl Default for $op<$t> {
+                fn default() -> Self {
+                    <$op<$t> as Monoid>::identity()
+                }
+            }
+        )*
+    };
+}
+
+/// The sum of the values, the identity is 0
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Sum<T>(pub T);
+
+/// The product of the values, the identity is 1
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Product<T>(pub T);
+
+macro_rules! arithmetic {
+    ($($t:ty),*) => {
+        $(
+            impl Monoid for Sum<$t> {
+                fn identity() -> Self {
+                    Sum(0 as $t)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    Sum(self.0 + other.0)
+                }
+            }
+
+            impl Monoid for Product<$t> {
+                fn identity() -> Self {
+                    Product(1 as $t)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    Product(self.0 * other.0)
+                }
+            }
+
+            default_is_identity!(Sum<$t>, Product<$t>);
+        )*
+    };
+}
+
+arithmetic!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+/// The sum of the values, clamped to the bounds of the type on overflow. The identity is 0.
+/// Saturation depends on the order of the values (`(MAX + 1) + -1 = MAX - 1` while `MAX + (1 + -1) = MAX`),
+/// so the aggregate of a range may differ from a left to right fold once it saturated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SaturatingSum<T>(pub T);
+
+/// The sum of the values, wrapping around the bounds of the type on overflow. The identity is 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WrappingSum<T>(pub T);
+
+/// An associative operation with an identity element that can fail, e.g. on overflow
+pub trait TryMonoid: Clone {
+    type Error: Debug + Clone;
+
+    /// The value that leaves any other value unchanged when combined with it
+    fn identity() -> Self;
+
+    /// Combines the aggregate of a segment with the aggregate of the segment right after it
+    fn try_combine(self, other: Self) -> Result<Self, Self::Error>;
+}
+
+/// The error of the checked arithmetic operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow;
+
+impl Display for Overflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl std::error::Error for Overflow {}
+
+/// The sum of the values, failing with [`Overflow`]. The identity is 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CheckedSum<T>(pub T);
+
+/// The product of the values, failing with [`Overflow`]. The identity is 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CheckedProduct<T>(pub T);
+
+macro_rules! overflow_aware {
+    ($($t:ty),*) => {
+        $(
+            impl Monoid for SaturatingSum<$t> {
+                fn identity() -> Self {
+                    SaturatingSum(0)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    SaturatingSum(self.0.saturating_add(other.0))
+                }
+            }
+
+            impl Monoid for WrappingSum<$t> {
+                fn identity() -> Self {
+                    WrappingSum(0)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    WrappingSum(self.0.wrapping_add(other.0))
+                }
+            }
+
+            impl TryMonoid for CheckedSum<$t> {
+                type Error = Overflow;
+
+                fn identity() -> Self {
+                    CheckedSum(0)
+                }
+
+                fn try_combine(self, other: Self) -> Result<Self, Overflow> {
+                    self.0.checked_add(other.0).map(CheckedSum).ok_or(Overflow)
+                }
+            }
+
+            impl TryMonoid for CheckedProduct<$t> {
+                type Error = Overflow;
+
+                fn identity() -> Self {
+                    CheckedProduct(1)
+                }
+
+                fn try_combine(self, other: Self) -> Result<Self, Overflow> {
+                    self.0.checked_mul(other.0).map(CheckedProduct).ok_or(Overflow)
+                }
+            }
+
+            default_is_identity!(SaturatingSum<$t>, WrappingSum<$t>);
+        )*
+    };
+}
+
+overflow_aware!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// The aggregate of a [`TryMonoid`], which is the first error of the merges below it once one failed.
+/// Storing it in a tree makes the merges fallible: the segments whose aggregate failed hold the error,
+/// so only the queries covering them fail.
+/// ```
+/// # use datastructures_in_rust::intervals::ops::{self, Checked, CheckedSum, Overflow};
+/// # use datastructures_in_rust::intervals::segment_tree::array_based_segment_tree::ArrayBasedSegmentTree;
+/// let values: Vec<Checked<CheckedSum<i8>, Overflow>> =
+///     [100, 27, 1].iter().map(|v| Checked::from(CheckedSum(*v))).collect();
+/// let st = ArrayBasedSegmentTree::new(&values, ops::boxed());
+/// assert_eq!(st.try_query(0..=1), Ok(Some(CheckedSum(127))));
+/// assert_eq!(st.try_query(0..=2), Err(Overflow));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Checked<T, E>(pub Result<T, E>);
+
+impl<M: TryMonoid> Monoid for Checked<M, M::Error> {
+    fn identity() -> Self {
+        Checked(Ok(M::identity()))
+    }
+
+    fn combine(self, other: Self) -> Self {
+        match (self.0, other.0) {
+            (Ok(a), Ok(b)) => Checked(a.try_combine(b)),
+            (Err(e), _) | (_, Err(e)) => Checked(Err(e)),
+        }
+    }
+}
+
+impl<M: TryMonoid> Default for Checked<M, M::Error> {
+    fn default() -> Self {
+        Checked::identity()
+    }
+}
+
+impl<M: TryMonoid> From<M> for Checked<M, M::Error> {
+    fn from(value: M) -> Self {
+        Checked(Ok(value))
+    }
+}
+
+/// The smallest of the values, the identity is the largest value of the type (infinity for floats)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Min<T>(pub T);
+
+/// The largest of the values, the identity is the smallest value of the type (negative infinity for floats)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Max<T>(pub T);
+
+/// The smallest of the values along with its index, the leftmost one on ties
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MinWithIndex<T> {
+    pub value: T,
+    pub index: i64,
+}
+
+/// The largest of the values along with its index, the leftmost one on ties
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaxWithIndex<T> {
+    pub value: T,
+    pub index: i64,
+}
+
+impl<T> MinWithIndex<T> {
+    pub fn new(value: T, index: i64) -> Self {
+        MinWithIndex { value, index }
+    }
+}
+
+impl<T> MaxWithIndex<T> {
+    pub fn new(value: T, index: i64) -> Self {
+        MaxWithIndex { value, index }
+    }
+}
+
+/// Values are compared with `<`, a NaN is kept only if it is the left operand of every comparison.
+macro_rules! ordered {
+    ($($t:ty: $lowest:expr, $highest:expr);*) => {
+        $(
+            impl Monoid for Min<$t> {
+                fn identity() -> Self {
+                    Min($highest)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    if other.0 < self.0 { other } else { self }
+                }
+            }
+
+            impl Monoid for Max<$t> {
+                fn identity() -> Self {
+                    Max($lowest)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    if other.0 > self.0 { other } else { self }
+                }
+            }
+
+            impl Monoid for MinWithIndex<$t> {
+                /// The largest value at the largest index, so that any element wins a tie against it
+                fn identity() -> Self {
+                    MinWithIndex::new($highest, i64::MAX)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    if other.value < self.value
+                        || (other.value == self.value && other.index < self.index)
+                    {
+                        other
+                    } else {
+                        self
+                    }
+                }
+            }
+
+            impl Monoid for MaxWithIndex<$t> {
+                /// The smallest value at the largest index, so that any element wins a tie against it
+                fn identity() -> Self {
+                    MaxWithIndex::new($lowest, i64::MAX)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    if other.value > self.value
+                        || (other.value == self.value && other.index < self.index)
+                    {
+                        other
+                    } else {
+                        self
+                    }
+                }
+            }
+
+            impl Idempotent for Min<$t> {}
+            impl Idempotent for Max<$t> {}
+            impl Idempotent for MinWithIndex<$t> {}
+            impl Idempotent for MaxWithIndex<$t> {}
+
+            default_is_identity!(Min<$t>, Max<$t>, MinWithIndex<$t>, MaxWithIndex<$t>);
+        )*
+    };
+}
+
+ordered!(
+    i8: i8::MIN, i8::MAX;
+    i16: i16::MIN, i16::MAX;
+    i32: i32::MIN, i32::MAX;
+    i64: i64::MIN, i64::MAX;
+    i128: i128::MIN, i128::MAX;
+    isize: isize::MIN, isize::MAX;
+    u8: u8::MIN, u8::MAX;
+    u16: u16::MIN, u16::MAX;
+    u32: u32::MIN, u32::MAX;
+    u64: u64::MIN, u64::MAX;
+    u128: u128::MIN, u128::MAX;
+    usize: usize::MIN, usize::MAX;
+    f32: f32::NEG_INFINITY, f32::INFINITY;
+    f64: f64::NEG_INFINITY, f64::INFINITY
+);
+
+/// The greatest common divisor of the values, the identity is 0 (`gcd(0, x) = x`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Gcd<T>(pub T);
+
+/// The least common multiple of the values, the identity is 1.
+/// The lcm of 0 and any value is 0. The product `a / gcd(a, b) * b` can overflow, like [`Product`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Lcm<T>(pub T);
+
+macro_rules! divisibility {
+    ($($t:ty),*) => {
+        $(
+            impl Monoid for Gcd<$t> {
+                fn identity() -> Self {
+                    Gcd(0)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    let (mut a, mut b) = (self.0, other.0);
+                    while b != 0 {
+                        let r = a % b;
+                        a = b;
+                        b = r;
+                    }
+                    Gcd(a)
+                }
+            }
+
+            impl Monoid for Lcm<$t> {
+                fn identity() -> Self {
+                    Lcm(1)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    if self.0 == 0 || other.0 == 0 {
+                        return Lcm(0);
+                    }
+                    let gcd = Gcd(self.0).combine(Gcd(other.0)).0;
+                    Lcm(self.0 / gcd * other.0)
+                }
+            }
+
+            impl Idempotent for Gcd<$t> {}
+            impl Idempotent for Lcm<$t> {}
+
+            default_is_identity!(Gcd<$t>, Lcm<$t>);
+        )*
+    };
+}
+
+divisibility!(u8, u16, u32, u64, u128, usize);
+
+/// The bitwise xor of the values, the identity is 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Xor<T>(pub T);
+
+/// The bitwise and of the values, the identity has all the bits set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BitAnd<T>(pub T);
+
+/// The bitwise or of the values, the identity is 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BitOr<T>(pub T);
+
+macro_rules! bitwise {
+    ($($t:ty),*) => {
+        $(
+            impl Monoid for Xor<$t> {
+                fn identity() -> Self {
+                    Xor(0)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    Xor(self.0 ^ other.0)
+                }
+            }
+
+            impl Monoid for BitAnd<$t> {
+                fn identity() -> Self {
+                    BitAnd(!0)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    BitAnd(self.0 & other.0)
+                }
+            }
+
+            impl Monoid for BitOr<$t> {
+                fn identity() -> Self {
+                    BitOr(0)
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    BitOr(self.0 | other.0)
+                }
+            }
+
+            impl Idempotent for BitAnd<$t> {}
+            impl Idempotent for BitOr<$t> {}
+
+            default_is_identity!(Xor<$t>, BitAnd<$t>, BitOr<$t>);
+        )*
+    };
+}
+
+bitwise!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+macro_rules! group {
+    ($op:ident, |$v:ident| $inverse:expr, $($t:ty),*) => {
+        $(
+            impl Group for $op<$t> {
+                fn inverse(self) -> Self {
+                    let $v = self.0;
+                    $op($inverse)
+                }
+            }
+        )*
+    };
+}
+
+/// The groups of floating point values, equal up to a relative error of the tolerance
+macro_rules! float_group {
+    ($op:ident, |$v:ident| $inverse:expr, $($t:ty: $tolerance:expr),*) => {
+        $(
+            impl Group for $op<$t> {
+                fn inverse(self) -> Self {
+                    let $v = self.0;
+                    $op($inverse)
+                }
+
+                fn approx_eq(&self, other: &Self) -> bool {
+                    let (a, b) = (self.0, other.0);
+                    a == b || (a - b).abs() <= $tolerance * a.abs().max(b.abs())
+                }
+            }
+        )*
+    };
+}
+
+group!(Sum, |v| -v, i8, i16, i32, i64, i128, isize);
+float_group!(Sum, |v| -v, f32: 1e-4, f64: 1e-9);
+// the product of the non zero floats, the inverse of 0 is infinite
+float_group!(Product, |v| 1.0 / v, f32: 1e-4, f64: 1e-9);
+group!(
+    Xor,
+    |v| v,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize
+);
+
+/// The state of Kadane's algorithm for a segment, the best sum of a non empty subarray is `best`.
+/// The identity stands for the empty segment and has no subarray, its sums are the smallest value of the type.
+/// ```
+/// # use datastructures_in_rust::intervals::ops::{self, MaxSubarraySum};
+/// # use datastructures_in_rust::intervals::segment_tree::array_based_segment_tree::ArrayBasedSegmentTree;
+/// let values: Vec<MaxSubarraySum<i64>> = [-2, 1, -3, 4, -1, 2, 1, -5, 4]
+///     .iter()
+///     .map(|v| MaxSubarraySum::new(*v))
+///     .collect();
+/// let st = ArrayBasedSegmentTree::new(&values, ops::boxed());
+/// assert_eq!(st.query(0..=8).unwrap().best, 6);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaxSubarraySum<T> {
+    /// The sum of the segment
+    pub total: T,
+    /// The best sum of a non empty prefix
+    pub prefix: T,
+    /// The best sum of a non empty suffix
+    pub suffix: T,
+    /// The best sum of a non empty subarray
+    pub best: T,
+}
+
+impl<T: Copy> MaxSubarraySum<T> {
+    /// The state of a segment of a single value
+    pub fn new(value: T) -> Self {
+        MaxSubarraySum {
+            total: value,
+            prefix: value,
+            suffix: value,
+            best: value,
+        }
+    }
+}
+
+macro_rules! max_subarray_sum {
+    ($($t:ty),*) => {
+        $(
+            impl Monoid for MaxSubarraySum<$t> {
+                fn identity() -> Self {
+                    MaxSubarraySum {
+                        total: 0,
+                        prefix: <$t>::MIN,
+                        suffix: <$t>::MIN,
+                        best: <$t>::MIN,
+                    }
+                }
+
+                fn combine(self, other: Self) -> Self {
+                    // The sums of the identity are not real sums, adding to them could overflow
+                    if self == Self::identity() {
+                        return other;
+                    }
+                    if other == Self::identity() {
+                        return self;
+                    }
+                    MaxSubarraySum {
+                        total: self.total + other.total,
+                        prefix: self.prefix.max(self.total + other.prefix),
+                        suffix: other.suffix.max(self.suffix + other.total),
+                        best: self.best.max(other.best).max(self.suffix + other.prefix),
+                    }
+                }
+            }
+
+            default_is_identity!(MaxSubarraySum<$t>);
+        )*
+    };
+}
+
+max_subarray_sum!(i8, i16, i32, i64, i128, isize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fold<M: Monoid>(values: &[M]) -> M {
+        values.iter().cloned().fold(M::identity(), M::combine)
+    }
+
+    /// The identity is neutral on both sides and the default
+    fn check_identity<M: Monoid + Default + PartialEq + std::fmt::Debug>(values: &[M]) {
+        assert_eq!(M::default(), M::identity());
+        for v in values {
+            assert_eq!(M::identity().combine(v.clone()), *v);
+            assert_eq!(v.clone().combine(M::identity()), *v);
+        }
+    }
+
+    #[test]
+    fn identities_are_neutral() {
+        check_identity(&[Sum(-3i64), Sum(0), Sum(i64::MAX)]);
+        check_identity(&[Product(7u32), Product(0)]);
+        check_identity(&[Min(i32::MIN), Min(0), Min(i32::MAX)]);
+        check_identity(&[Max(-1.5f64), Max(f64::MAX)]);
+        check_identity(&[MinWithIndex::new(u8::MAX, 3), MinWithIndex::new(0, 0)]);
+        check_identity(&[MaxWithIndex::new(i16::MIN, 3)]);
+        check_identity(&[Gcd(0u64), Gcd(12)]);
+        check_identity(&[Lcm(0u64), Lcm(12)]);
+        check_identity(&[Xor(5i8), Xor(-1)]);
+        check_identity(&[BitAnd(0b1010u8), BitAnd(u8::MAX)]);
+        check_identity(&[BitOr(0b1010u8)]);
+        check_identity(&[MaxSubarraySum::new(i64::MIN), MaxSubarraySum::new(3)]);
+    }
+
+    #[test]
+    fn inverses_cancel() {
+        fn check<G: Group + PartialEq + std::fmt::Debug>(values: &[G]) {
+            for v in values {
+                assert_eq!(v.clone().combine(v.clone().inverse()), G::identity());
+                assert_eq!(v.clone().inverse().combine(v.clone()), G::identity());
+            }
+        }
+        check(&[Sum(-3i64), Sum(0), Sum(i64::MAX)]);
+        check(&[Sum(2.5f64)]);
+        check(&[Product(4.0f64), Product(0.5), Product(-2.0)]);
+        check(&[Xor(0b1010u8), Xor(u8::MAX)]);
+    }
+
+    #[test]
+    fn floats_are_approximately_equal() {
+        assert!(Product(0.1f64 * 0.7).approx_eq(&Product(0.07)));
+        assert_ne!(Product(0.1f64 * 0.7), Product(0.07));
+        assert!(Sum(0.1f64 + 0.2).approx_eq(&Sum(0.3)));
+        assert!(Sum(0.1f32 + 0.2).approx_eq(&Sum(0.3)));
+        assert!(!Product(1.1f64).approx_eq(&Product(1.1000001)));
+        assert!(!Sum(0.0f64).approx_eq(&Sum(1e-300)));
+        assert!(Sum(3i64).approx_eq(&Sum(3)));
+        assert!(!Sum(3i64).approx_eq(&Sum(4)));
+    }
+
+    #[test]
+    fn operations_work() {
+        assert_eq!(fold(&[Sum(1), Sum(2), Sum(3)]), Sum(6));
+        assert_eq!(
+            fold(&[Product(2.0), Product(0.5), Product(3.0)]),
+            Product(3.0)
+        );
+        assert_eq!(fold(&[Min(4), Min(-2), Min(9)]), Min(-2));
+        assert_eq!(fold(&[Max(4u8), Max(2), Max(9)]), Max(9));
+        assert_eq!(fold(&[Gcd(12u32), Gcd(18), Gcd(30)]), Gcd(6));
+        assert_eq!(fold(&[Lcm(4u32), Lcm(6), Lcm(10)]), Lcm(60));
+        assert_eq!(fold(&[Lcm(4u32), Lcm(0)]), Lcm(0));
+        assert_eq!(fold(&[Xor(0b110), Xor(0b011)]), Xor(0b101));
+        assert_eq!(fold(&[BitAnd(0b110), BitAnd(0b011)]), BitAnd(0b010));
+        assert_eq!(fold(&[BitOr(0b110), BitOr(0b011)]), BitOr(0b111));
+    }
+
+    #[test]
+    fn overflow_behaviour_is_chosen() {
+        assert_eq!(
+            fold(&[SaturatingSum(100i8), SaturatingSum(100), SaturatingSum(-50)]),
+            SaturatingSum(77)
+        );
+        assert_eq!(
+            fold(&[SaturatingSum(0u8), SaturatingSum(255), SaturatingSum(1)]),
+            SaturatingSum(255)
+        );
+        assert_eq!(
+            fold(&[WrappingSum(100i8), WrappingSum(100), WrappingSum(-50)]),
+            WrappingSum(-106)
+        );
+        let checked = |values: &[i8]| {
+            fold(
+                &values
+                    .iter()
+                    .map(|v| Checked::from(CheckedSum(*v)))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(checked(&[100, 27]), Checked(Ok(CheckedSum(127))));
+        assert_eq!(checked(&[100, 28, -50]), Checked(Err(Overflow)));
+        assert_eq!(checked(&[-100, -28]), Checked(Ok(CheckedSum(-128))));
+        assert_eq!(
+            Checked::from(CheckedProduct(16u8)).combine(Checked::from(CheckedProduct(16))),
+            Checked(Err(Overflow))
+        );
+        check_identity(&[Checked(Ok(CheckedSum(3u8))), Checked(Err(Overflow))]);
+    }
+
+    #[test]
+    fn ties_pick_the_leftmost_index() {
+        let values = [3, 1, 4, 1, 5, 9, 2, 9];
+        let min = fold(
+            &values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| MinWithIndex::new(*v, i as i64))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(min, MinWithIndex::new(1, 1));
+        let max = fold(
+            &values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| MaxWithIndex::new(*v, i as i64))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(max, MaxWithIndex::new(9, 5));
+    }
+
+    #[test]
+    fn max_subarray_sum_matches_brute_force() {
+        let values: Vec<i64> = vec![-2, 1, -3, 4, -1, 2, 1, -5, 4, -7, -8];
+        for start in 0..values.len() {
+            for end in start..values.len() {
+                let segment = &values[start..=end];
+                let mut expected = i64::MIN;
+                for i in 0..segment.len() {
+                    for j in i..segment.len() {
+                        expected = expected.max(segment[i..=j].iter().sum());
+                    }
+                }
+                let states: Vec<_> = segment.iter().map(|v| MaxSubarraySum::new(*v)).collect();
+                let state = fold(&states);
+                assert_eq!(state.best, expected);
+                assert_eq!(state.total, segment.iter().sum::<i64>());
+            }
+        }
+        // a single element at the extremes of the type does not overflow
+        let state = fold(&[MaxSubarraySum::new(i8::MIN)]);
+        assert_eq!(state.best, i8::MIN);
+    }
+}
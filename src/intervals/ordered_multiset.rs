@@ -0,0 +1,233 @@
+//! An ordered multiset of integers, backed by a counting [`DynamicSegmentTree`] over the value domain.
+//! Only the nodes on the paths to the inserted values are created, so the domain can span up to 10^18 values
+//! while every operation is O(log V).
+use std::{ops::RangeInclusive, sync::Arc};
+
+use super::segment_tree::dynamic_segment_tree::DynamicSegmentTree;
+
+/// An ordered multiset of the integers in a domain.
+/// ```
+/// # use datastructures_in_rust::intervals::ordered_multiset::OrderedMultiset;
+/// let mut set = OrderedMultiset::new(0..=1_000_000_000_000_000_000);
+/// set.insert(5);
+/// set.insert(5);
+/// set.insert(1_000_000_000_000_000_000);
+/// assert_eq!(set.kth(1), Some(5));
+/// assert_eq!(set.rank(6), 2);
+/// assert_eq!(set.successor(5), Some(1_000_000_000_000_000_000));
+/// ```
+pub struct OrderedMultiset {
+    /// The number of occurrences of each value
+    counts: DynamicSegmentTree<u64>,
+}
+
+impl OrderedMultiset {
+    /// Creates an empty multiset holding values in the given domain
+    pub fn new(domain: RangeInclusive<i64>) -> Self {
+        OrderedMultiset {
+            counts: DynamicSegmentTree::new(domain, Arc::new(|a, b| a + b)),
+        }
+    }
+
+    /// The values the multiset can hold
+    pub fn domain(&self) -> RangeInclusive<i64> {
+        self.counts.range()
+    }
+
+    /// The number of values, counting each occurrence
+    pub fn len(&self) -> u64 {
+        self.count_range(self.domain())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds an occurrence of the value.
+    /// Panics if the value is outside the domain.
+    pub fn insert(&mut self, value: i64) {
+        assert!(
+            self.domain().contains(&value),
+            "{} is outside the domain {:?}",
+            value,
+            self.domain()
+        );
+        self.counts.insert(value, 1);
+    }
+
+    /// Removes an occurrence of the value, returns false if there was none
+    pub fn remove(&mut self, value: i64) -> bool {
+        match self.count(value) {
+            0 => false,
+            count => {
+                self.counts.set(value, count - 1);
+                true
+            }
+        }
+    }
+
+    /// The number of occurrences of the value
+    pub fn count(&self, value: i64) -> u64 {
+        self.count_range(value..=value)
+    }
+
+    /// The number of values in the range, counting each occurrence
+    pub fn count_range(&self, range: RangeInclusive<i64>) -> u64 {
+        self.counts.query(range).unwrap_or(0)
+    }
+
+    /// The `k`-th smallest value (from 0), counting each occurrence
+    pub fn kth(&self, k: u64) -> Option<i64> {
+        self.counts.find_prefix(|count| *count > k)
+    }
+
+    /// The number of values smaller than the given one
+    pub fn rank(&self, value: i64) -> u64 {
+        match value.checked_sub(1) {
+            Some(end) => self.count_range(*self.domain().start()..=end),
+            None => 0,
+        }
+    }
+
+    /// The largest value smaller than the given one
+    pub fn predecessor(&self, value: i64) -> Option<i64> {
+        match self.rank(value) {
+            0 => None,
+            rank => self.kth(rank - 1),
+        }
+    }
+
+    /// The smallest value larger than the given one
+    pub fn successor(&self, value: i64) -> Option<i64> {
+        match value.checked_add(1) {
+            Some(value) => self.kth(self.rank(value)),
+            None => None,
+        }
+    }
+
+    /// The distinct values in increasing order, along with their number of occurrences. O(D log V) for D distinct values.
+    pub fn iter(&self) -> impl Iterator<Item = (i64, u64)> + '_ {
+        let mut next = self.kth(0);
+        std::iter::from_fn(move || {
+            let value = next?;
+            next = self.successor(value);
+            Some((value, self.count(value)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rand::{thread_rng, Rng};
+
+    use super::OrderedMultiset;
+
+    #[test]
+    fn operations_work() {
+        let mut set = OrderedMultiset::new(-10..=10);
+        assert!(set.is_empty());
+        assert_eq!(set.kth(0), None);
+        for value in [3, -2, 3, 7, 10, -10] {
+            set.insert(value);
+        }
+        assert_eq!(set.len(), 6);
+        assert_eq!(
+            (0..7).map(|k| set.kth(k)).collect::<Vec<_>>(),
+            vec![
+                Some(-10),
+                Some(-2),
+                Some(3),
+                Some(3),
+                Some(7),
+                Some(10),
+                None
+            ]
+        );
+        assert_eq!(set.rank(3), 2);
+        assert_eq!(set.rank(4), 4);
+        assert_eq!(set.count(3), 2);
+        assert_eq!(set.count_range(-2..=7), 4);
+        assert_eq!(set.predecessor(3), Some(-2));
+        assert_eq!(set.predecessor(-10), None);
+        assert_eq!(set.successor(3), Some(7));
+        assert_eq!(set.successor(10), None);
+        assert!(set.remove(3));
+        assert!(!set.remove(4));
+        assert_eq!(set.count(3), 1);
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            vec![(-10, 1), (-2, 1), (3, 1), (7, 1), (10, 1)]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_outside_domain_panics() {
+        OrderedMultiset::new(0..=10).insert(11);
+    }
+
+    #[test]
+    fn matches_btree_map() {
+        const MAX: i64 = 1_000_000_000_000_000_000;
+        let mut rng = thread_rng();
+        let mut set = OrderedMultiset::new(0..=MAX);
+        let mut reference: BTreeMap<i64, u64> = BTreeMap::new();
+        // a few hot values so that the counts and removals matter
+        let mut values: Vec<i64> = (0..20).map(|_| rng.gen_range(0..=MAX)).collect();
+        values.extend([0, MAX]);
+        for _ in 0..3_000 {
+            let value = if rng.gen_bool(0.7) {
+                values[rng.gen_range(0..values.len())]
+            } else {
+                rng.gen_range(0..=MAX)
+            };
+            if rng.gen_bool(0.6) {
+                set.insert(value);
+                *reference.entry(value).or_insert(0) += 1;
+            } else {
+                let expected = match reference.get_mut(&value) {
+                    Some(count) => {
+                        *count -= 1;
+                        if *count == 0 {
+                            reference.remove(&value);
+                        }
+                        true
+                    }
+                    None => false,
+                };
+                assert_eq!(set.remove(value), expected);
+            }
+            let sorted: Vec<i64> = reference
+                .iter()
+                .flat_map(|(v, c)| std::iter::repeat_n(*v, *c as usize))
+                .collect();
+            let probe = values[rng.gen_range(0..values.len())];
+            assert_eq!(set.len(), sorted.len() as u64);
+            let k = rng.gen_range(0..=sorted.len());
+            assert_eq!(set.kth(k as u64), sorted.get(k).copied());
+            assert_eq!(
+                set.rank(probe),
+                sorted.partition_point(|v| *v < probe) as u64
+            );
+            assert_eq!(
+                set.predecessor(probe),
+                reference.range(..probe).next_back().map(|(v, _)| *v)
+            );
+            assert_eq!(
+                set.successor(probe),
+                reference.range(probe + 1..).next().map(|(v, _)| *v)
+            );
+            let end = rng.gen_range(probe..=MAX);
+            assert_eq!(
+                set.count_range(probe..=end),
+                reference.range(probe..=end).map(|(_, c)| c).sum::<u64>()
+            );
+        }
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            reference.into_iter().collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,23 +1,41 @@
 #[cfg(feature = "pretty_print")]
-use colored::Colorize;
+use std::io::stdout;
 use std::{
     fmt::Debug,
-    io::{stdout, Write},
+    io::{self, Read, Write},
     ops::RangeInclusive,
+    thread,
 };
 
-use super::{contains, merge, split};
+#[cfg(feature = "pretty_print")]
+use super::render::RenderFormat;
+use crate::intervals::ops::{Checked, TryMonoid};
+
+use super::{
+    contains,
+    journal::{Journal, Token},
+    merge,
+    node::ArrayNode,
+    parallel_depth, query_batch,
+    render::{render, RenderOptions},
+    snapshot::{Codec, SnapshotError, SnapshotReader, SnapshotWriter, TreeKind},
+    split,
+    stats::{self, TreeStats},
+    validate::{validate, ValidationError},
+};
 
 /// The SegmentTree. Inspired by <https://cp-algorithms.com/data_structures/segment_tree.html>
 pub struct ArrayBasedSegmentTree<T: Debug + Default + Clone> {
     segments: Vec<T>,
-    merge_fn: Box<dyn Fn(T, T) -> T>,
+    merge_fn: Box<dyn Fn(T, T) -> T + Send + Sync>,
     size: usize,
+    /// The overwritten segments (index, old value) since the oldest live checkpoint
+    journal: Journal<(usize, T)>,
 }
 
 impl<T: Debug + Default + Clone> ArrayBasedSegmentTree<T> {
     /// Creates a new instance
-    pub fn new(values: &[T], merge_fn: Box<dyn Fn(T, T) -> T>) -> Self {
+    pub fn new(values: &[T], merge_fn: Box<dyn Fn(T, T) -> T + Send + Sync>) -> Self {
         let size = values.len();
         let mut segments: Vec<T> = vec![T::default(); 4 * size];
         ArrayBasedSegmentTree::initialize(
@@ -31,6 +49,7 @@ impl<T: Debug + Default + Clone> ArrayBasedSegmentTree<T> {
             segments,
             merge_fn,
             size,
+            journal: Journal::default(),
         }
     }
 
@@ -117,51 +136,48 @@ impl<T: Debug + Default + Clone> ArrayBasedSegmentTree<T> {
             merge(left, right, merge_fn)
         }
     }
-    #[cfg(feature = "pretty_print")]
-    fn pretty_print_to<W: Write>(&self, write: &mut W) {
-        fn pretty_print<T: Debug, W: Write>(
-            segments: &[T],
-            index: usize,
-            segment: RangeInclusive<usize>,
-            prefix: String,
-            last: bool,
-            write: &mut W,
-        ) {
-            let prefix_current = if last { "`- " } else { "|- " };
-            {
-                let segment = format!("{:?}", segment);
-                let value = format!("{:?}", segments[index]);
-                writeln!(
-                    write,
-                    "{}{}[{}] {} {}",
-                    prefix.green().bold(),
-                    prefix_current.green().bold(),
-                    segment.bold(),
-                    "=>".blue(),
-                    value.bold()
-                )
-                .expect("Write");
-            }
-            let prefix_child = if last { "   " } else { "|  " };
-            let prefix = prefix + prefix_child;
-            let start = *segment.start();
-            let end = *segment.end();
-            if start < end {
-                let (left, right) = split(start, end);
-                pretty_print(&segments, 2 * index + 1, left, prefix.clone(), false, write);
-                pretty_print(&segments, 2 * index + 2, right, prefix, true, write);
-            }
+    /// Renders the tree to the writer in the given format, see [`RenderOptions`] for limiting the output.
+    /// ```
+    /// # use datastructures_in_rust::intervals::segment_tree::array_based_segment_tree::ArrayBasedSegmentTree;
+    /// # use datastructures_in_rust::intervals::segment_tree::render::RenderFormat;
+    /// let st: ArrayBasedSegmentTree<u32> = ArrayBasedSegmentTree::new(&[1, 2, 3], Box::new(|a, b| a + b));
+    /// let mut output = vec![];
+    /// st.render(RenderFormat::Json, &mut output).unwrap();
+    /// ```
+    pub fn render<W: Write>(
+        &self,
+        options: impl Into<RenderOptions>,
+        write: &mut W,
+    ) -> io::Result<()> {
+        render(self.root(), "SEGMENT_TREE", options.into(), write)
+    }
+
+    /// Returns the shape and memory footprint of the tree.
+    /// The segments array is allocated upfront, so the heap bytes do not depend on the updates.
+    pub fn stats(&self) -> TreeStats {
+        TreeStats {
+            heap_bytes: self.segments.capacity() * std::mem::size_of::<T>(),
+            ..stats::collect(self.root())
+        }
+    }
+
+    /// Checks that every segment is the merge of its two halves.
+    /// Returns the first inconsistent segment found, an O(N) operation meant for tests and debugging.
+    pub fn validate(&self) -> Result<(), ValidationError<T>>
+    where
+        T: PartialEq,
+    {
+        validate(self.root(), self.merge_fn.as_ref())
+    }
+
+    fn root(&self) -> ArrayNode<'_, T> {
+        ArrayNode {
+            segments: &self.segments,
+            index: 0,
+            range: 0..=self.size - 1,
         }
-        writeln!(write, "{}", "SEGMENT_TREE".bold()).expect("write");
-        pretty_print(
-            &self.segments,
-            0,
-            0..=self.size - 1,
-            "  ".to_string(),
-            true,
-            write,
-        );
     }
+
     /// Used to pretty print the segment tree.
     /// This is an expensive operation and should be used only for debugging.
     /// ```ignore
@@ -180,7 +196,8 @@ impl<T: Debug + Default + Clone> ArrayBasedSegmentTree<T> {
     ///```
     #[cfg(feature = "pretty_print")]
     pub fn pretty_print(&self) {
-        self.pretty_print_to(&mut stdout())
+        self.render(RenderFormat::ColoredText, &mut stdout())
+            .expect("write")
     }
 
     pub fn update(&mut self, range: RangeInclusive<usize>, value: T) {
@@ -191,9 +208,30 @@ impl<T: Debug + Default + Clone> ArrayBasedSegmentTree<T> {
             0,
             value,
             &self.merge_fn,
+            &mut self.journal,
         );
     }
 
+    /// Starts recording the segments the updates overwrite, so that [`ArrayBasedSegmentTree::rollback`]
+    /// can restore the tree as it is now. Checkpoints can be nested.
+    pub fn checkpoint(&mut self) -> Token {
+        self.journal.checkpoint()
+    }
+
+    /// Restores the tree as it was when the checkpoint was taken, undoing the updates since then.
+    /// The checkpoints taken after this one are forgotten, this one can be rolled back to again.
+    /// Panics if the checkpoint was forgotten (by a rollback to an earlier one or by a commit).
+    pub fn rollback(&mut self, token: Token) {
+        for (index, value) in self.journal.rollback(token) {
+            self.segments[index] = value;
+        }
+    }
+
+    /// Forgets every checkpoint and stops recording the overwritten segments
+    pub fn commit(&mut self) {
+        self.journal.commit();
+    }
+
     fn update_with_segments(
         segments: &mut [T],
         update_range: &RangeInclusive<usize>,
@@ -201,6 +239,7 @@ impl<T: Debug + Default + Clone> ArrayBasedSegmentTree<T> {
         index: usize,
         value: T,
         merge_fn: &dyn Fn(T, T) -> T,
+        journal: &mut Journal<(usize, T)>,
     ) -> T {
         let ur_start = *update_range.start();
         let ur_end = *update_range.end();
@@ -210,6 +249,7 @@ impl<T: Debug + Default + Clone> ArrayBasedSegmentTree<T> {
         if ur_start > cr_end || ur_end < cr_start {
             return segments[index].clone();
         }
+        journal.record(|| (index, segments[index].clone()));
         if cr_end == cr_start {
             segments[index] = value;
             return segments[index].clone();
@@ -223,6 +263,7 @@ impl<T: Debug + Default + Clone> ArrayBasedSegmentTree<T> {
             2 * index + 1,
             value.clone(),
             merge_fn,
+            journal,
         );
         let right = ArrayBasedSegmentTree::update_with_segments(
             segments,
@@ -231,19 +272,248 @@ impl<T: Debug + Default + Clone> ArrayBasedSegmentTree<T> {
             2 * index + 2,
             value,
             merge_fn,
+            journal,
         );
         segments[index] = merge_fn(left, right);
         segments[index].clone()
     }
 }
 
+impl<T: Debug + Default + Clone + Send + Sync> ArrayBasedSegmentTree<T> {
+    /// Creates a new instance, building independent subtrees in parallel.
+    /// Produces the same tree as [`ArrayBasedSegmentTree::new`].
+    pub fn build(values: &[T], merge_fn: Box<dyn Fn(T, T) -> T + Send + Sync>) -> Self {
+        let size = values.len();
+        let depth = parallel_depth(size);
+        if depth == 0 {
+            return ArrayBasedSegmentTree::new(values, merge_fn);
+        }
+        let mut subtrees = vec![];
+        ArrayBasedSegmentTree::<T>::collect_subtrees(0..=size - 1, 0, depth, &mut subtrees);
+        let merge_fn_ref: &(dyn Fn(T, T) -> T + Send + Sync) = merge_fn.as_ref();
+        // Each subtree is built in its own array, with the subtree root at index 0
+        let built: Vec<(RangeInclusive<usize>, usize, Vec<T>)> = thread::scope(|s| {
+            let handles: Vec<_> = subtrees
+                .into_iter()
+                .map(|(range, index)| {
+                    s.spawn(move || {
+                        let mut local = vec![T::default(); 4 * (range.end() - range.start() + 1)];
+                        ArrayBasedSegmentTree::initialize(
+                            values,
+                            &mut local,
+                            range.clone(),
+                            0,
+                            merge_fn_ref,
+                        );
+                        (range, index, local)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("build thread panicked"))
+                .collect()
+        });
+        let mut segments: Vec<T> = vec![T::default(); 4 * size];
+        for (range, index, mut local) in built {
+            ArrayBasedSegmentTree::copy_subtree(&mut segments, index, &mut local, 0, range);
+        }
+        ArrayBasedSegmentTree::merge_top(&mut segments, 0..=size - 1, 0, depth, merge_fn_ref);
+        ArrayBasedSegmentTree {
+            segments,
+            merge_fn,
+            size,
+            journal: Journal::default(),
+        }
+    }
+
+    /// Queries each of the ranges, spreading large batches across threads.
+    /// The results are in the same order as the ranges.
+    pub fn query_batch(&self, ranges: &[RangeInclusive<usize>]) -> Vec<Option<T>> {
+        query_batch(ranges, |range| self.query(range.clone()))
+    }
+
+    /// Collects the roots of the subtrees at the given depth (or leaves above it)
+    fn collect_subtrees(
+        range: RangeInclusive<usize>,
+        index: usize,
+        depth: usize,
+        subtrees: &mut Vec<(RangeInclusive<usize>, usize)>,
+    ) {
+        let (start, end) = (*range.start(), *range.end());
+        if depth == 0 || start == end {
+            subtrees.push((range, index));
+        } else {
+            let (left, right) = split(start, end);
+            ArrayBasedSegmentTree::<T>::collect_subtrees(left, 2 * index + 1, depth - 1, subtrees);
+            ArrayBasedSegmentTree::<T>::collect_subtrees(right, 2 * index + 2, depth - 1, subtrees);
+        }
+    }
+
+    /// Moves a subtree built with its root at `local_index` into `segments` at `index`
+    fn copy_subtree(
+        segments: &mut [T],
+        index: usize,
+        local: &mut [T],
+        local_index: usize,
+        range: RangeInclusive<usize>,
+    ) {
+        segments[index] = std::mem::take(&mut local[local_index]);
+        let (start, end) = (*range.start(), *range.end());
+        if start < end {
+            let (left, right) = split(start, end);
+            ArrayBasedSegmentTree::copy_subtree(
+                segments,
+                2 * index + 1,
+                local,
+                2 * local_index + 1,
+                left,
+            );
+            ArrayBasedSegmentTree::copy_subtree(
+                segments,
+                2 * index + 2,
+                local,
+                2 * local_index + 2,
+                right,
+            );
+        }
+    }
+
+    /// Merges the segments above the subtrees that were built in parallel
+    fn merge_top(
+        segments: &mut [T],
+        range: RangeInclusive<usize>,
+        index: usize,
+        depth: usize,
+        merge_fn: &dyn Fn(T, T) -> T,
+    ) {
+        let (start, end) = (*range.start(), *range.end());
+        if depth == 0 || start == end {
+            return;
+        }
+        let (left, right) = split(start, end);
+        ArrayBasedSegmentTree::merge_top(segments, left, 2 * index + 1, depth - 1, merge_fn);
+        ArrayBasedSegmentTree::merge_top(segments, right, 2 * index + 2, depth - 1, merge_fn);
+        segments[index] = merge_fn(
+            segments[2 * index + 1].clone(),
+            segments[2 * index + 2].clone(),
+        );
+    }
+}
+
+impl<M: TryMonoid + Debug> ArrayBasedSegmentTree<Checked<M, M::Error>> {
+    /// Queries the value of the range, failing if merging its values failed (e.g. overflowed)
+    pub fn try_query(&self, range: RangeInclusive<usize>) -> Result<Option<M>, M::Error> {
+        self.query(range).map(|aggregate| aggregate.0).transpose()
+    }
+
+    /// Updates the range, failing if the aggregate of the whole tree can not be computed afterwards.
+    /// The elements are updated either way, the segments whose aggregate failed hold the error
+    /// so that only the queries covering them fail.
+    pub fn try_update(&mut self, range: RangeInclusive<usize>, value: M) -> Result<(), M::Error> {
+        self.update(range, Checked::from(value));
+        self.segments[0].0.clone().map(|_| ())
+    }
+}
+
+impl<T: Debug + Default + Clone + Codec> ArrayBasedSegmentTree<T> {
+    /// Saves a snapshot of the tree to the writer.
+    /// The segments are written in pre-order, see [`snapshot`](super::snapshot) for the format.
+    pub fn save_to<W: Write>(&self, writer: W) -> Result<(), SnapshotError> {
+        let mut writer = SnapshotWriter::new::<T>(writer, TreeKind::ArrayBased)?;
+        (self.size as u64).encode(&mut writer)?;
+        self.save_segments(&mut writer, 0, 0..=self.size - 1)?;
+        writer.finish()
+    }
+
+    fn save_segments<W: Write>(
+        &self,
+        writer: &mut W,
+        index: usize,
+        range: RangeInclusive<usize>,
+    ) -> io::Result<()> {
+        self.segments[index].encode(writer)?;
+        let (start, end) = (*range.start(), *range.end());
+        if start < end {
+            let (left, right) = split(start, end);
+            self.save_segments(writer, 2 * index + 1, left)?;
+            self.save_segments(writer, 2 * index + 2, right)?;
+        }
+        Ok(())
+    }
+
+    /// Loads a tree from a snapshot written by [`ArrayBasedSegmentTree::save_to`].
+    /// The merge function has to be the one the saved tree was created with.
+    pub fn load_from<R: Read>(
+        reader: R,
+        merge_fn: Box<dyn Fn(T, T) -> T + Send + Sync>,
+    ) -> Result<Self, SnapshotError> {
+        let mut reader = SnapshotReader::new::<T>(reader, TreeKind::ArrayBased)?;
+        let size = usize::decode(&mut reader)?;
+        let segments_len = size
+            .checked_mul(4)
+            .filter(|len| *len > 0)
+            .ok_or_else(|| SnapshotError::InvalidData(format!("Invalid size {}", size)))?;
+        // the size can't be trusted before the nodes are read and the checksum is verified,
+        // so the nodes are read first and only then placed in an array sized after it
+        let mut nodes = Vec::new();
+        ArrayBasedSegmentTree::load_nodes(&mut reader, &mut nodes, 0..=size - 1)?;
+        reader.finish()?;
+        let mut segments = vec![T::default(); segments_len];
+        let mut nodes = nodes.into_iter();
+        ArrayBasedSegmentTree::place_nodes(&mut nodes, &mut segments, 0, 0..=size - 1);
+        Ok(ArrayBasedSegmentTree {
+            segments,
+            merge_fn,
+            size,
+            journal: Journal::default(),
+        })
+    }
+
+    /// Reads the nodes of the range in pre-order
+    fn load_nodes<R: Read>(
+        reader: &mut R,
+        nodes: &mut Vec<T>,
+        range: RangeInclusive<usize>,
+    ) -> Result<(), SnapshotError> {
+        nodes.push(T::decode(reader)?);
+        let (start, end) = (*range.start(), *range.end());
+        if start < end {
+            let (left, right) = split(start, end);
+            ArrayBasedSegmentTree::load_nodes(reader, nodes, left)?;
+            ArrayBasedSegmentTree::load_nodes(reader, nodes, right)?;
+        }
+        Ok(())
+    }
+
+    /// Places the nodes read by `load_nodes` at their indices
+    fn place_nodes(
+        nodes: &mut impl Iterator<Item = T>,
+        segments: &mut [T],
+        index: usize,
+        range: RangeInclusive<usize>,
+    ) {
+        segments[index] = nodes.next().expect("a node per segment");
+        let (start, end) = (*range.start(), *range.end());
+        if start < end {
+            let (left, right) = split(start, end);
+            ArrayBasedSegmentTree::place_nodes(nodes, segments, 2 * index + 1, left);
+            ArrayBasedSegmentTree::place_nodes(nodes, segments, 2 * index + 2, right);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use colored::Colorize;
     use std::io::Write;
     use std::ops::RangeInclusive;
 
+    use rand::{thread_rng, Rng};
+
     use crate::intervals::segment_tree::array_based_segment_tree::ArrayBasedSegmentTree;
+    use crate::intervals::segment_tree::render::{RenderFormat, RenderOptions};
+    use crate::intervals::segment_tree::validate::ValidationError;
 
     fn sum(range: RangeInclusive<usize>, items: &[u32]) -> u32 {
         let v = &items[range];
@@ -355,6 +625,123 @@ mod tests {
         assert_eq!(st.query(2..=4), Some(sum(2..=4, values.as_slice())));
     }
 
+    #[test]
+    fn render_plain_text_works() {
+        let values: Vec<u32> = (1..=3).collect();
+        let st: ArrayBasedSegmentTree<u32> =
+            ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
+        let mut output: Vec<u8> = vec![];
+        st.render(RenderFormat::PlainText, &mut output)
+            .expect("write");
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "SEGMENT_TREE\n  `- [0..=2] => 6\n     |- [0..=1] => 3\n     |  |- [0..=0] => 1\n     |  `- [1..=1] => 2\n     `- [2..=2] => 3\n"
+        );
+        let mut output: Vec<u8> = vec![];
+        st.render(
+            RenderOptions::new(RenderFormat::PlainText).max_depth(1),
+            &mut output,
+        )
+        .expect("write");
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "SEGMENT_TREE\n  `- [0..=2] => 6\n     |- [0..=1] => 3\n     |  |- ...\n     |  `- ...\n     `- [2..=2] => 3\n"
+        );
+    }
+
+    #[test]
+    fn render_json_works() {
+        let values: Vec<u32> = (1..=3).collect();
+        let st: ArrayBasedSegmentTree<u32> =
+            ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
+        let mut output: Vec<u8> = vec![];
+        st.render(
+            RenderOptions::new(RenderFormat::Json).max_nodes(4),
+            &mut output,
+        )
+        .expect("write");
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                r#"{"tree":"SEGMENT_TREE","root":{"range":[0,2],"value":6,"children":["#,
+                r#"{"range":[0,1],"value":3,"children":["#,
+                r#"{"range":[0,0],"value":1,"children":[]},"#,
+                r#"{"range":[1,1],"value":2,"children":[]}]}],"truncated":true}}"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn validate_works() {
+        let values: Vec<u32> = (1..=5).collect();
+        let mut st: ArrayBasedSegmentTree<u32> =
+            ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
+        assert_eq!(st.validate(), Ok(()));
+        st.update(1..=3, 7);
+        assert_eq!(st.validate(), Ok(()));
+        // segment 2 is 3..=4
+        st.segments[2] += 1;
+        assert_eq!(
+            st.validate(),
+            Err(ValidationError::InconsistentValue {
+                range: 3..=4,
+                expected: 12,
+                actual: 13
+            })
+        );
+    }
+
+    #[test]
+    fn rollback_restores_the_segments() {
+        let mut rng = thread_rng();
+        let values: Vec<i64> = (0..100).map(|_| rng.gen_range(-50..50)).collect();
+        let mut st = ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
+        st.update(0..=3, 1);
+        // nothing is recorded without a checkpoint
+        assert_eq!(st.journal.len(), 0);
+        let mut checkpoints = vec![];
+        for _ in 0..5 {
+            checkpoints.push((st.checkpoint(), st.segments.clone()));
+            for _ in 0..20 {
+                let start = rng.gen_range(0..100);
+                let end = rng.gen_range(start..100);
+                st.update(start..=end, rng.gen_range(-50..50));
+            }
+        }
+        // roll back to the checkpoints from the newest to the oldest, then again to the oldest one
+        checkpoints.insert(0, checkpoints[0].clone());
+        for (token, segments) in checkpoints.into_iter().rev() {
+            st.rollback(token);
+            assert_eq!(st.segments, segments);
+            assert_eq!(st.validate(), Ok(()));
+        }
+        st.commit();
+        st.update(0..=99, 0);
+        assert_eq!(st.journal.len(), 0);
+    }
+
+    #[test]
+    fn stats_works() {
+        let values: Vec<u32> = (1..=5).collect();
+        let st: ArrayBasedSegmentTree<u32> =
+            ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
+        let stats = st.stats();
+        assert_eq!(stats.node_count, 9);
+        assert_eq!(stats.leaf_count, 5);
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(stats.pending_updates, 0);
+        assert_eq!(stats.heap_bytes, 20 * 4);
+    }
+
+    #[test]
+    fn build_matches_new() {
+        let values: Vec<u64> = (1..=100_000).collect();
+        let st = ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
+        let parallel_st = ArrayBasedSegmentTree::build(&values, Box::new(|a, b| a + b));
+        assert_eq!(st.segments, parallel_st.segments);
+    }
+
     #[test]
     fn pretty_print_prints_correctly() {
         let values: Vec<u32> = (1..=3).collect();
@@ -362,7 +749,8 @@ mod tests {
             ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
         let mut actual_write_buffer: Vec<u8> = vec![];
         let mut expected_write_buffer: Vec<u8> = vec![];
-        st.pretty_print_to(&mut actual_write_buffer);
+        st.render(RenderFormat::ColoredText, &mut actual_write_buffer)
+            .expect("write");
         expect_output(&mut expected_write_buffer, "SEGMENT_TREE");
         assert_eq!(actual_write_buffer, expected_write_buffer);
     }
@@ -0,0 +1,255 @@
+//! Coverage segment tree, the length covered by a set of intervals under additions and removals.
+//! The building block of the sweep line for the area of a union of rectangles.
+use std::ops::{Range, RangeInclusive};
+
+use super::split;
+
+/// Tracks the total length covered by the intervals added (and not removed yet).
+/// The coordinates are compressed: the tree has a leaf per gap between consecutive coordinates,
+/// and every node keeps the number of intervals covering it entirely along with its covered length.
+/// The counts are never pushed down, an interval covering a node is only counted at that node.
+/// ```
+/// # use datastructures_in_rust::intervals::segment_tree::coverage_segment_tree::CoverageSegmentTree;
+/// let mut tree = CoverageSegmentTree::new(&[0, 10, 5, 20]);
+/// tree.add(0..10);
+/// tree.add(5..20);
+/// assert_eq!(tree.covered_length(), 20);
+/// tree.remove(0..10);
+/// assert_eq!(tree.covered_length(), 15);
+/// ```
+pub struct CoverageSegmentTree {
+    /// The sorted distinct coordinates, leaf `i` covers `coordinates[i]..coordinates[i + 1]`
+    coordinates: Vec<i64>,
+    /// The number of intervals covering the whole node
+    counts: Vec<u32>,
+    /// The length of the node covered by the intervals
+    covered: Vec<u64>,
+}
+
+impl CoverageSegmentTree {
+    /// Creates an instance over the given coordinates, the intervals have to start and end at them
+    pub fn new(coordinates: &[i64]) -> Self {
+        let mut coordinates = coordinates.to_vec();
+        coordinates.sort_unstable();
+        coordinates.dedup();
+        let segments = 4 * coordinates.len().saturating_sub(1);
+        CoverageSegmentTree {
+            coordinates,
+            counts: vec![0; segments],
+            covered: vec![0; segments],
+        }
+    }
+
+    /// The length covered by at least one interval
+    pub fn covered_length(&self) -> u64 {
+        self.covered.first().copied().unwrap_or(0)
+    }
+
+    /// Adds the interval, it may overlap the intervals added before.
+    /// Panics if an end of the interval is not one of the coordinates.
+    pub fn add(&mut self, interval: Range<i64>) {
+        if let Some(leaves) = self.leaves(&interval) {
+            self.update(&leaves, self.root(), 0, true);
+        }
+    }
+
+    /// Removes an interval that was added before.
+    /// Panics if the interval was not added or if an end of the interval is not one of the coordinates.
+    pub fn remove(&mut self, interval: Range<i64>) {
+        if let Some(leaves) = self.leaves(&interval) {
+            self.update(&leaves, self.root(), 0, false);
+        }
+    }
+
+    fn root(&self) -> RangeInclusive<usize> {
+        0..=self.coordinates.len() - 2
+    }
+
+    /// The leaves covered by the interval, None if it is empty
+    fn leaves(&self, interval: &Range<i64>) -> Option<RangeInclusive<usize>> {
+        if interval.start >= interval.end {
+            return None;
+        }
+        let index = |coordinate: i64| {
+            self.coordinates
+                .binary_search(&coordinate)
+                .unwrap_or_else(|_| panic!("{} is not one of the coordinates", coordinate))
+        };
+        Some(index(interval.start)..=index(interval.end) - 1)
+    }
+
+    fn update(
+        &mut self,
+        leaves: &RangeInclusive<usize>,
+        current: RangeInclusive<usize>,
+        index: usize,
+        add: bool,
+    ) {
+        let (start, end) = (*current.start(), *current.end());
+        // no overlap
+        if *leaves.start() > end || *leaves.end() < start {
+            return;
+        }
+        if *leaves.start() <= start && end <= *leaves.end() {
+            self.counts[index] = if add {
+                self.counts[index] + 1
+            } else {
+                self.counts[index]
+                    .checked_sub(1)
+                    .expect("removing an interval that was not added")
+            };
+        } else {
+            let (left, right) = split(start, end);
+            self.update(leaves, left, 2 * index + 1, add);
+            self.update(leaves, right, 2 * index + 2, add);
+        }
+        self.covered[index] = if self.counts[index] > 0 {
+            self.length(start..=end)
+        } else if start == end {
+            0
+        } else {
+            self.covered[2 * index + 1] + self.covered[2 * index + 2]
+        };
+    }
+
+    /// The length between the coordinates at the ends of the leaves
+    fn length(&self, leaves: RangeInclusive<usize>) -> u64 {
+        let start = self.coordinates[*leaves.start()] as i128;
+        let end = self.coordinates[*leaves.end() + 1] as i128;
+        (end - start) as u64
+    }
+}
+
+/// The area covered by the union of the rectangles, given as two opposite corners `(x1, y1, x2, y2)`.
+/// Sweeps a vertical line over the x coordinates of the edges, the covered length of the line comes from a
+/// [`CoverageSegmentTree`] over the y coordinates. O(N logN) for N rectangles.
+/// ```
+/// # use datastructures_in_rust::intervals::segment_tree::coverage_segment_tree::rectangle_union_area;
+/// assert_eq!(rectangle_union_area(&[(0, 0, 2, 2), (1, 0, 2, 3), (1, 0, 3, 1)]), 6);
+/// ```
+pub fn rectangle_union_area(rectangles: &[(i64, i64, i64, i64)]) -> u128 {
+    // (x, is the left edge, y1, y2)
+    let mut edges = Vec::with_capacity(2 * rectangles.len());
+    let mut ys = Vec::with_capacity(2 * rectangles.len());
+    for &(x1, y1, x2, y2) in rectangles {
+        let (x1, x2) = (x1.min(x2), x1.max(x2));
+        let (y1, y2) = (y1.min(y2), y1.max(y2));
+        if x1 == x2 || y1 == y2 {
+            continue;
+        }
+        edges.push((x1, true, y1, y2));
+        edges.push((x2, false, y1, y2));
+        ys.extend([y1, y2]);
+    }
+    edges.sort_unstable_by_key(|(x, _, _, _)| *x);
+    let mut tree = CoverageSegmentTree::new(&ys);
+    let mut area = 0;
+    let mut previous_x = edges.first().map_or(0, |(x, _, _, _)| *x);
+    for (x, left_edge, y1, y2) in edges {
+        area += tree.covered_length() as u128 * (x as i128 - previous_x as i128) as u128;
+        previous_x = x;
+        if left_edge {
+            tree.add(y1..y2);
+        } else {
+            tree.remove(y1..y2);
+        }
+    }
+    area
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::{thread_rng, Rng};
+
+    use super::{rectangle_union_area, CoverageSegmentTree};
+
+    #[test]
+    fn covered_length_works() {
+        let mut tree = CoverageSegmentTree::new(&[0, 3, 5, 8, 12]);
+        assert_eq!(tree.covered_length(), 0);
+        tree.add(0..5);
+        tree.add(3..8);
+        assert_eq!(tree.covered_length(), 8);
+        tree.add(3..5);
+        tree.remove(3..8);
+        assert_eq!(tree.covered_length(), 5);
+        tree.add(8..12);
+        tree.remove(0..5);
+        assert_eq!(tree.covered_length(), 6);
+        tree.remove(3..5);
+        tree.remove(8..12);
+        assert_eq!(tree.covered_length(), 0);
+        // empty intervals are ignored
+        tree.add(5..5);
+        assert_eq!(tree.covered_length(), 0);
+    }
+
+    #[test]
+    fn extreme_coordinates_work() {
+        let mut tree = CoverageSegmentTree::new(&[i64::MIN, i64::MAX]);
+        tree.add(i64::MIN..i64::MAX);
+        assert_eq!(tree.covered_length(), u64::MAX);
+        assert_eq!(
+            rectangle_union_area(&[(0, i64::MIN, 2, i64::MAX)]),
+            2 * u64::MAX as u128
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn removing_an_interval_that_was_not_added_panics() {
+        let mut tree = CoverageSegmentTree::new(&[0, 3, 5]);
+        tree.add(0..3);
+        tree.remove(3..5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn intervals_have_to_end_at_the_coordinates() {
+        CoverageSegmentTree::new(&[0, 3, 5]).add(0..4);
+    }
+
+    #[test]
+    fn rectangle_union_area_works() {
+        assert_eq!(rectangle_union_area(&[]), 0);
+        assert_eq!(
+            rectangle_union_area(&[(0, 0, 1_000_000_000, 1_000_000_000)]),
+            1_000_000_000_000_000_000
+        );
+        // the corners can be in any order, empty rectangles cover nothing
+        assert_eq!(rectangle_union_area(&[(2, 2, 0, 0), (5, 5, 5, 9)]), 4);
+    }
+
+    #[test]
+    fn rectangle_union_area_matches_grid() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let rectangles: Vec<(i64, i64, i64, i64)> = (0..rng.gen_range(1..8))
+                .map(|_| {
+                    (
+                        rng.gen_range(-10..10),
+                        rng.gen_range(-10..10),
+                        rng.gen_range(-10..10),
+                        rng.gen_range(-10..10),
+                    )
+                })
+                .collect();
+            let mut cells = HashSet::new();
+            for &(x1, y1, x2, y2) in rectangles.iter() {
+                for x in x1.min(x2)..x1.max(x2) {
+                    for y in y1.min(y2)..y1.max(y2) {
+                        cells.insert((x, y));
+                    }
+                }
+            }
+            assert_eq!(
+                rectangle_union_area(&rectangles),
+                cells.len() as u128,
+                "{:?}",
+                rectangles
+            );
+        }
+    }
+}
@@ -1,13 +1,30 @@
 #[cfg(feature = "pretty_print")]
-use colored::Colorize;
-#[cfg(feature = "pretty_print")]
 use std::io::stdout;
-#[cfg(feature = "pretty_print")]
-use std::io::Write;
 
-use std::{fmt::Debug, ops::RangeInclusive, rc::Rc};
+use std::{
+    fmt::Debug,
+    io::{self, Read, Write},
+    ops::RangeInclusive,
+    thread,
+};
+
+#[cfg(feature = "pretty_print")]
+use super::render::RenderFormat;
+use crate::intervals::ops::{Checked, TryMonoid};
 
-use super::merge;
+use super::{
+    merge, midpoint,
+    node::NodeView,
+    parallel_depth, query_batch,
+    render::{render, RenderOptions},
+    snapshot::{
+        Codec, SnapshotError, SnapshotReader, SnapshotWriter, TreeKind, HAS_LEFT_CHILD,
+        HAS_RIGHT_CHILD,
+    },
+    stats::{self, TreeStats},
+    validate::{validate, ValidationError},
+    MergeFn,
+};
 
 /// Dynamic Segment Tree
 /// https://cp-algorithms.com/data_structures/segment_tree.html#toc-tgt-13
@@ -17,26 +34,44 @@ pub struct DynamicSegmentTree<T: Debug + Default + Clone> {
     left: i64,
     right: i64,
     value: T,
-    merge_fn: Rc<dyn Fn(T, T) -> T>,
+    merge_fn: MergeFn<T>,
 }
 
 impl<T: Debug + Default + Clone> Debug for DynamicSegmentTree<T> {
+    /// A summary of the tree, use [`DynamicSegmentTree::render`] to see all the nodes
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!(
-            "[left={}, right={}, value={:?}]\nleft_child={:?}\nright_child={:?}",
-            self.left, self.right, self.value, self.left_child, self.right_child
-        ))
+        let stats = self.stats();
+        f.debug_struct("DynamicSegmentTree")
+            .field("range", &self.range())
+            .field("value", &self.value)
+            .field("node_count", &stats.node_count)
+            .field("max_depth", &stats.max_depth)
+            .finish()
+    }
+}
+
+impl<T: Debug + Default + Clone> NodeView<T> for &DynamicSegmentTree<T> {
+    fn range(&self) -> RangeInclusive<i64> {
+        self.left..=self.right
+    }
+
+    fn value(&self) -> &T {
+        &self.value
+    }
+
+    fn children(&self) -> (Option<Self>, Option<Self>) {
+        (self.left_child.as_deref(), self.right_child.as_deref())
     }
 }
 
 impl<T: Debug + Default + Clone> DynamicSegmentTree<T> {
     /// Creates an instance of a Dynamic Segment Tree
-    pub fn new(range: RangeInclusive<i64>, merge_fn: Rc<dyn Fn(T, T) -> T>) -> Self {
+    pub fn new(range: RangeInclusive<i64>, merge_fn: MergeFn<T>) -> Self {
         DynamicSegmentTree::inner_new(range, merge_fn)
     }
 
     /// Creates an instance of a Dynamic Segment Tree
-    pub fn new_with_values(values: &[T], merge_fn: Rc<dyn Fn(T, T) -> T>) -> Self {
+    pub fn new_with_values(values: &[T], merge_fn: MergeFn<T>) -> Self {
         let mut dst = DynamicSegmentTree::new(0..=((values.len() - 1) as i64), merge_fn);
         values.iter().enumerate().for_each(|(i, v)| {
             dst.insert(i as i64, v.clone());
@@ -44,7 +79,12 @@ impl<T: Debug + Default + Clone> DynamicSegmentTree<T> {
         dst
     }
 
-    fn inner_new(range: RangeInclusive<i64>, merge_fn: Rc<dyn Fn(T, T) -> T>) -> Self {
+    /// returns the range for self
+    pub fn range(&self) -> RangeInclusive<i64> {
+        self.left..=self.right
+    }
+
+    fn inner_new(range: RangeInclusive<i64>, merge_fn: MergeFn<T>) -> Self {
         DynamicSegmentTree {
             left_child: None,
             right_child: None,
@@ -55,44 +95,62 @@ impl<T: Debug + Default + Clone> DynamicSegmentTree<T> {
         }
     }
 
-    fn extend_if_needed(&mut self) {
-        if self.left_child.is_none() && self.left < self.right {
-            let delta = (self.right - self.left) / 2;
-            let mid = self.left + delta;
-            // extend the left one
-            self.left_child = Some(Box::new(DynamicSegmentTree::inner_new(
-                self.left..=mid,
-                self.merge_fn.clone(),
-            )));
-            // extend the left one
-            self.right_child = Some(Box::new(DynamicSegmentTree::inner_new(
-                mid + 1..=self.right,
-                self.merge_fn.clone(),
-            )));
+    /// Returns the child covering the index, creating it if it does not exist yet.
+    /// The second element is true if the child was created.
+    fn child_for(&mut self, index: i64) -> (&mut DynamicSegmentTree<T>, bool) {
+        let mid = midpoint(self.left, self.right);
+        let (child, range) = if index <= mid {
+            (&mut self.left_child, self.left..=mid)
+        } else {
+            (&mut self.right_child, mid + 1..=self.right)
+        };
+        let created = child.is_none();
+        let merge_fn = &self.merge_fn;
+        let child = child.get_or_insert_with(|| {
+            Box::new(DynamicSegmentTree::inner_new(range, merge_fn.clone()))
+        });
+        (child, created)
+    }
+
+    /// Sets the element at the index to `value`, or merges it into the existing element if `accumulate` is true
+    fn write(&mut self, index: i64, value: T, accumulate: bool) {
+        if index < self.left || index > self.right {
+            return;
+        }
+        if self.left == self.right {
+            self.value = if accumulate {
+                self.merge_fn.as_ref()(self.value.clone(), value)
+            } else {
+                value
+            };
+            return;
         }
+        let (child, created) = self.child_for(index);
+        // There is nothing to merge with in a new child
+        child.write(index, value, accumulate && !created);
+        // The value is recomputed from the children in index order, so that the merge function need not be commutative
+        self.value = merge(
+            self.left_child.as_ref().map(|c| c.value.clone()),
+            self.right_child.as_ref().map(|c| c.value.clone()),
+            self.merge_fn.as_ref(),
+        )
+        .unwrap_or_default();
     }
 
+    /// Merges the value into the one at a given index, the same as [`DynamicSegmentTree::insert`]
     pub fn update(&mut self, index: i64, value: T) {
         self.insert(index, value);
     }
-    /// Inserts a value at a given index
+
+    /// Inserts a value at a given index, merging it (as `merge(existing, value)`) into the existing one.
+    /// Only the nodes on the path to the index are created.
     pub fn insert(&mut self, index: i64, value: T) {
-        // extend if needed.
-        self.extend_if_needed();
-        // merge the value
-        self.value = self.merge_fn.as_ref()(self.value.clone(), value.clone());
-
-        if let (Some(left_child), Some(right_child)) =
-            (self.left_child.as_mut(), self.right_child.as_mut())
-        {
-            // insert on the left half, if it belongs to the left half
-            if index <= left_child.right {
-                left_child.insert(index, value)
-            } else {
-                // or it is in the right half
-                right_child.insert(index, value);
-            }
-        }
+        self.write(index, value, true);
+    }
+
+    /// Sets the value at a given index, replacing the existing one
+    pub fn set(&mut self, index: i64, value: T) {
+        self.write(index, value, false);
     }
 
     /// Queries the value of a given range
@@ -108,58 +166,237 @@ impl<T: Debug + Default + Clone> DynamicSegmentTree<T> {
             // if there is no overlap return none
         } else if q_start > self.right || q_end < self.left {
             return None;
-            // if there is overlap, recurse.
-        } else if let (Some(left_child), Some(right_child)) =
-            (self.left_child.as_ref(), self.right_child.as_ref())
-        {
-            return merge(
-                left_child.query(q_start..=q_end),
-                right_child.query(q_start..=q_end),
-                self.merge_fn.as_ref(),
-            );
         }
-        None
+        // if there is overlap, recurse into the children that exist, the missing ones hold no elements.
+        merge(
+            self.left_child
+                .as_ref()
+                .and_then(|c| c.query(q_start..=q_end)),
+            self.right_child
+                .as_ref()
+                .and_then(|c| c.query(q_start..=q_end)),
+            self.merge_fn.as_ref(),
+        )
     }
 
-    #[cfg(feature = "pretty_print")]
-    fn pretty_print_to<W: Write>(&self, write: &mut W) {
-        fn pretty_print<T: Debug + Default + Clone, W: Write>(
-            node: &DynamicSegmentTree<T>,
-            prefix: String,
-            last: bool,
-            write: &mut W,
-        ) {
-            let prefix_current = if last { "`- " } else { "|- " };
-            {
-                let segment = format!("{}..={}", node.left, node.right);
-                let value = format!("{:?}", node.value);
-                writeln!(
-                    write,
-                    "{}{}[{}] {} {}",
-                    prefix.green().bold(),
-                    prefix_current.green().bold(),
-                    segment.bold(),
-                    "=>".blue(),
-                    value.bold()
-                )
-                .expect("Write");
-            }
-            let prefix_child = if last { "   " } else { "|  " };
-            let prefix = prefix + prefix_child;
-            if let (Some(left_child), Some(right_child)) =
-                (node.left_child.as_ref(), node.right_child.as_ref())
-            {
-                pretty_print(left_child, prefix.clone(), false, write);
-                pretty_print(right_child, prefix, true, write);
-            }
+    /// Returns the smallest index `i` for which `predicate(query(start..=i))` holds, `start` being the start of the range.
+    /// The predicate has to be monotone (once it holds, it holds for every larger index), e.g. "the count is above k".
+    /// Descends the tree once, an O(logN) operation.
+    pub fn find_prefix<F: Fn(&T) -> bool>(&self, predicate: F) -> Option<i64> {
+        self.find_prefix_after(&mut None, &predicate)
+    }
+
+    /// `prefix` is the value of the elements before self, it is extended with self if the predicate does not hold
+    fn find_prefix_after<F: Fn(&T) -> bool>(
+        &self,
+        prefix: &mut Option<T>,
+        predicate: &F,
+    ) -> Option<i64> {
+        let with_self = merge(
+            prefix.clone(),
+            Some(self.value.clone()),
+            self.merge_fn.as_ref(),
+        );
+        if !with_self.as_ref().is_some_and(predicate) {
+            *prefix = with_self;
+            return None;
+        }
+        if self.left == self.right {
+            return Some(self.left);
+        }
+        // The children that do not exist hold no elements
+        self.left_child
+            .iter()
+            .chain(self.right_child.iter())
+            .find_map(|child| child.find_prefix_after(prefix, predicate))
+    }
+
+    /// Returns the shape and memory footprint of the tree.
+    /// The heap bytes count the boxed child nodes, the root is owned by the caller.
+    pub fn stats(&self) -> TreeStats {
+        let stats = stats::collect(self);
+        TreeStats {
+            heap_bytes: (stats.node_count - 1) * std::mem::size_of::<Self>(),
+            ..stats
         }
-        writeln!(write, "{}", "DYNAMIC_SEGMENT_TREE".bold()).expect("write");
-        pretty_print(&self, "  ".to_string(), true, write);
+    }
+
+    /// Checks the invariants of every node: its value is the merge of its children
+    /// and the children split its range into halves.
+    /// Returns the first offending node found, an O(N) operation meant for tests and debugging.
+    pub fn validate(&self) -> Result<(), ValidationError<T>>
+    where
+        T: PartialEq,
+    {
+        validate(self, self.merge_fn.as_ref())
+    }
+
+    /// Renders the tree to the writer in the given format, see [`RenderOptions`] for limiting the output.
+    pub fn render<W: Write>(
+        &self,
+        options: impl Into<RenderOptions>,
+        write: &mut W,
+    ) -> io::Result<()> {
+        render(self, "DYNAMIC_SEGMENT_TREE", options.into(), write)
     }
 
     #[cfg(feature = "pretty_print")]
     pub fn pretty_print(&self) {
-        self.pretty_print_to(&mut stdout())
+        self.render(RenderFormat::ColoredText, &mut stdout())
+            .expect("write")
+    }
+}
+
+impl<T: Debug + Default + Clone + Send + Sync> DynamicSegmentTree<T> {
+    /// Creates an instance of a Dynamic Segment Tree, building independent subtrees in parallel.
+    /// Produces the same tree as [`DynamicSegmentTree::new_with_values`].
+    pub fn build(values: &[T], merge_fn: MergeFn<T>) -> Self {
+        let depth = parallel_depth(values.len());
+        DynamicSegmentTree::build_range(values, 0..=((values.len() - 1) as i64), &merge_fn, depth)
+    }
+
+    fn build_range(
+        values: &[T],
+        range: RangeInclusive<i64>,
+        merge_fn: &MergeFn<T>,
+        depth: usize,
+    ) -> Self {
+        let mut node = DynamicSegmentTree::inner_new(range, merge_fn.clone());
+        if node.left == node.right {
+            node.value = values[node.left as usize].clone();
+            return node;
+        }
+        let mid = midpoint(node.left, node.right);
+        let (left, right) = (node.left..=mid, mid + 1..=node.right);
+        let (left_child, right_child) = if depth > 0 {
+            thread::scope(|s| {
+                let left_child =
+                    s.spawn(|| DynamicSegmentTree::build_range(values, left, merge_fn, depth - 1));
+                let right_child =
+                    DynamicSegmentTree::build_range(values, right, merge_fn, depth - 1);
+                (
+                    left_child.join().expect("build thread panicked"),
+                    right_child,
+                )
+            })
+        } else {
+            (
+                DynamicSegmentTree::build_range(values, left, merge_fn, 0),
+                DynamicSegmentTree::build_range(values, right, merge_fn, 0),
+            )
+        };
+        node.value = merge_fn(left_child.value.clone(), right_child.value.clone());
+        node.left_child = Some(Box::new(left_child));
+        node.right_child = Some(Box::new(right_child));
+        node
+    }
+
+    /// Queries each of the ranges, spreading large batches across threads.
+    /// The results are in the same order as the ranges.
+    pub fn query_batch(&self, ranges: &[RangeInclusive<i64>]) -> Vec<Option<T>> {
+        query_batch(ranges, |range| self.query(range.clone()))
+    }
+}
+
+impl<M: TryMonoid + Debug> DynamicSegmentTree<Checked<M, M::Error>> {
+    /// Queries the value of the range, failing if merging its values failed (e.g. overflowed)
+    pub fn try_query(&self, range: RangeInclusive<i64>) -> Result<Option<M>, M::Error> {
+        self.query(range).map(|aggregate| aggregate.0).transpose()
+    }
+
+    /// Sets the value at the index, failing if the aggregate of the whole tree can not be computed afterwards.
+    /// The element is updated either way, the nodes whose aggregate failed hold the error
+    /// so that only the queries covering them fail.
+    pub fn try_set(&mut self, index: i64, value: M) -> Result<(), M::Error> {
+        self.set(index, Checked::from(value));
+        self.value.0.clone().map(|_| ())
+    }
+
+    /// Merges the value into the one at the index, the same as [`DynamicSegmentTree::try_insert`]
+    pub fn try_update(&mut self, index: i64, value: M) -> Result<(), M::Error> {
+        self.try_insert(index, value)
+    }
+
+    /// Merges the value into the one at the index, failing like [`DynamicSegmentTree::try_set`]
+    pub fn try_insert(&mut self, index: i64, value: M) -> Result<(), M::Error> {
+        self.insert(index, Checked::from(value));
+        self.value.0.clone().map(|_| ())
+    }
+}
+
+impl<T: Debug + Default + Clone + Codec> DynamicSegmentTree<T> {
+    /// Saves a snapshot of the tree to the writer.
+    /// Only the nodes that were created are written (in pre-order), see [`snapshot`](super::snapshot) for the format.
+    pub fn save_to<W: Write>(&self, writer: W) -> Result<(), SnapshotError> {
+        let mut writer = SnapshotWriter::new::<T>(writer, TreeKind::Dynamic)?;
+        self.left.encode(&mut writer)?;
+        self.right.encode(&mut writer)?;
+        self.save_node(&mut writer)?;
+        writer.finish()
+    }
+
+    fn save_node<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut flags = 0;
+        if self.left_child.is_some() {
+            flags |= HAS_LEFT_CHILD;
+        }
+        if self.right_child.is_some() {
+            flags |= HAS_RIGHT_CHILD;
+        }
+        flags.encode(writer)?;
+        self.value.encode(writer)?;
+        if let Some(left_child) = self.left_child.as_ref() {
+            left_child.save_node(writer)?;
+        }
+        if let Some(right_child) = self.right_child.as_ref() {
+            right_child.save_node(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Loads a tree from a snapshot written by [`DynamicSegmentTree::save_to`].
+    /// The merge function has to be the one the saved tree was created with.
+    pub fn load_from<R: Read>(reader: R, merge_fn: MergeFn<T>) -> Result<Self, SnapshotError> {
+        let mut reader = SnapshotReader::new::<T>(reader, TreeKind::Dynamic)?;
+        let left = i64::decode(&mut reader)?;
+        let right = i64::decode(&mut reader)?;
+        if left > right {
+            return Err(SnapshotError::InvalidData(format!(
+                "Invalid range {}..={}",
+                left, right
+            )));
+        }
+        let tree = DynamicSegmentTree::load_node(&mut reader, left..=right, &merge_fn)?;
+        reader.finish()?;
+        Ok(tree)
+    }
+
+    fn load_node<R: Read>(
+        reader: &mut R,
+        range: RangeInclusive<i64>,
+        merge_fn: &MergeFn<T>,
+    ) -> Result<Self, SnapshotError> {
+        let mut node = DynamicSegmentTree::inner_new(range, merge_fn.clone());
+        let flags = u8::decode(reader)?;
+        let children = flags & (HAS_LEFT_CHILD | HAS_RIGHT_CHILD);
+        if flags != children || (children != 0 && node.left == node.right) {
+            return Err(SnapshotError::InvalidData(format!(
+                "Invalid flags {:#b} for node {}..={}",
+                flags, node.left, node.right
+            )));
+        }
+        node.value = T::decode(reader)?;
+        let mid = midpoint(node.left, node.right);
+        if flags & HAS_LEFT_CHILD != 0 {
+            let left_child = DynamicSegmentTree::load_node(reader, node.left..=mid, merge_fn)?;
+            node.left_child = Some(Box::new(left_child));
+        }
+        if flags & HAS_RIGHT_CHILD != 0 {
+            let right_child =
+                DynamicSegmentTree::load_node(reader, mid + 1..=node.right, merge_fn)?;
+            node.right_child = Some(Box::new(right_child));
+        }
+        Ok(node)
     }
 }
 
@@ -255,18 +492,21 @@ mod tests {
     }
 
     mod dynamic_segment_tree {
-        use std::rc::Rc;
+        use std::sync::Arc;
 
-        use crate::intervals::segment_tree::dynamic_segment_tree::{
-            tests::{expect_output, get_child, sum, LeftOrRight},
-            DynamicSegmentTree,
+        use crate::intervals::segment_tree::{
+            dynamic_segment_tree::{
+                tests::{expect_output, get_child, sum, LeftOrRight},
+                DynamicSegmentTree,
+            },
+            render::RenderFormat,
         };
 
         #[test]
         fn initializes_correctly() {
             let values: Vec<u32> = (1..=5).collect();
             let mut dst: DynamicSegmentTree<u32> =
-                DynamicSegmentTree::new(0..=4, Rc::new(|a, b| a + b));
+                DynamicSegmentTree::new(0..=4, Arc::new(|a, b| a + b));
             for (i, v) in values.iter().enumerate() {
                 dst.insert(i as i64, *v);
             }
@@ -317,8 +557,8 @@ mod tests {
                 ],
                 &dst,
             );
-            assert_eq!(left_child.is_none(), true);
-            assert_eq!(right_child.is_none(), true);
+            assert!(left_child.is_none());
+            assert!(right_child.is_none());
             let left_child = get_child(
                 vec![
                     LeftOrRight::Left,
@@ -337,8 +577,8 @@ mod tests {
                 ],
                 &dst,
             );
-            assert_eq!(left_child.is_none(), true);
-            assert_eq!(right_child.is_none(), true);
+            assert!(left_child.is_none());
+            assert!(right_child.is_none());
             let left_child = get_child(
                 vec![LeftOrRight::Left, LeftOrRight::Right, LeftOrRight::Left],
                 &dst,
@@ -347,8 +587,8 @@ mod tests {
                 vec![LeftOrRight::Left, LeftOrRight::Right, LeftOrRight::Right],
                 &dst,
             );
-            assert_eq!(left_child.is_none(), true);
-            assert_eq!(right_child.is_none(), true);
+            assert!(left_child.is_none());
+            assert!(right_child.is_none());
             let left_child = get_child(
                 vec![LeftOrRight::Right, LeftOrRight::Right, LeftOrRight::Left],
                 &dst,
@@ -357,8 +597,8 @@ mod tests {
                 vec![LeftOrRight::Right, LeftOrRight::Right, LeftOrRight::Right],
                 &dst,
             );
-            assert_eq!(left_child.is_none(), true);
-            assert_eq!(right_child.is_none(), true);
+            assert!(left_child.is_none());
+            assert!(right_child.is_none());
             let left_child = get_child(
                 vec![LeftOrRight::Right, LeftOrRight::Left, LeftOrRight::Left],
                 &dst,
@@ -367,8 +607,8 @@ mod tests {
                 vec![LeftOrRight::Right, LeftOrRight::Left, LeftOrRight::Right],
                 &dst,
             );
-            assert_eq!(left_child.is_none(), true);
-            assert_eq!(right_child.is_none(), true);
+            assert!(left_child.is_none());
+            assert!(right_child.is_none());
             let left_child = get_child(vec![LeftOrRight::Right, LeftOrRight::Left], &dst).unwrap();
             let right_child =
                 get_child(vec![LeftOrRight::Right, LeftOrRight::Right], &dst).unwrap();
@@ -382,27 +622,112 @@ mod tests {
         fn query_works() {
             let values: Vec<u32> = (1..=11).collect();
             let mut dst: DynamicSegmentTree<u32> =
-                DynamicSegmentTree::new(0..=10, Rc::new(|a, b| a + b));
+                DynamicSegmentTree::new(0..=10, Arc::new(|a, b| a + b));
             for (i, v) in values.iter().enumerate() {
                 dst.insert(i as i64, *v);
             }
+            assert_eq!(dst.validate(), Ok(()));
             assert_eq!(dst.query(0..=5), Some(sum(0..=5, values.as_slice())));
             assert_eq!(dst.query(2..=5), Some(sum(2..=5, values.as_slice())));
             assert_eq!(dst.query(5..=10), Some(sum(5..=10, values.as_slice())));
             assert_eq!(dst.query(6..=9), Some(sum(6..=9, values.as_slice())));
         }
 
+        #[test]
+        fn insert_merges_and_set_replaces() {
+            let mut dst: DynamicSegmentTree<u32> =
+                DynamicSegmentTree::new(0..=100, Arc::new(|a, b| a + b));
+            dst.insert(40, 2);
+            dst.insert(40, 3);
+            assert_eq!(dst.query(40..=40), Some(5));
+            dst.set(40, 7);
+            assert_eq!(dst.query(0..=100), Some(7));
+            assert_eq!(dst.query(0..=39), None);
+            dst.set(101, 1);
+            assert_eq!(dst.query(0..=100), Some(7));
+            // update merges, as it did before set was added
+            dst.update(40, 1);
+            dst.update(60, 2);
+            assert_eq!(dst.query(0..=100), Some(10));
+            assert_eq!(dst.validate(), Ok(()));
+        }
+
+        #[test]
+        fn values_are_merged_in_index_order() {
+            let mut dst: DynamicSegmentTree<String> =
+                DynamicSegmentTree::new(0..=1_000, Arc::new(|a, b| a + &b));
+            for (index, word) in [(900, "d"), (7, "a"), (500, "c"), (8, "b")] {
+                dst.insert(index, word.to_string());
+            }
+            assert_eq!(dst.query(0..=1_000), Some("abcd".to_string()));
+            assert_eq!(dst.query(8..=900), Some("bcd".to_string()));
+            assert_eq!(dst.query(0..=7), Some("a".to_string()));
+            dst.insert(8, "e".to_string());
+            assert_eq!(dst.query(0..=1_000), Some("abecd".to_string()));
+            assert_eq!(dst.validate(), Ok(()));
+        }
+
+        #[test]
+        fn find_prefix_works() {
+            let mut dst: DynamicSegmentTree<u32> =
+                DynamicSegmentTree::new(0..=1_000_000, Arc::new(|a, b| a + b));
+            for (index, count) in [(5, 2), (70, 1), (900_000, 3)] {
+                dst.insert(index, count);
+            }
+            assert_eq!(dst.find_prefix(|count| *count > 0), Some(5));
+            assert_eq!(dst.find_prefix(|count| *count > 1), Some(5));
+            assert_eq!(dst.find_prefix(|count| *count > 2), Some(70));
+            assert_eq!(dst.find_prefix(|count| *count > 3), Some(900_000));
+            assert_eq!(dst.find_prefix(|count| *count > 6), None);
+            dst.set(5, 0);
+            assert_eq!(dst.find_prefix(|count| *count > 0), Some(70));
+        }
+
+        #[test]
+        fn stats_works() {
+            let mut dst: DynamicSegmentTree<u32> =
+                DynamicSegmentTree::new(0..=1_000_000, Arc::new(|a, b| a + b));
+            assert_eq!(dst.stats().node_count, 1);
+            assert_eq!(dst.stats().heap_bytes, 0);
+            dst.insert(10, 1);
+            let stats = dst.stats();
+            // only the path from the root to the leaf
+            assert_eq!(stats.max_depth, 20);
+            assert_eq!(stats.node_count, 21);
+            assert_eq!(stats.leaf_count, 1);
+            assert_eq!(stats.pending_updates, 0);
+            assert_eq!(
+                stats.heap_bytes,
+                20 * std::mem::size_of::<DynamicSegmentTree<u32>>()
+            );
+            assert_eq!(
+                format!("{:?}", dst),
+                "DynamicSegmentTree { range: 0..=1000000, value: 1, node_count: 21, max_depth: 20 }"
+            );
+        }
+
+        #[test]
+        fn build_matches_new_with_values() {
+            let values: Vec<u64> = (1..=100_000).collect();
+            let dst = DynamicSegmentTree::new_with_values(&values, Arc::new(|a, b| a + b));
+            let parallel_dst = DynamicSegmentTree::build(&values, Arc::new(|a, b| a + b));
+            for (start, end) in [(0, 99_999), (1, 2), (17, 65_537), (50_000, 50_000)] {
+                assert_eq!(dst.query(start..=end), parallel_dst.query(start..=end));
+            }
+        }
+
         #[test]
         fn pretty_print_works() {
             let values: Vec<u32> = (1..=3).collect();
             let mut dst: DynamicSegmentTree<u32> =
-                DynamicSegmentTree::new(0..=2, Rc::new(|a, b| a + b));
+                DynamicSegmentTree::new(0..=2, Arc::new(|a, b| a + b));
             for (i, v) in values.iter().enumerate() {
                 dst.insert(i as i64, *v);
             }
             let mut actual_write_buffer: Vec<u8> = vec![];
             let mut expected_write_buffer: Vec<u8> = vec![];
-            dst.pretty_print_to(&mut actual_write_buffer);
+            dst.render(RenderFormat::ColoredText, &mut actual_write_buffer)
+                .expect("write");
             expect_output(&mut expected_write_buffer, "DYNAMIC_SEGMENT_TREE");
             assert_eq!(actual_write_buffer, expected_write_buffer);
         }
@@ -1,13 +1,32 @@
 #[cfg(feature = "pretty_print")]
-use colored::Colorize;
-#[cfg(feature = "pretty_print")]
 use std::io::stdout;
-#[cfg(feature = "pretty_print")]
-use std::io::Write;
 
-use std::{fmt::Debug, ops::RangeInclusive, rc::Rc};
+use std::{
+    fmt::Debug,
+    io::{self, Read, Write},
+    ops::RangeInclusive,
+    thread,
+};
+
+#[cfg(feature = "pretty_print")]
+use super::render::RenderFormat;
+use crate::intervals::ops::{Checked, TryMonoid};
 
-use super::merge;
+use super::{
+    journal::{Journal, Token},
+    merge, midpoint,
+    node::{NodeView, PendingUpdate},
+    parallel_depth, query_batch, range_len,
+    render::{render, RenderOptions},
+    repeat,
+    snapshot::{
+        Codec, SnapshotError, SnapshotReader, SnapshotWriter, TreeKind, HAS_LEFT_CHILD,
+        HAS_PENDING_ADD, HAS_PENDING_UPDATE, HAS_RIGHT_CHILD,
+    },
+    stats::{self, TreeStats},
+    validate::{validate, ValidationError},
+    MergeFn,
+};
 
 /// Dynamic Segment Tree
 /// https://cp-algorithms.com/data_structures/segment_tree.html#toc-tgt-13
@@ -17,8 +36,21 @@ pub struct DynamicSegmentTreeWithRangeUpdates<T: Debug + Default + Clone> {
     left: i64,
     right: i64,
     value: T,
-    pending_child_update: Option<T>,
-    merge_fn: Rc<dyn Fn(T, T) -> T>,
+    pending_child_update: Option<PendingUpdate<T>>,
+    merge_fn: MergeFn<T>,
+    /// Adds to the elements, only for the trees created with [`DynamicSegmentTreeWithRangeUpdates::new_with_add`]
+    add_fn: Option<MergeFn<T>>,
+    /// The undo log of the tree, only the root has one
+    journal: Option<Box<Journal<NodeState<T>>>>,
+}
+
+/// The state of a node before an update overwrote it
+struct NodeState<T> {
+    left: i64,
+    right: i64,
+    value: T,
+    pending_child_update: Option<PendingUpdate<T>>,
+    had_children: bool,
 }
 
 impl<T: Debug + Default + Clone> Debug for DynamicSegmentTreeWithRangeUpdates<T> {
@@ -35,14 +67,50 @@ impl<T: Debug + Default + Clone> Debug for DynamicSegmentTreeWithRangeUpdates<T>
     }
 }
 
+impl<T: Debug + Default + Clone> NodeView<T> for &DynamicSegmentTreeWithRangeUpdates<T> {
+    const HAS_PENDING_UPDATES: bool = true;
+
+    fn range(&self) -> RangeInclusive<i64> {
+        self.left..=self.right
+    }
+
+    fn value(&self) -> &T {
+        &self.value
+    }
+
+    fn pending_update(&self) -> Option<&PendingUpdate<T>> {
+        self.pending_child_update.as_ref()
+    }
+
+    fn add_fn(&self) -> Option<&(dyn Fn(T, T) -> T + Send + Sync)> {
+        self.add_fn.as_deref()
+    }
+
+    fn children(&self) -> (Option<Self>, Option<Self>) {
+        (self.left_child.as_deref(), self.right_child.as_deref())
+    }
+}
+
 impl<T: Debug + Default + Clone> DynamicSegmentTreeWithRangeUpdates<T> {
-    /// Creates an instance of a Dynamic Segment Tree
-    pub fn new(range: RangeInclusive<i64>, merge_fn: Rc<dyn Fn(T, T) -> T>) -> Self {
-        DynamicSegmentTreeWithRangeUpdates::inner_new(range, merge_fn)
+    /// Creates an instance of a Dynamic Segment Tree.
+    /// The range can hold up to `u64::MAX` elements, `i64::MIN..=i64::MAX - 1` but not every `i64`.
+    pub fn new(range: RangeInclusive<i64>, merge_fn: MergeFn<T>) -> Self {
+        DynamicSegmentTreeWithRangeUpdates::inner_new(range, merge_fn, None)
+    }
+
+    /// Creates an instance that can also add to the elements of a range, see [`DynamicSegmentTreeWithRangeUpdates::add`].
+    /// `add_fn` adds to a single element. It has to distribute over the merge function,
+    /// `merge(add(a, x), add(b, y)) == add(merge(a, b), merge(x, y))`, as `+` does over sum, min and max.
+    pub fn new_with_add(
+        range: RangeInclusive<i64>,
+        merge_fn: MergeFn<T>,
+        add_fn: MergeFn<T>,
+    ) -> Self {
+        DynamicSegmentTreeWithRangeUpdates::inner_new(range, merge_fn, Some(add_fn))
     }
 
     /// Creates an instance of a Dynamic Segment Tree
-    pub fn new_with_values(values: &[T], merge_fn: Rc<dyn Fn(T, T) -> T>) -> Self {
+    pub fn new_with_values(values: &[T], merge_fn: MergeFn<T>) -> Self {
         let mut dst =
             DynamicSegmentTreeWithRangeUpdates::new(0..=((values.len() - 1) as i64), merge_fn);
         values.iter().enumerate().for_each(|(i, v)| {
@@ -52,7 +120,11 @@ impl<T: Debug + Default + Clone> DynamicSegmentTreeWithRangeUpdates<T> {
         dst
     }
 
-    fn inner_new(range: RangeInclusive<i64>, merge_fn: Rc<dyn Fn(T, T) -> T>) -> Self {
+    fn inner_new(
+        range: RangeInclusive<i64>,
+        merge_fn: MergeFn<T>,
+        add_fn: Option<MergeFn<T>>,
+    ) -> Self {
         DynamicSegmentTreeWithRangeUpdates {
             left_child: None,
             right_child: None,
@@ -60,22 +132,25 @@ impl<T: Debug + Default + Clone> DynamicSegmentTreeWithRangeUpdates<T> {
             right: *range.end(),
             pending_child_update: None,
             merge_fn,
+            add_fn,
             value: T::default(),
+            journal: None,
         }
     }
 
     fn extend_if_needed(&mut self) {
         if self.left_child.is_none() && self.left < self.right {
-            let delta = (self.right - self.left) / 2;
-            let mid = self.left + delta;
+            let mid = midpoint(self.left, self.right);
             // extend the children
             self.left_child = Some(Box::new(DynamicSegmentTreeWithRangeUpdates::inner_new(
                 self.left..=mid,
                 self.merge_fn.clone(),
+                self.add_fn.clone(),
             )));
             self.right_child = Some(Box::new(DynamicSegmentTreeWithRangeUpdates::inner_new(
                 mid + 1..=self.right,
                 self.merge_fn.clone(),
+                self.add_fn.clone(),
             )));
         }
     }
@@ -100,145 +175,576 @@ impl<T: Debug + Default + Clone> DynamicSegmentTreeWithRangeUpdates<T> {
         self.left == self.right
     }
 
-    /// Inserts a value at a given range
+    /// returns the number of elements in the range of self
+    fn len(&self) -> u64 {
+        range_len(self.left, self.right)
+    }
+
+    /// Assigns the value to every element in the range of self.
+    /// The children (if any) are updated lazily, when they are visited next.
+    fn assign(&mut self, value: T) {
+        self.value = repeat(value.clone(), self.len(), self.merge_fn.as_ref());
+        if !self.is_leaf() {
+            self.pending_child_update = Some(PendingUpdate::Assign(value));
+        }
+    }
+
+    /// Adds the delta to every element in the range of self.
+    /// The children (if any) are updated lazily, when they are visited next.
+    fn add_to(&mut self, delta: T) {
+        let add_fn = self
+            .add_fn
+            .clone()
+            .expect("the tree was created with an add function");
+        let total = repeat(delta.clone(), self.len(), self.merge_fn.as_ref());
+        self.value = add_fn(self.value.clone(), total);
+        if !self.is_leaf() {
+            // Compose with the update the children have not received yet
+            self.pending_child_update = Some(match self.pending_child_update.take() {
+                None => PendingUpdate::Add(delta),
+                Some(PendingUpdate::Assign(v)) => PendingUpdate::Assign(add_fn(v, delta)),
+                Some(PendingUpdate::Add(d)) => PendingUpdate::Add(add_fn(d, delta)),
+            });
+        }
+    }
+
+    /// Applies the update to every element in the range of self
+    fn apply(&mut self, update: PendingUpdate<T>) {
+        match update {
+            PendingUpdate::Assign(value) => self.assign(value),
+            PendingUpdate::Add(delta) => self.add_to(delta),
+        }
+    }
+
+    /// The state of self to record before overwriting it
+    fn state(&self) -> NodeState<T> {
+        NodeState {
+            left: self.left,
+            right: self.right,
+            value: self.value.clone(),
+            pending_child_update: self.pending_child_update.clone(),
+            had_children: self.left_child.is_some(),
+        }
+    }
+
+    /// Applies the pending update (if any) to the children
+    fn push_pending_update(&mut self, journal: Option<&mut Journal<NodeState<T>>>) {
+        if let Some(update) = self.pending_child_update.take() {
+            if let (Some(left_child), Some(right_child)) =
+                (self.left_child.as_mut(), self.right_child.as_mut())
+            {
+                if let Some(journal) = journal {
+                    journal.record(|| left_child.state());
+                    journal.record(|| right_child.state());
+                }
+                left_child.apply(update.clone());
+                right_child.apply(update);
+            }
+        }
+    }
+
+    /// Assigns the value to every element of the given range, replacing what they held.
+    /// Each element holds the value, so a query merges it once per element: over a sum tree,
+    /// `update(0..=9, Sum(1))` makes `query(0..=9)` return `Sum(10)`.
     pub fn update(&mut self, range: RangeInclusive<i64>, value: T) {
+        let mut journal = self.journal.take();
+        self.update_node(range, PendingUpdate::Assign(value), journal.as_deref_mut());
+        self.journal = journal;
+    }
+
+    /// Adds the delta to every element of the given range.
+    /// Panics if the tree was not created with [`DynamicSegmentTreeWithRangeUpdates::new_with_add`].
+    pub fn add(&mut self, range: RangeInclusive<i64>, delta: T) {
+        assert!(
+            self.add_fn.is_some(),
+            "adding requires a tree created with new_with_add"
+        );
+        let mut journal = self.journal.take();
+        self.update_node(range, PendingUpdate::Add(delta), journal.as_deref_mut());
+        self.journal = journal;
+    }
+
+    fn update_node(
+        &mut self,
+        range: RangeInclusive<i64>,
+        update: PendingUpdate<T>,
+        mut journal: Option<&mut Journal<NodeState<T>>>,
+    ) {
         // if outside bounds, nothing to do here.
         if !self.overlaps_range(&range) {
             return;
         }
-        // extend if needed.
-        self.extend_if_needed();
+        if let Some(journal) = journal.as_deref_mut() {
+            journal.record(|| self.state());
+        }
         let up_r_left = *range.start();
         let up_r_right = *range.end();
-        // set the pending update value will be propagated to children as neccesary
-        if !self.is_leaf() {
-            self.pending_child_update = Some(value.clone());
-        }
         // if the update range is bigger than the current range update and return
         if up_r_left <= self.left && up_r_right >= self.right {
-            self.value = value;
+            self.apply(update);
             return;
         }
         // else we need to visit the children
+        // extend if needed.
+        self.extend_if_needed();
+        // Any earlier update of the whole range has to reach the children before they are partially updated
+        self.push_pending_update(journal.as_deref_mut());
         if let (Some(left_child), Some(right_child)) =
             (self.left_child.as_mut(), self.right_child.as_mut())
         {
-            // Take the new value for children
-            let new_value = self.pending_child_update.take().unwrap();
-            // There are three possibilities now
-            // 1. It is just within left range
-            // 2. It is just within right range
-            // 3. It is split across left and right ranges
-            if left_child.contains_range(&range) {
-                // 1
-                left_child.update(range, new_value);
-            } else if right_child.contains_range(&range) {
-                // 2
-                right_child.update(range, new_value);
-            } else {
-                // 3
-                left_child.update(up_r_left..=left_child.right, new_value.clone());
-                right_child.update(right_child.left..=up_r_right, new_value);
-            }
+            // The children ignore the update if it is outside their range
+            left_child.update_node(range.clone(), update.clone(), journal.as_deref_mut());
+            right_child.update_node(range, update, journal);
             let merge_fn = self.merge_fn.as_ref();
             // If we updated the children, we need to update the value based on the updated children
             self.value = merge_fn(left_child.value.clone(), right_child.value.clone());
         }
     }
 
+    /// Starts recording the nodes the updates overwrite (including their pending updates and children),
+    /// so that [`DynamicSegmentTreeWithRangeUpdates::rollback`] can restore the tree exactly as it is now.
+    /// Checkpoints can be nested.
+    pub fn checkpoint(&mut self) -> Token {
+        self.journal
+            .get_or_insert_with(|| Box::new(Journal::default()))
+            .checkpoint()
+    }
+
+    /// Restores the tree as it was when the checkpoint was taken, undoing the updates since then.
+    /// The checkpoints taken after this one are forgotten, this one can be rolled back to again.
+    /// Panics if the checkpoint was forgotten (by a rollback to an earlier one or by a commit).
+    pub fn rollback(&mut self, token: Token) {
+        let mut journal = self
+            .journal
+            .take()
+            .expect("the checkpoint was rolled back past or committed");
+        for state in journal.rollback(token) {
+            self.node_mut(state.left, state.right).restore(state);
+        }
+        self.journal = Some(journal);
+    }
+
+    /// Forgets every checkpoint and stops recording the overwritten nodes
+    pub fn commit(&mut self) {
+        if let Some(journal) = self.journal.as_mut() {
+            journal.commit();
+        }
+    }
+
+    /// The node covering exactly `left..=right`, it has to exist
+    fn node_mut(&mut self, left: i64, right: i64) -> &mut Self {
+        let mut node = self;
+        while (node.left, node.right) != (left, right) {
+            let child = match node.left_child.as_mut() {
+                Some(child) if right <= child.right => child,
+                _ => node.right_child.as_mut().expect("recorded node exists"),
+            };
+            node = child.as_mut();
+        }
+        node
+    }
+
+    fn restore(&mut self, state: NodeState<T>) {
+        self.value = state.value;
+        self.pending_child_update = state.pending_child_update;
+        if !state.had_children {
+            self.left_child = None;
+            self.right_child = None;
+        }
+    }
+
     /// Queries the value of a given range
-    pub fn query(&mut self, range: RangeInclusive<i64>) -> Option<T> {
-        // println!("{:?}", range);
+    pub fn query(&self, range: RangeInclusive<i64>) -> Option<T> {
         // Invalid range
         if range.start() > range.end() {
             return None;
         }
-        let q_left = *range.start();
-        let q_right = *range.end();
-
+        // Only the part of the query range that overlaps with self matters
+        let q_left = *range.start().max(&self.left);
+        let q_right = *range.end().min(&self.right);
+        if q_left > q_right {
+            return None;
+        }
         // if the query range is bigger than the current range update and return
-        if q_left <= self.left && q_right >= self.right {
+        if q_left == self.left && q_right == self.right {
             return Some(self.value.clone());
         }
+        let times = range_len(q_left, q_right);
+        // if the children have not received the pending assignment yet, every element in the range has that value
+        if let Some(PendingUpdate::Assign(v)) = self.pending_child_update.as_ref() {
+            return Some(repeat(v.clone(), times, self.merge_fn.as_ref()));
+        }
         // else we need to visit the children
-        if let (Some(left_child), Some(right_child)) =
-            (self.left_child.as_mut(), self.right_child.as_mut())
+        let children = if let (Some(left_child), Some(right_child)) =
+            (self.left_child.as_ref(), self.right_child.as_ref())
         {
-            // if there are pending updates, apply them for children.
-            if let Some(v) = self.pending_child_update.take() {
-                left_child.update(left_child.range(), v.clone());
-                right_child.update(right_child.range(), v);
-            }
-            // There are three possibilities now
-            // 1. It is just within left
-            // 2. It is just within right
-            // 3. It is split across left and right
-            if left_child.contains_range(&range) {
-                // 1
-                left_child.query(range)
-            } else if right_child.contains_range(&range) {
-                // 2
-                right_child.query(range)
-            } else {
-                // 3
-                return merge(
-                    left_child.query(q_left..=left_child.right),
-                    right_child.query(right_child.left..=q_right),
-                    self.merge_fn.as_ref(),
-                );
-            }
+            merge(
+                left_child.query(q_left..=q_right),
+                right_child.query(q_left..=q_right),
+                self.merge_fn.as_ref(),
+            )
         } else {
             None
+        };
+        // the children have not received the pending addition yet
+        match (self.pending_child_update.as_ref(), self.add_fn.as_ref()) {
+            (Some(PendingUpdate::Add(delta)), Some(add_fn)) => {
+                let merge_fn = self.merge_fn.as_ref();
+                let children = children.unwrap_or_else(|| repeat(T::default(), times, merge_fn));
+                Some(add_fn(children, repeat(delta.clone(), times, merge_fn)))
+            }
+            _ => children,
         }
     }
 
-    #[cfg(feature = "pretty_print")]
-    fn pretty_print_to<W: Write>(&self, write: &mut W) {
-        fn pretty_print<T: Debug + Default + Clone, W: Write>(
-            node: &DynamicSegmentTreeWithRangeUpdates<T>,
-            prefix: String,
-            last: bool,
-            write: &mut W,
-        ) {
-            let prefix_current = if last { "`- " } else { "|- " };
-            {
-                let segment = format!("{}..={}", node.left, node.right);
-                let value = format!("{:?} (pending:{:?})", node.value, node.pending_child_update);
-                writeln!(
-                    write,
-                    "{}{}[{}] {} {}",
-                    prefix.green().bold(),
-                    prefix_current.green().bold(),
-                    segment.bold(),
-                    "=>".blue(),
-                    value.bold()
-                )
-                .expect("Write");
+    /// Returns the smallest index `i` for which `predicate(query(start..=i))` holds, `start` being the start of the range.
+    /// The elements that were never updated hold the default value.
+    /// The predicate has to be monotone (once it holds, it holds for every larger index), e.g. "the sum is above k".
+    /// Descends the tree once without applying the pending updates, a range holding a single value
+    /// is binary searched, an O(log²N) operation.
+    pub fn find_prefix<F: Fn(&T) -> bool>(&self, predicate: F) -> Option<i64> {
+        self.find_prefix_after(None, &mut None, &predicate)
+    }
+
+    /// `update` is the update of the ancestors that self has not received yet.
+    /// `prefix` is the value of the elements before self, it is extended with self if the predicate does not hold
+    fn find_prefix_after<F: Fn(&T) -> bool>(
+        &self,
+        update: Option<PendingUpdate<T>>,
+        prefix: &mut Option<T>,
+        predicate: &F,
+    ) -> Option<i64> {
+        let merge_fn = self.merge_fn.as_ref();
+        let add = |a: T, b: T| {
+            (self
+                .add_fn
+                .as_ref()
+                .expect("pending additions have an add function"))(a, b)
+        };
+        let value = match update.as_ref() {
+            Some(PendingUpdate::Assign(v)) => repeat(v.clone(), self.len(), merge_fn),
+            Some(PendingUpdate::Add(d)) => {
+                add(self.value.clone(), repeat(d.clone(), self.len(), merge_fn))
             }
-            let prefix_child = if last { "   " } else { "|  " };
-            let prefix = prefix + prefix_child;
-            if let (Some(left_child), Some(right_child)) =
-                (node.left_child.as_ref(), node.right_child.as_ref())
-            {
-                pretty_print(left_child, prefix.clone(), false, write);
-                pretty_print(right_child, prefix, true, write);
+            None => self.value.clone(),
+        };
+        let with_self = merge(prefix.clone(), Some(value), merge_fn);
+        if !with_self.as_ref().is_some_and(predicate) {
+            *prefix = with_self;
+            return None;
+        }
+        if self.is_leaf() {
+            return Some(self.left);
+        }
+        // The update of the ancestors is newer than the pending update of self
+        let update = match (update, self.pending_child_update.clone()) {
+            (Some(PendingUpdate::Add(d)), Some(PendingUpdate::Assign(v))) => {
+                Some(PendingUpdate::Assign(add(v, d)))
+            }
+            (Some(PendingUpdate::Add(d)), Some(PendingUpdate::Add(e))) => {
+                Some(PendingUpdate::Add(add(e, d)))
             }
+            (None, pending) => pending,
+            (update, _) => update,
+        };
+        match (update, self.left_child.as_ref(), self.right_child.as_ref()) {
+            (Some(PendingUpdate::Assign(v)), _, _) => {
+                self.find_prefix_in_uniform(v, prefix, predicate)
+            }
+            (update, Some(left_child), Some(right_child)) => left_child
+                .find_prefix_after(update.clone(), prefix, predicate)
+                .or_else(|| right_child.find_prefix_after(update, prefix, predicate)),
+            // The children were never created, every element holds the default value
+            (Some(PendingUpdate::Add(d)), _, _) => {
+                self.find_prefix_in_uniform(add(T::default(), d), prefix, predicate)
+            }
+            (None, _, _) => self.find_prefix_in_uniform(T::default(), prefix, predicate),
         }
-        writeln!(write, "{}", "DYNAMIC_SEGMENT_TREE".bold()).expect("write");
-        pretty_print(&self, "  ".to_string(), true, write);
+    }
+
+    /// Binary searches the range of self, every element of which holds `element`.
+    /// The predicate holds for the whole range.
+    fn find_prefix_in_uniform<F: Fn(&T) -> bool>(
+        &self,
+        element: T,
+        prefix: &Option<T>,
+        predicate: &F,
+    ) -> Option<i64> {
+        let merge_fn = self.merge_fn.as_ref();
+        let (mut low, mut high) = (1, self.len());
+        while low < high {
+            let count = low + (high - low) / 2;
+            let with_count = merge(
+                prefix.clone(),
+                Some(repeat(element.clone(), count, merge_fn)),
+                merge_fn,
+            );
+            if with_count.as_ref().is_some_and(predicate) {
+                high = count;
+            } else {
+                low = count + 1;
+            }
+        }
+        Some(self.left.wrapping_add_unsigned(low - 1))
+    }
+
+    /// Returns the shape and memory footprint of the tree.
+    /// The heap bytes count the boxed child nodes, the root is owned by the caller.
+    pub fn stats(&self) -> TreeStats {
+        let stats = stats::collect(self);
+        TreeStats {
+            heap_bytes: (stats.node_count - 1) * std::mem::size_of::<Self>(),
+            ..stats
+        }
+    }
+
+    /// Checks the invariants of every node: its value is the merge of its children (or of its pending update),
+    /// the children split its range into halves and leaves have no pending updates.
+    /// Returns the first offending node found, an O(N) operation meant for tests and debugging.
+    pub fn validate(&self) -> Result<(), ValidationError<T>>
+    where
+        T: PartialEq,
+    {
+        validate(self, self.merge_fn.as_ref())
+    }
+
+    /// Renders the tree to the writer in the given format, see [`RenderOptions`] for limiting the output.
+    pub fn render<W: Write>(
+        &self,
+        options: impl Into<RenderOptions>,
+        write: &mut W,
+    ) -> io::Result<()> {
+        render(self, "DYNAMIC_SEGMENT_TREE", options.into(), write)
     }
 
     #[cfg(feature = "pretty_print")]
     pub fn pretty_print(&self) {
-        self.pretty_print_to(&mut stdout())
+        self.render(RenderFormat::ColoredText, &mut stdout())
+            .expect("write")
+    }
+}
+
+impl<T: Debug + Default + Clone + Send + Sync> DynamicSegmentTreeWithRangeUpdates<T> {
+    /// Creates an instance of a Dynamic Segment Tree, building independent subtrees in parallel.
+    /// Produces a tree equivalent to [`DynamicSegmentTreeWithRangeUpdates::new_with_values`].
+    pub fn build(values: &[T], merge_fn: MergeFn<T>) -> Self {
+        let depth = parallel_depth(values.len());
+        DynamicSegmentTreeWithRangeUpdates::build_range(
+            values,
+            0..=((values.len() - 1) as i64),
+            &merge_fn,
+            depth,
+        )
+    }
+
+    fn build_range(
+        values: &[T],
+        range: RangeInclusive<i64>,
+        merge_fn: &MergeFn<T>,
+        depth: usize,
+    ) -> Self {
+        let mut node = DynamicSegmentTreeWithRangeUpdates::inner_new(range, merge_fn.clone(), None);
+        if node.is_leaf() {
+            node.value = values[node.left as usize].clone();
+            return node;
+        }
+        let mid = midpoint(node.left, node.right);
+        let (left, right) = (node.left..=mid, mid + 1..=node.right);
+        let build = DynamicSegmentTreeWithRangeUpdates::build_range;
+        let (left_child, right_child) = if depth > 0 {
+            thread::scope(|s| {
+                let left_child = s.spawn(|| build(values, left, merge_fn, depth - 1));
+                let right_child = build(values, right, merge_fn, depth - 1);
+                (
+                    left_child.join().expect("build thread panicked"),
+                    right_child,
+                )
+            })
+        } else {
+            (
+                build(values, left, merge_fn, 0),
+                build(values, right, merge_fn, 0),
+            )
+        };
+        node.value = merge_fn(left_child.value.clone(), right_child.value.clone());
+        node.left_child = Some(Box::new(left_child));
+        node.right_child = Some(Box::new(right_child));
+        node
+    }
+
+    /// Queries each of the ranges, spreading large batches across threads.
+    /// The results are in the same order as the ranges.
+    pub fn query_batch(&self, ranges: &[RangeInclusive<i64>]) -> Vec<Option<T>> {
+        query_batch(ranges, |range| self.query(range.clone()))
+    }
+}
+
+impl<M: TryMonoid + Debug> DynamicSegmentTreeWithRangeUpdates<Checked<M, M::Error>> {
+    /// Queries the value of the range, failing if merging its values failed (e.g. overflowed)
+    pub fn try_query(&self, range: RangeInclusive<i64>) -> Result<Option<M>, M::Error> {
+        self.query(range).map(|aggregate| aggregate.0).transpose()
+    }
+
+    /// Updates the range, failing if the aggregate of the whole tree can not be computed afterwards.
+    /// The elements are updated either way, the nodes whose aggregate failed hold the error
+    /// so that only the queries covering them fail.
+    pub fn try_update(&mut self, range: RangeInclusive<i64>, value: M) -> Result<(), M::Error> {
+        self.update(range, Checked::from(value));
+        self.value.0.clone().map(|_| ())
+    }
+}
+
+impl<T: Debug + Default + Clone + Codec> DynamicSegmentTreeWithRangeUpdates<T> {
+    /// Saves a snapshot of the tree to the writer, including the pending updates.
+    /// Only the nodes that were created are written (in pre-order), see [`snapshot`](super::snapshot) for the format.
+    pub fn save_to<W: Write>(&self, writer: W) -> Result<(), SnapshotError> {
+        let mut writer = SnapshotWriter::new::<T>(writer, TreeKind::DynamicWithRangeUpdates)?;
+        self.left.encode(&mut writer)?;
+        self.right.encode(&mut writer)?;
+        self.save_node(&mut writer)?;
+        writer.finish()
+    }
+
+    fn save_node<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut flags = 0;
+        if self.left_child.is_some() {
+            flags |= HAS_LEFT_CHILD;
+        }
+        if self.right_child.is_some() {
+            flags |= HAS_RIGHT_CHILD;
+        }
+        match self.pending_child_update {
+            Some(PendingUpdate::Assign(_)) => flags |= HAS_PENDING_UPDATE,
+            Some(PendingUpdate::Add(_)) => flags |= HAS_PENDING_UPDATE | HAS_PENDING_ADD,
+            None => {}
+        }
+        flags.encode(writer)?;
+        self.value.encode(writer)?;
+        if let Some(PendingUpdate::Assign(v) | PendingUpdate::Add(v)) =
+            self.pending_child_update.as_ref()
+        {
+            v.encode(writer)?;
+        }
+        if let Some(left_child) = self.left_child.as_ref() {
+            left_child.save_node(writer)?;
+        }
+        if let Some(right_child) = self.right_child.as_ref() {
+            right_child.save_node(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Loads a tree from a snapshot written by [`DynamicSegmentTreeWithRangeUpdates::save_to`].
+    /// The merge function has to be the one the saved tree was created with.
+    pub fn load_from<R: Read>(reader: R, merge_fn: MergeFn<T>) -> Result<Self, SnapshotError> {
+        DynamicSegmentTreeWithRangeUpdates::load(reader, merge_fn, None)
+    }
+
+    /// Loads a tree from a snapshot written by [`DynamicSegmentTreeWithRangeUpdates::save_to`],
+    /// for the trees created with [`DynamicSegmentTreeWithRangeUpdates::new_with_add`].
+    pub fn load_from_with_add<R: Read>(
+        reader: R,
+        merge_fn: MergeFn<T>,
+        add_fn: MergeFn<T>,
+    ) -> Result<Self, SnapshotError> {
+        DynamicSegmentTreeWithRangeUpdates::load(reader, merge_fn, Some(add_fn))
+    }
+
+    fn load<R: Read>(
+        reader: R,
+        merge_fn: MergeFn<T>,
+        add_fn: Option<MergeFn<T>>,
+    ) -> Result<Self, SnapshotError> {
+        let mut reader = SnapshotReader::new::<T>(reader, TreeKind::DynamicWithRangeUpdates)?;
+        let left = i64::decode(&mut reader)?;
+        let right = i64::decode(&mut reader)?;
+        if left > right {
+            return Err(SnapshotError::InvalidData(format!(
+                "Invalid range {}..={}",
+                left, right
+            )));
+        }
+        // the additions were added in version 2
+        let known_flags = match reader.version() {
+            1 => HAS_LEFT_CHILD | HAS_RIGHT_CHILD | HAS_PENDING_UPDATE,
+            _ => HAS_LEFT_CHILD | HAS_RIGHT_CHILD | HAS_PENDING_UPDATE | HAS_PENDING_ADD,
+        };
+        let tree = DynamicSegmentTreeWithRangeUpdates::load_node(
+            &mut reader,
+            left..=right,
+            &merge_fn,
+            &add_fn,
+            known_flags,
+        )?;
+        reader.finish()?;
+        Ok(tree)
+    }
+
+    fn load_node<R: Read>(
+        reader: &mut R,
+        range: RangeInclusive<i64>,
+        merge_fn: &MergeFn<T>,
+        add_fn: &Option<MergeFn<T>>,
+        known_flags: u8,
+    ) -> Result<Self, SnapshotError> {
+        let mut node =
+            DynamicSegmentTreeWithRangeUpdates::inner_new(range, merge_fn.clone(), add_fn.clone());
+        let flags = u8::decode(reader)?;
+        let is_add = flags & HAS_PENDING_ADD != 0;
+        if flags & !known_flags != 0
+            || (flags != 0 && node.is_leaf())
+            || (is_add && (flags & HAS_PENDING_UPDATE == 0 || add_fn.is_none()))
+        {
+            return Err(SnapshotError::InvalidData(format!(
+                "Invalid flags {:#b} for node {}..={}",
+                flags, node.left, node.right
+            )));
+        }
+        node.value = T::decode(reader)?;
+        if flags & HAS_PENDING_UPDATE != 0 {
+            let v = T::decode(reader)?;
+            node.pending_child_update = Some(if is_add {
+                PendingUpdate::Add(v)
+            } else {
+                PendingUpdate::Assign(v)
+            });
+        }
+        let mid = midpoint(node.left, node.right);
+        let load = DynamicSegmentTreeWithRangeUpdates::load_node;
+        if flags & HAS_LEFT_CHILD != 0 {
+            node.left_child = Some(Box::new(load(
+                reader,
+                node.left..=mid,
+                merge_fn,
+                add_fn,
+                known_flags,
+            )?));
+        }
+        if flags & HAS_RIGHT_CHILD != 0 {
+            node.right_child = Some(Box::new(load(
+                reader,
+                mid + 1..=node.right,
+                merge_fn,
+                add_fn,
+                known_flags,
+            )?));
+        }
+        Ok(node)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{cmp, ops::RangeInclusive, rc::Rc};
+    use std::{cmp, ops::RangeInclusive, sync::Arc};
+
+    use rand::{thread_rng, Rng};
 
-    use crate::intervals::segment_tree::dynamic_segment_tree_with_range_updates::DynamicSegmentTreeWithRangeUpdates;
+    use crate::intervals::segment_tree::{
+        dynamic_segment_tree_with_range_updates::DynamicSegmentTreeWithRangeUpdates,
+        node::PendingUpdate,
+        render::{RenderFormat, RenderOptions},
+        validate::ValidationError,
+    };
 
     fn update(items: &mut [u32], range: RangeInclusive<usize>, value: u32) {
         items[range].iter_mut().for_each(|i| *i = value);
@@ -254,7 +760,7 @@ mod tests {
         let mut values: Vec<u32> = vec![0; 14];
         //https://drive.google.com/file/d/1aURFiakwaUSisvfwwLboyfuQwiyZJuqQ/view?usp=sharing
         let mut dst: DynamicSegmentTreeWithRangeUpdates<u32> =
-            DynamicSegmentTreeWithRangeUpdates::new(0..=13, Rc::new(cmp::max));
+            DynamicSegmentTreeWithRangeUpdates::new(0..=13, Arc::new(cmp::max));
         for (i, v) in values.iter().enumerate() {
             dst.update(i as i64..=i as i64, *v);
         }
@@ -275,12 +781,13 @@ mod tests {
             (9..=10, 2),
         ];
 
-        updates.iter().enumerate().for_each(|(_i, (range, v))| {
+        updates.iter().for_each(|(range, v)| {
             let u = *v + max(&values, range.clone()).unwrap_or(0);
             update(&mut values, range.clone(), u);
             let (s, e) = (*range.start() as i64, *range.end() as i64);
             let c = dst.query(s..=e).unwrap_or(0);
             dst.update(s..=e, *v + c);
+            assert_eq!(dst.validate(), Ok(()));
             // dst.pretty_print();
             for from in 0..=13 {
                 for to in from + 1..=13 {
@@ -295,4 +802,324 @@ mod tests {
             }
         });
     }
+
+    #[test]
+    fn add_and_assign_match_brute_force() {
+        let mut rng = thread_rng();
+        let sum: fn(i64, i64) -> i64 = |a, b| a + b;
+        for (merge_fn, name) in [(sum, "sum"), (cmp::min, "min"), (cmp::max, "max")] {
+            let mut values = vec![0i64; 50];
+            let mut dst: DynamicSegmentTreeWithRangeUpdates<i64> =
+                DynamicSegmentTreeWithRangeUpdates::new_with_add(
+                    0..=49,
+                    Arc::new(merge_fn),
+                    Arc::new(|a, b| a + b),
+                );
+            dst.update(0..=49, 0);
+            for _ in 0..500 {
+                let start = rng.gen_range(0..50);
+                let end = rng.gen_range(start..50);
+                let v = rng.gen_range(-20..20);
+                if rng.gen_bool(0.3) {
+                    dst.update(start as i64..=end as i64, v);
+                    values[start..=end].iter_mut().for_each(|e| *e = v);
+                } else {
+                    dst.add(start as i64..=end as i64, v);
+                    values[start..=end].iter_mut().for_each(|e| *e += v);
+                }
+                assert_eq!(dst.validate(), Ok(()), "{}", name);
+                let start = rng.gen_range(0..50);
+                let end = rng.gen_range(start..50);
+                assert_eq!(
+                    dst.query(start as i64..=end as i64),
+                    values[start..=end].iter().copied().reduce(merge_fn),
+                    "{} of {}..={}",
+                    name,
+                    start,
+                    end
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn find_prefix_matches_brute_force() {
+        let mut rng = thread_rng();
+        let mut values = vec![0u64; 64];
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<u64> =
+            DynamicSegmentTreeWithRangeUpdates::new_with_add(
+                0..=63,
+                Arc::new(|a, b| a + b),
+                Arc::new(|a, b| a + b),
+            );
+        for _ in 0..300 {
+            let start = rng.gen_range(0..64);
+            let end = rng.gen_range(start..64);
+            let v = rng.gen_range(0..5);
+            if rng.gen_bool(0.3) {
+                dst.update(start as i64..=end as i64, v);
+                values[start..=end].iter_mut().for_each(|e| *e = v);
+            } else {
+                dst.add(start as i64..=end as i64, v);
+                values[start..=end].iter_mut().for_each(|e| *e += v);
+            }
+            let total: u64 = values.iter().sum();
+            let k = rng.gen_range(0..=total);
+            let expected = values
+                .iter()
+                .scan(0, |sum, v| {
+                    *sum += v;
+                    Some(*sum)
+                })
+                .position(|sum| sum > k)
+                .map(|i| i as i64);
+            assert_eq!(dst.find_prefix(|sum| *sum > k), expected, "k = {}", k);
+        }
+        // the elements that were never updated hold the default value
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<u64> =
+            DynamicSegmentTreeWithRangeUpdates::new_with_add(
+                0..=1_000_000_000,
+                Arc::new(|a, b| a + b),
+                Arc::new(|a, b| a + b),
+            );
+        dst.add(0..=1_000_000_000, 1);
+        dst.update(10..=20, 0);
+        assert_eq!(dst.find_prefix(|sum| *sum > 10), Some(21));
+        assert_eq!(
+            dst.find_prefix(|sum| *sum >= 999_999_990),
+            Some(1_000_000_000)
+        );
+        assert_eq!(dst.find_prefix(|sum| *sum > 999_999_990), None);
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<u64> =
+            DynamicSegmentTreeWithRangeUpdates::new(0..=1_000_000_000, Arc::new(|a, b| a + b));
+        dst.update(500..=500, 3);
+        assert_eq!(dst.find_prefix(|sum| *sum >= 3), Some(500));
+        assert_eq!(dst.find_prefix(|_| true), Some(0));
+    }
+
+    #[test]
+    fn add_to_untouched_elements_adds_to_the_default() {
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<i64> =
+            DynamicSegmentTreeWithRangeUpdates::new_with_add(
+                0..=1_000_000_000,
+                Arc::new(cmp::max),
+                Arc::new(|a, b| a + b),
+            );
+        dst.add(10..=20, 1);
+        dst.add(15..=1_000, 1);
+        assert_eq!(dst.query(0..=9), Some(0));
+        assert_eq!(dst.query(0..=14), Some(1));
+        assert_eq!(dst.query(16..=16), Some(2));
+        assert_eq!(dst.query(0..=1_000_000_000), Some(2));
+        // only the nodes on the paths to the ends of the ranges are created
+        assert!(dst.stats().node_count < 200);
+        let token = dst.checkpoint();
+        dst.add(0..=1_000_000_000, 5);
+        dst.add(18..=18, 1);
+        assert_eq!(dst.query(0..=1_000_000_000), Some(8));
+        dst.rollback(token);
+        assert_eq!(dst.query(0..=1_000_000_000), Some(2));
+        assert_eq!(dst.validate(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn add_without_add_fn_panics() {
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<i64> =
+            DynamicSegmentTreeWithRangeUpdates::new(0..=10, Arc::new(cmp::max));
+        dst.add(0..=5, 1);
+    }
+
+    #[test]
+    fn validate_reports_the_offending_node() {
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<u32> =
+            DynamicSegmentTreeWithRangeUpdates::new(0..=7, Arc::new(|a, b| a + b));
+        dst.update(0..=5, 1);
+        dst.update(2..=2, 3);
+        assert_eq!(dst.validate(), Ok(()));
+        // a pending update on a leaf
+        let leaf = dst
+            .left_child
+            .as_mut()
+            .unwrap()
+            .left_child
+            .as_mut()
+            .unwrap();
+        leaf.extend_if_needed();
+        let leaf = leaf.left_child.as_mut().unwrap();
+        leaf.pending_child_update = Some(PendingUpdate::Assign(1));
+        assert_eq!(
+            dst.validate(),
+            Err(ValidationError::PendingUpdateOnLeaf { range: 0..=0 })
+        );
+        dst.left_child
+            .as_mut()
+            .unwrap()
+            .left_child
+            .as_mut()
+            .unwrap()
+            .left_child
+            .as_mut()
+            .unwrap()
+            .pending_child_update = None;
+        // a pending update that does not match the value of the node
+        dst.right_child
+            .as_mut()
+            .unwrap()
+            .left_child
+            .as_mut()
+            .unwrap()
+            .value = 3;
+        let error = dst.validate().unwrap_err();
+        assert_eq!(error.range(), &(4..=5));
+        assert_eq!(
+            error,
+            ValidationError::InconsistentValue {
+                range: 4..=5,
+                expected: 2,
+                actual: 3
+            }
+        );
+    }
+
+    /// The JSON rendering holds the value, pending update and children of every node
+    fn snapshot(dst: &DynamicSegmentTreeWithRangeUpdates<i64>) -> String {
+        let mut output = vec![];
+        dst.render(RenderFormat::Json, &mut output).expect("render");
+        String::from_utf8(output).expect("utf-8")
+    }
+
+    #[test]
+    fn rollback_restores_the_exact_state() {
+        let mut rng = thread_rng();
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<i64> =
+            DynamicSegmentTreeWithRangeUpdates::new(0..=1_000, Arc::new(|a, b| a + b));
+        dst.update(100..=900, 4);
+        let mut checkpoints = vec![];
+        for _ in 0..5 {
+            checkpoints.push((dst.checkpoint(), snapshot(&dst)));
+            for _ in 0..20 {
+                let start = rng.gen_range(0..=1_000);
+                let end = rng.gen_range(start..=1_000);
+                dst.update(start..=end, rng.gen_range(-50..50));
+            }
+        }
+        // the updates created nodes and moved pending updates, the rollbacks remove and restore them
+        checkpoints.insert(0, checkpoints[0].clone());
+        for (token, expected) in checkpoints.into_iter().rev() {
+            dst.rollback(token);
+            assert_eq!(snapshot(&dst), expected);
+            assert_eq!(dst.validate(), Ok(()));
+        }
+        dst.commit();
+        let journal = dst.journal.as_ref().expect("the journal is kept");
+        assert!(!journal.is_recording());
+        assert_eq!(journal.len(), 0);
+        dst.update(0..=10, 1);
+        assert_eq!(dst.query(0..=1_000), Some(11 + 4 * 801));
+    }
+
+    #[test]
+    #[should_panic]
+    fn rollback_to_a_committed_checkpoint_panics() {
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<i64> =
+            DynamicSegmentTreeWithRangeUpdates::new(0..=100, Arc::new(|a, b| a + b));
+        let stale = dst.checkpoint();
+        dst.update(0..=10, 1);
+        dst.commit();
+        // the checkpoint taken after the commit must not be mistaken for the committed one
+        dst.checkpoint();
+        dst.update(20..=30, 2);
+        dst.rollback(stale);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rollback_to_the_checkpoint_of_another_tree_panics() {
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<i64> =
+            DynamicSegmentTreeWithRangeUpdates::new(0..=100, Arc::new(|a, b| a + b));
+        let mut other: DynamicSegmentTreeWithRangeUpdates<i64> =
+            DynamicSegmentTreeWithRangeUpdates::new(0..=100, Arc::new(|a, b| a + b));
+        let token = other.checkpoint();
+        dst.checkpoint();
+        dst.update(0..=10, 1);
+        dst.rollback(token);
+    }
+
+    #[test]
+    fn stats_counts_pending_updates() {
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<u32> =
+            DynamicSegmentTreeWithRangeUpdates::new(0..=7, Arc::new(|a, b| a + b));
+        dst.update(0..=3, 1);
+        dst.update(4..=5, 1);
+        let stats = dst.stats();
+        assert_eq!(stats.node_count, 5);
+        assert_eq!(stats.leaf_count, 0);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(stats.pending_updates, 2);
+        dst.update(0..=0, 2);
+        let stats = dst.stats();
+        assert_eq!(stats.node_count, 9);
+        assert_eq!(stats.leaf_count, 2);
+        assert_eq!(stats.pending_updates, 2);
+    }
+
+    #[test]
+    fn render_dot_shows_pending_updates() {
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<u32> =
+            DynamicSegmentTreeWithRangeUpdates::new(0..=3, Arc::new(|a, b| a + b));
+        dst.update(0..=1, 2);
+        let mut output: Vec<u8> = vec![];
+        dst.render(RenderFormat::Dot, &mut output).expect("write");
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                "digraph DYNAMIC_SEGMENT_TREE {\n",
+                "  node [shape=box];\n",
+                "  n0 [label=\"[0..=3]\\n4\"];\n",
+                "  n1 [label=\"[0..=1]\\n4\", xlabel=\"pending: 2\"];\n",
+                "  n0 -> n1;\n",
+                "  n2 [label=\"[2..=3]\\n0\"];\n",
+                "  n0 -> n2;\n",
+                "}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn render_dot_shows_pending_additions() {
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<u32> =
+            DynamicSegmentTreeWithRangeUpdates::new_with_add(
+                0..=3,
+                Arc::new(|a, b| a + b),
+                Arc::new(|a, b| a + b),
+            );
+        dst.add(0..=1, 2);
+        let mut output: Vec<u8> = vec![];
+        dst.render(RenderFormat::Dot, &mut output).expect("write");
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .contains("n1 [label=\"[0..=1]\\n4\", xlabel=\"pending: +2\"];\n"));
+    }
+
+    #[test]
+    fn render_json_shows_pending_updates() {
+        let mut dst: DynamicSegmentTreeWithRangeUpdates<u32> =
+            DynamicSegmentTreeWithRangeUpdates::new(0..=3, Arc::new(|a, b| a + b));
+        dst.update(0..=1, 2);
+        let mut output: Vec<u8> = vec![];
+        dst.render(
+            RenderOptions::new(RenderFormat::Json).max_depth(0),
+            &mut output,
+        )
+        .expect("write");
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                r#"{"tree":"DYNAMIC_SEGMENT_TREE","root":"#,
+                r#"{"range":[0,3],"value":4,"pending":null,"children":[],"truncated":true}}"#,
+                "\n"
+            )
+        );
+    }
 }
@@ -0,0 +1,133 @@
+//! The undo log behind `checkpoint` / `rollback`.
+//! While a checkpoint is live the trees record the state of every node before they overwrite it,
+//! a rollback replays the records newest first. Nothing is recorded when there are no checkpoints.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The id of the next checkpoint, shared by every tree so that a token is only ever live in the tree it came from
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A checkpoint of a tree, see [`ArrayBasedSegmentTree::checkpoint`](super::array_based_segment_tree::ArrayBasedSegmentTree::checkpoint)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    id: u64,
+    /// The length of the undo log when the checkpoint was taken
+    position: usize,
+}
+
+pub(crate) struct Journal<E> {
+    entries: Vec<E>,
+    /// The live checkpoints, oldest first
+    checkpoints: Vec<Token>,
+}
+
+impl<E> Default for Journal<E> {
+    fn default() -> Self {
+        Journal {
+            entries: vec![],
+            checkpoints: vec![],
+        }
+    }
+}
+
+impl<E> Journal<E> {
+    /// True if the overwritten states have to be recorded
+    pub(crate) fn is_recording(&self) -> bool {
+        !self.checkpoints.is_empty()
+    }
+
+    /// Records the state (built only if needed) that is about to be overwritten
+    pub(crate) fn record<F: FnOnce() -> E>(&mut self, entry: F) {
+        if self.is_recording() {
+            self.entries.push(entry());
+        }
+    }
+
+    pub(crate) fn checkpoint(&mut self) -> Token {
+        let token = Token {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            position: self.entries.len(),
+        };
+        self.checkpoints.push(token);
+        token
+    }
+
+    /// Returns the states to restore, newest first. The checkpoints taken after the token are forgotten,
+    /// the token itself stays live so that it can be rolled back to again.
+    /// Panics if the token is not live.
+    pub(crate) fn rollback(&mut self, token: Token) -> impl Iterator<Item = E> + '_ {
+        let index = self
+            .checkpoints
+            .iter()
+            .position(|checkpoint| *checkpoint == token)
+            .expect("the checkpoint was rolled back past or committed");
+        self.checkpoints.truncate(index + 1);
+        self.entries.drain(token.position..).rev()
+    }
+
+    /// Forgets every checkpoint, which stops the recording. Their tokens are never live again.
+    pub(crate) fn commit(&mut self) {
+        self.entries = vec![];
+        self.checkpoints.clear();
+    }
+
+    /// The number of recorded states
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Journal;
+
+    #[test]
+    fn records_only_while_checkpoints_are_live() {
+        let mut journal = Journal::default();
+        journal.record(|| 1);
+        assert_eq!(journal.len(), 0);
+        let first = journal.checkpoint();
+        journal.record(|| 2);
+        let second = journal.checkpoint();
+        journal.record(|| 3);
+        journal.record(|| 4);
+        assert_eq!(journal.rollback(second).collect::<Vec<_>>(), vec![4, 3]);
+        journal.record(|| 5);
+        assert_eq!(journal.rollback(first).collect::<Vec<_>>(), vec![5, 2]);
+        // the first checkpoint is still live
+        journal.record(|| 6);
+        assert_eq!(journal.len(), 1);
+        journal.commit();
+        assert!(!journal.is_recording());
+    }
+
+    #[test]
+    #[should_panic]
+    fn rolling_back_to_a_forgotten_checkpoint_panics() {
+        let mut journal: Journal<u32> = Journal::default();
+        let first = journal.checkpoint();
+        let second = journal.checkpoint();
+        journal.rollback(first).for_each(drop);
+        journal.rollback(second).for_each(drop);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rolling_back_to_a_committed_checkpoint_panics() {
+        let mut journal: Journal<u32> = Journal::default();
+        let committed = journal.checkpoint();
+        journal.commit();
+        journal.checkpoint();
+        journal.rollback(committed).for_each(drop);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rolling_back_to_the_checkpoint_of_another_journal_panics() {
+        let mut journal: Journal<u32> = Journal::default();
+        let mut other: Journal<u32> = Journal::default();
+        let token = other.checkpoint();
+        journal.checkpoint();
+        journal.rollback(token).for_each(drop);
+    }
+}
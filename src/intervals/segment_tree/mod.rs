@@ -1,18 +1,46 @@
 //! Segment tree module
 //! Contains twp implementations - 1) array based and 2) dynamic
-use std::ops::RangeInclusive;
+use std::{ops::RangeInclusive, sync::Arc, thread};
 
 pub mod array_based_segment_tree;
+pub mod coverage_segment_tree;
 pub mod dynamic_segment_tree;
 pub mod dynamic_segment_tree_with_range_updates;
+pub mod journal;
+mod node;
+pub mod render;
+pub mod snapshot;
+pub mod stats;
+pub mod validate;
+
+/// The merge function shared between the nodes of the dynamic segment trees.
+/// It is `Send + Sync` so that a built tree can be shared across threads.
+pub type MergeFn<T> = Arc<dyn Fn(T, T) -> T + Send + Sync>;
+
+/// Below this many elements (or queries) the parallel operations fall back to the sequential ones,
+/// spawning threads costs more than it saves.
+const PARALLEL_THRESHOLD: usize = 1 << 14;
+
 #[inline]
-fn split(start: usize, end: usize) -> (RangeInclusive<usize>, RangeInclusive<usize>) {
+pub(crate) fn split(start: usize, end: usize) -> (RangeInclusive<usize>, RangeInclusive<usize>) {
     let left_index = start;
     let right_index = end;
     let middle_index = left_index + (right_index - left_index) / 2;
     (left_index..=middle_index, middle_index + 1..=right_index)
 }
 
+/// The last index of the left half of `left..=right`, without overflowing for any `i64` range
+#[inline]
+pub(crate) fn midpoint(left: i64, right: i64) -> i64 {
+    left + (right.abs_diff(left) / 2) as i64
+}
+
+/// The number of indices of `left..=right`, the range can not cover every `i64`
+#[inline]
+fn range_len(left: i64, right: i64) -> u64 {
+    right.abs_diff(left) + 1
+}
+
 #[inline]
 fn merge<T>(left: Option<T>, right: Option<T>, merge_fn: &dyn Fn(T, T) -> T) -> Option<T> {
     match (left, right) {
@@ -31,3 +59,90 @@ fn contains<T: PartialEq + PartialOrd>(
     // return the current segment if the current range is within the query range
     *larger.start() <= *smaller.start() && *larger.end() >= *smaller.end()
 }
+
+/// Merges `value` with itself so that the result covers `times` elements, i.e. the aggregate of a segment
+/// where every element was assigned `value`. O(log times) merges (exponentiation by squaring).
+fn repeat<T: Clone>(value: T, times: u64, merge_fn: &dyn Fn(T, T) -> T) -> T {
+    debug_assert!(times > 0);
+    let mut result: Option<T> = None;
+    let mut power = value;
+    let mut times = times;
+    loop {
+        if times & 1 == 1 {
+            result = merge(result, Some(power.clone()), merge_fn);
+        }
+        times >>= 1;
+        if times == 0 {
+            return result.expect("times > 0");
+        }
+        power = merge_fn(power.clone(), power);
+    }
+}
+
+/// The number of threads used by the parallel operations
+fn parallelism() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// The depth of the tree up to which the parallel builds spawn a thread per subtree
+fn parallel_depth(size: usize) -> usize {
+    if size < PARALLEL_THRESHOLD {
+        0
+    } else {
+        // ceil(log2(threads)) levels give at least one subtree per thread
+        (usize::BITS - (parallelism() - 1).leading_zeros()) as usize
+    }
+}
+
+/// Runs the query for each of the ranges, spreading large batches across the available cores.
+/// The results are in the same order as the ranges.
+fn query_batch<R, T, F>(ranges: &[R], query: F) -> Vec<Option<T>>
+where
+    R: Sync,
+    T: Send,
+    F: Fn(&R) -> Option<T> + Sync,
+{
+    let threads = parallelism();
+    if ranges.len() < PARALLEL_THRESHOLD || threads == 1 {
+        return ranges.iter().map(query).collect();
+    }
+    let chunk_size = ranges.len().div_ceil(threads);
+    let query = &query;
+    thread::scope(|s| {
+        let handles: Vec<_> = ranges
+            .chunks(chunk_size)
+            .map(|chunk| s.spawn(move || chunk.iter().map(query).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("query thread panicked"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{query_batch, repeat};
+
+    #[test]
+    fn repeat_works() {
+        let sum = |a: u64, b: u64| a + b;
+        for times in 1..=20 {
+            assert_eq!(repeat(3, times, &sum), 3 * times);
+        }
+        assert_eq!(repeat(7, 1 << 40, &sum), 7 << 40);
+        assert_eq!(repeat(7, 1000, &std::cmp::max), 7);
+        let concat = |a: String, b: String| a + &b;
+        assert_eq!(repeat("ab".to_string(), 3, &concat), "ababab");
+    }
+
+    #[test]
+    fn query_batch_preserves_order() {
+        let ranges: Vec<usize> = (0..100_000).collect();
+        let results = query_batch(&ranges, |r| Some(r * 2));
+        assert_eq!(
+            results,
+            ranges.iter().map(|r| Some(r * 2)).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! A read only view of the nodes, so that the operations walking a tree (rendering, statistics, ...)
+//! are written once for all the trees.
+use std::ops::RangeInclusive;
+
+use super::split;
+
+/// An update of a whole node that is yet to be applied to its children
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PendingUpdate<T> {
+    /// Every element is set to the value
+    Assign(T),
+    /// The value is added to every element
+    Add(T),
+}
+
+pub(crate) trait NodeView<T>: Sized {
+    /// True if the nodes of the tree can carry a pending (lazy) update for their children
+    const HAS_PENDING_UPDATES: bool = false;
+
+    /// The range of elements covered by this node
+    fn range(&self) -> RangeInclusive<i64>;
+
+    /// The aggregated value of the range
+    fn value(&self) -> &T;
+
+    /// The update that is yet to be applied to the children
+    fn pending_update(&self) -> Option<&PendingUpdate<T>> {
+        None
+    }
+
+    /// The function adding to the elements, for the trees supporting additions
+    fn add_fn(&self) -> Option<&(dyn Fn(T, T) -> T + Send + Sync)> {
+        None
+    }
+
+    /// The children that exist, a dynamic tree creates them on demand
+    fn children(&self) -> (Option<Self>, Option<Self>);
+}
+
+/// A node of an [`ArrayBasedSegmentTree`](super::array_based_segment_tree::ArrayBasedSegmentTree),
+/// the segment at `index` covering `range`.
+pub(crate) struct ArrayNode<'a, T> {
+    pub(crate) segments: &'a [T],
+    pub(crate) index: usize,
+    pub(crate) range: RangeInclusive<usize>,
+}
+
+impl<'a, T> NodeView<T> for ArrayNode<'a, T> {
+    fn range(&self) -> RangeInclusive<i64> {
+        *self.range.start() as i64..=*self.range.end() as i64
+    }
+
+    fn value(&self) -> &T {
+        &self.segments[self.index]
+    }
+
+    fn children(&self) -> (Option<Self>, Option<Self>) {
+        let (start, end) = (*self.range.start(), *self.range.end());
+        if start == end {
+            return (None, None);
+        }
+        let (left, right) = split(start, end);
+        (
+            Some(ArrayNode {
+                segments: self.segments,
+                index: 2 * self.index + 1,
+                range: left,
+            }),
+            Some(ArrayNode {
+                segments: self.segments,
+                index: 2 * self.index + 2,
+                range: right,
+            }),
+        )
+    }
+}
@@ -0,0 +1,398 @@
+//! Renders the segment trees as text, Graphviz DOT or JSON.
+#[cfg(feature = "pretty_print")]
+use colored::Colorize;
+use std::{
+    fmt::Debug,
+    io::{self, Write},
+};
+
+use super::node::{NodeView, PendingUpdate};
+
+/// The output format of [`render`](super::array_based_segment_tree::ArrayBasedSegmentTree::render)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderFormat {
+    /// The layout of `pretty_print`, without colors. Suitable for log files.
+    PlainText,
+    /// The layout of `pretty_print`, with ANSI colors
+    #[cfg(feature = "pretty_print")]
+    ColoredText,
+    /// A Graphviz DOT digraph, pending updates are shown as node annotations (`xlabel`), additions prefixed with `+`
+    Dot,
+    /// Nested objects with the range, value, pending update and children of every node.
+    /// A pending addition is an object `{"add": value}`.
+    Json,
+}
+
+/// The format along with limits for rendering large trees.
+/// The nodes beyond the limits are left out and marked as truncated in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    format: RenderFormat,
+    max_depth: Option<usize>,
+    max_nodes: Option<usize>,
+}
+
+impl RenderOptions {
+    pub fn new(format: RenderFormat) -> Self {
+        RenderOptions {
+            format,
+            max_depth: None,
+            max_nodes: None,
+        }
+    }
+
+    /// Renders the nodes up to the given depth, the root is at depth 0
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Renders at most the given number of nodes (in pre-order)
+    pub fn max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+}
+
+impl From<RenderFormat> for RenderOptions {
+    fn from(format: RenderFormat) -> Self {
+        RenderOptions::new(format)
+    }
+}
+
+/// Keeps track of the limits while walking the tree
+struct Budget {
+    max_depth: Option<usize>,
+    nodes_left: Option<usize>,
+}
+
+impl Budget {
+    /// Returns true (and uses up one node) if the node at the given depth can be rendered
+    fn take(&mut self, depth: usize) -> bool {
+        if self.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            return false;
+        }
+        match self.nodes_left.as_mut() {
+            Some(0) => false,
+            Some(nodes_left) => {
+                *nodes_left -= 1;
+                true
+            }
+            None => true,
+        }
+    }
+}
+
+/// Renders the tree rooted at `root`, `title` names the tree in the output.
+pub(crate) fn render<T: Debug, N: NodeView<T>, W: Write>(
+    root: N,
+    title: &str,
+    options: RenderOptions,
+    write: &mut W,
+) -> io::Result<()> {
+    let mut budget = Budget {
+        max_depth: options.max_depth,
+        nodes_left: options.max_nodes,
+    };
+    match options.format {
+        RenderFormat::PlainText => {
+            writeln!(write, "{}", title)?;
+            render_text(&root, 0, "  ".to_string(), true, false, &mut budget, write)
+        }
+        #[cfg(feature = "pretty_print")]
+        RenderFormat::ColoredText => {
+            writeln!(write, "{}", title.bold())?;
+            render_text(&root, 0, "  ".to_string(), true, true, &mut budget, write)
+        }
+        RenderFormat::Dot => {
+            writeln!(write, "digraph {} {{", title)?;
+            writeln!(write, "  node [shape=box];")?;
+            if budget.take(0) {
+                render_dot(&root, 0, &mut 0, &mut budget, write)?;
+            }
+            writeln!(write, "}}")
+        }
+        RenderFormat::Json => {
+            write!(write, "{{\"tree\":{},\"root\":", json_string(title))?;
+            if budget.take(0) {
+                render_json(&root, 0, &mut budget, write)?;
+            } else {
+                write!(write, "null")?;
+            }
+            writeln!(write, "}}")
+        }
+    }
+}
+
+fn render_text<T: Debug, N: NodeView<T>, W: Write>(
+    node: &N,
+    depth: usize,
+    prefix: String,
+    last: bool,
+    colored: bool,
+    budget: &mut Budget,
+    write: &mut W,
+) -> io::Result<()> {
+    let prefix_current = if last { "`- " } else { "|- " };
+    if !budget.take(depth) {
+        return write_text_line(write, &prefix, prefix_current, "...", None, colored);
+    }
+    {
+        let range = node.range();
+        let segment = format!("{}..={}", range.start(), range.end());
+        let value = if N::HAS_PENDING_UPDATES {
+            let pending = node.pending_update().map_or("None".to_string(), |p| {
+                format!("Some({})", pending_debug(p))
+            });
+            format!("{:?} (pending:{})", node.value(), pending)
+        } else {
+            format!("{:?}", node.value())
+        };
+        write_text_line(
+            write,
+            &prefix,
+            prefix_current,
+            &segment,
+            Some(&value),
+            colored,
+        )?;
+    }
+    let prefix_child = if last { "   " } else { "|  " };
+    let prefix = prefix + prefix_child;
+    match node.children() {
+        (Some(left_child), Some(right_child)) => {
+            render_text(
+                &left_child,
+                depth + 1,
+                prefix.clone(),
+                false,
+                colored,
+                budget,
+                write,
+            )?;
+            render_text(
+                &right_child,
+                depth + 1,
+                prefix,
+                true,
+                colored,
+                budget,
+                write,
+            )
+        }
+        (Some(child), None) | (None, Some(child)) => {
+            render_text(&child, depth + 1, prefix, true, colored, budget, write)
+        }
+        (None, None) => Ok(()),
+    }
+}
+
+/// Writes `[segment] => value`, or just the marker for truncated nodes when there is no value
+fn write_text_line<W: Write>(
+    write: &mut W,
+    prefix: &str,
+    prefix_current: &str,
+    segment: &str,
+    value: Option<&str>,
+    colored: bool,
+) -> io::Result<()> {
+    #[cfg(feature = "pretty_print")]
+    if colored {
+        return match value {
+            Some(value) => writeln!(
+                write,
+                "{}{}[{}] {} {}",
+                prefix.green().bold(),
+                prefix_current.green().bold(),
+                segment.bold(),
+                "=>".blue(),
+                value.bold()
+            ),
+            None => writeln!(
+                write,
+                "{}{}{}",
+                prefix.green().bold(),
+                prefix_current.green().bold(),
+                segment
+            ),
+        };
+    }
+    #[cfg(not(feature = "pretty_print"))]
+    let _ = colored;
+    match value {
+        Some(value) => writeln!(
+            write,
+            "{}{}[{}] => {}",
+            prefix, prefix_current, segment, value
+        ),
+        None => writeln!(write, "{}{}{}", prefix, prefix_current, segment),
+    }
+}
+
+/// Writes the node (named by its pre-order number `id`) and its subtree, `next_id` is the last number used
+fn render_dot<T: Debug, N: NodeView<T>, W: Write>(
+    node: &N,
+    depth: usize,
+    next_id: &mut usize,
+    budget: &mut Budget,
+    write: &mut W,
+) -> io::Result<()> {
+    let id = *next_id;
+    let range = node.range();
+    let label = format!("[{}..={}]\n{:?}", range.start(), range.end(), node.value());
+    write!(write, "  n{} [label={}", id, dot_string(&label))?;
+    if let Some(pending) = node.pending_update() {
+        write!(
+            write,
+            ", xlabel={}",
+            dot_string(&format!("pending: {}", pending_debug(pending)))
+        )?;
+    }
+    writeln!(write, "];")?;
+    let (left_child, right_child) = node.children();
+    for child in left_child.iter().chain(right_child.iter()) {
+        *next_id += 1;
+        let child_id = *next_id;
+        if budget.take(depth + 1) {
+            render_dot(child, depth + 1, next_id, budget, write)?;
+        } else {
+            writeln!(write, "  n{} [label=\"...\", style=dashed];", child_id)?;
+        }
+        writeln!(write, "  n{} -> n{};", id, child_id)?;
+    }
+    Ok(())
+}
+
+fn render_json<T: Debug, N: NodeView<T>, W: Write>(
+    node: &N,
+    depth: usize,
+    budget: &mut Budget,
+    write: &mut W,
+) -> io::Result<()> {
+    let range = node.range();
+    write!(
+        write,
+        "{{\"range\":[{},{}],\"value\":{}",
+        range.start(),
+        range.end(),
+        json_value(node.value())
+    )?;
+    if N::HAS_PENDING_UPDATES {
+        let pending = node
+            .pending_update()
+            .map_or("null".to_string(), |pending| match pending {
+                PendingUpdate::Assign(v) => json_value(v),
+                PendingUpdate::Add(delta) => format!("{{\"add\":{}}}", json_value(delta)),
+            });
+        write!(write, ",\"pending\":{}", pending)?;
+    }
+    write!(write, ",\"children\":[")?;
+    let (left_child, right_child) = node.children();
+    let mut truncated = false;
+    let mut first = true;
+    for child in left_child.iter().chain(right_child.iter()) {
+        if budget.take(depth + 1) {
+            if !first {
+                write!(write, ",")?;
+            }
+            first = false;
+            render_json(child, depth + 1, budget, write)?;
+        } else {
+            truncated = true;
+        }
+    }
+    write!(write, "]")?;
+    if truncated {
+        write!(write, ",\"truncated\":true")?;
+    }
+    write!(write, "}}")
+}
+
+/// The value of an assignment, or the value prefixed with `+` for an addition
+fn pending_debug<T: Debug>(pending: &PendingUpdate<T>) -> String {
+    match pending {
+        PendingUpdate::Assign(v) => format!("{:?}", v),
+        PendingUpdate::Add(delta) => format!("+{:?}", delta),
+    }
+}
+
+/// The debug representation of the value, as a JSON number if it is one or as a JSON string otherwise
+fn json_value<T: Debug>(value: &T) -> String {
+    let value = format!("{:?}", value);
+    if is_json_number(&value) {
+        value
+    } else {
+        json_string(&value)
+    }
+}
+
+fn is_json_number(s: &str) -> bool {
+    let s = s.strip_prefix('-').unwrap_or(s);
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+    let (integer, fraction) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], Some(&mantissa[i + 1..])),
+        None => (mantissa, None),
+    };
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    digits(integer)
+        && (integer == "0" || !integer.starts_with('0'))
+        && fraction.is_none_or(digits)
+        && exponent.is_none_or(|e| digits(e.strip_prefix(['+', '-']).unwrap_or(e)))
+}
+
+fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn dot_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_json_number, json_string};
+
+    #[test]
+    fn json_numbers_are_detected() {
+        for number in ["0", "-12", "3.25", "1e9", "-1.5E-3"] {
+            assert!(is_json_number(number), "{}", number);
+        }
+        for not_number in ["", "-", "01", "1.", ".5", "NaN", "inf", "Some(1)", "(1, 2)"] {
+            assert!(!is_json_number(not_number), "{}", not_number);
+        }
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("a\"b\\c\nd"), "\"a\\\"b\\\\c\\nd\"");
+        assert_eq!(json_string("\u{1}"), "\"\\u0001\"");
+    }
+}
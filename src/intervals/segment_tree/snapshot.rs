@@ -0,0 +1,435 @@
+//! Binary snapshots of the segment trees.
+//!
+//! A snapshot is laid out as
+//! ```text
+//! magic      4 bytes  b"DSST"
+//! version    u16      currently 2, version 1 snapshots are still read
+//! kind       u8       which tree wrote the snapshot
+//! value type string   Codec::type_name() of the values
+//! body                tree specific, nodes in pre-order
+//! checksum   u32      CRC-32 of all the preceding bytes
+//! ```
+//! All the integers are little endian, strings and collections are prefixed with their length as a u64.
+//! The merge function is not part of the snapshot, it has to be supplied when loading.
+//!
+//! Version 2 added the `HAS_PENDING_ADD` node flag of the trees that add to ranges,
+//! a version 1 snapshot is a version 2 snapshot without it.
+use std::{
+    convert::TryFrom,
+    fmt::Display,
+    io::{self, Read, Write},
+};
+
+const MAGIC: &[u8; 4] = b"DSST";
+const VERSION: u16 = 2;
+/// The oldest version that can still be read
+const MIN_VERSION: u16 = 1;
+
+/// Node flags used by the trees to record their (sparse) shape
+pub(crate) const HAS_LEFT_CHILD: u8 = 1;
+pub(crate) const HAS_RIGHT_CHILD: u8 = 1 << 1;
+pub(crate) const HAS_PENDING_UPDATE: u8 = 1 << 2;
+/// The pending update is an addition rather than an assignment, since version 2
+pub(crate) const HAS_PENDING_ADD: u8 = 1 << 3;
+
+/// The tree that wrote a snapshot
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TreeKind {
+    ArrayBased = 1,
+    Dynamic = 2,
+    DynamicWithRangeUpdates = 3,
+}
+
+impl TreeKind {
+    fn from_u8(kind: u8) -> Option<TreeKind> {
+        match kind {
+            1 => Some(TreeKind::ArrayBased),
+            2 => Some(TreeKind::Dynamic),
+            3 => Some(TreeKind::DynamicWithRangeUpdates),
+            _ => None,
+        }
+    }
+}
+
+/// Error while saving or loading a snapshot
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// The snapshot ended before it was completely read
+    Truncated,
+    /// Not a snapshot
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    KindMismatch {
+        expected: TreeKind,
+        found: u8,
+    },
+    TypeMismatch {
+        expected: String,
+        found: String,
+    },
+    ChecksumMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    InvalidData(String),
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            SnapshotError::Truncated
+        } else {
+            SnapshotError::Io(e)
+        }
+    }
+}
+
+/// Encodes and decodes the values stored in a tree
+pub trait Codec: Sized {
+    /// Name of the encoded type, recorded in the snapshot so that it is not loaded as a different type
+    fn type_name() -> String;
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, SnapshotError>;
+}
+
+macro_rules! impl_codec_for_primitive {
+    ($($t:ty),*) => {
+        $(
+            impl Codec for $t {
+                fn type_name() -> String {
+                    stringify!($t).to_string()
+                }
+
+                fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    writer.write_all(&self.to_le_bytes())
+                }
+
+                fn decode<R: Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+                    let mut bytes = [0; std::mem::size_of::<$t>()];
+                    reader.read_exact(&mut bytes)?;
+                    Ok(<$t>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_codec_for_primitive!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl Codec for usize {
+    fn type_name() -> String {
+        "usize".to_string()
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u64).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+        let value = u64::decode(reader)?;
+        usize::try_from(value)
+            .map_err(|_| SnapshotError::InvalidData(format!("{} does not fit a usize", value)))
+    }
+}
+
+impl Codec for isize {
+    fn type_name() -> String {
+        "isize".to_string()
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as i64).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+        let value = i64::decode(reader)?;
+        isize::try_from(value)
+            .map_err(|_| SnapshotError::InvalidData(format!("{} does not fit an isize", value)))
+    }
+}
+
+impl Codec for bool {
+    fn type_name() -> String {
+        "bool".to_string()
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u8).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+        match u8::decode(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            v => Err(SnapshotError::InvalidData(format!("{} is not a bool", v))),
+        }
+    }
+}
+
+impl Codec for String {
+    fn type_name() -> String {
+        "String".to_string()
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (self.len() as u64).encode(writer)?;
+        writer.write_all(self.as_bytes())
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+        let len = u64::decode(reader)?;
+        let mut bytes = vec![];
+        // `take` instead of allocating `len` bytes upfront, the length could be corrupt
+        reader.take(len).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != len {
+            return Err(SnapshotError::Truncated);
+        }
+        String::from_utf8(bytes).map_err(|e| SnapshotError::InvalidData(e.to_string()))
+    }
+}
+
+impl<T: Codec> Codec for Option<T> {
+    fn type_name() -> String {
+        format!("Option<{}>", T::type_name())
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            None => false.encode(writer),
+            Some(v) => {
+                true.encode(writer)?;
+                v.encode(writer)
+            }
+        }
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+        if bool::decode(reader)? {
+            Ok(Some(T::decode(reader)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T: Codec> Codec for Vec<T> {
+    fn type_name() -> String {
+        format!("Vec<{}>", T::type_name())
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (self.len() as u64).encode(writer)?;
+        self.iter().try_for_each(|v| v.encode(writer))
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+        let len = u64::decode(reader)?;
+        (0..len).map(|_| T::decode(reader)).collect()
+    }
+}
+
+impl<A: Codec, B: Codec> Codec for (A, B) {
+    fn type_name() -> String {
+        format!("({}, {})", A::type_name(), B::type_name())
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.encode(writer)?;
+        self.1.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+        Ok((A::decode(reader)?, B::decode(reader)?))
+    }
+}
+
+/// CRC-32 (IEEE 802.3) lookup table
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub(crate) fn update_crc(crc: u32, bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!crc, |crc, b| {
+        (crc >> 8) ^ CRC_TABLE[((crc ^ *b as u32) & 0xFF) as usize]
+    })
+}
+
+/// Writes the snapshot, keeping track of the checksum of everything written
+pub(crate) struct SnapshotWriter<W: Write> {
+    writer: W,
+    crc: u32,
+}
+
+impl<W: Write> Write for SnapshotWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.crc = update_crc(self.crc, &buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> SnapshotWriter<W> {
+    /// Writes the header of a snapshot for the given kind of tree storing values of type `T`
+    pub(crate) fn new<T: Codec>(writer: W, kind: TreeKind) -> Result<Self, SnapshotError> {
+        let mut snapshot_writer = SnapshotWriter { writer, crc: 0 };
+        snapshot_writer.write_all(MAGIC)?;
+        VERSION.encode(&mut snapshot_writer)?;
+        (kind as u8).encode(&mut snapshot_writer)?;
+        T::type_name().encode(&mut snapshot_writer)?;
+        Ok(snapshot_writer)
+    }
+
+    /// Writes the checksum, completing the snapshot
+    pub(crate) fn finish(mut self) -> Result<(), SnapshotError> {
+        let crc = self.crc;
+        crc.encode(&mut self.writer)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads a snapshot, keeping track of the checksum of everything read
+pub(crate) struct SnapshotReader<R: Read> {
+    reader: R,
+    crc: u32,
+    /// The version the snapshot was written with
+    version: u16,
+}
+
+impl<R: Read> Read for SnapshotReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.crc = update_crc(self.crc, &buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: Read> SnapshotReader<R> {
+    /// Reads and validates the header of a snapshot for the given kind of tree storing values of type `T`
+    pub(crate) fn new<T: Codec>(reader: R, kind: TreeKind) -> Result<Self, SnapshotError> {
+        let mut snapshot_reader = SnapshotReader {
+            reader,
+            crc: 0,
+            version: VERSION,
+        };
+        let mut magic = [0; 4];
+        snapshot_reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(SnapshotError::InvalidMagic);
+        }
+        let version = u16::decode(&mut snapshot_reader)?;
+        if !(MIN_VERSION..=VERSION).contains(&version) {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        snapshot_reader.version = version;
+        let found = u8::decode(&mut snapshot_reader)?;
+        if TreeKind::from_u8(found) != Some(kind) {
+            return Err(SnapshotError::KindMismatch {
+                expected: kind,
+                found,
+            });
+        }
+        let found = String::decode(&mut snapshot_reader)?;
+        if found != T::type_name() {
+            return Err(SnapshotError::TypeMismatch {
+                expected: T::type_name(),
+                found,
+            });
+        }
+        Ok(snapshot_reader)
+    }
+
+    /// The version the snapshot was written with
+    pub(crate) fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Reads and verifies the checksum, completing the snapshot
+    pub(crate) fn finish(mut self) -> Result<(), SnapshotError> {
+        let actual = self.crc;
+        let expected = u32::decode(&mut self.reader)?;
+        if expected != actual {
+            return Err(SnapshotError::ChecksumMismatch { expected, actual });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{update_crc, Codec, SnapshotError};
+
+    fn round_trip<T: Codec + PartialEq + std::fmt::Debug>(value: T) {
+        let mut bytes = vec![];
+        value.encode(&mut bytes).expect("encode");
+        assert_eq!(T::decode(&mut bytes.as_slice()).expect("decode"), value);
+    }
+
+    #[test]
+    fn codec_round_trips() {
+        round_trip(42u8);
+        round_trip(-42i64);
+        round_trip(u128::MAX);
+        round_trip(usize::MAX);
+        round_trip(1.5f64);
+        round_trip(true);
+        round_trip("segment tree".to_string());
+        round_trip(Some(3u32));
+        round_trip(None::<u32>);
+        round_trip(vec![(1i32, 2u64), (3, 4)]);
+        assert_eq!(<Vec<(i32, u64)>>::type_name(), "Vec<(i32, u64)>");
+    }
+
+    #[test]
+    fn decode_fails_on_truncated_input() {
+        let mut bytes = vec![];
+        "segment tree"
+            .to_string()
+            .encode(&mut bytes)
+            .expect("encode");
+        bytes.pop();
+        assert!(matches!(
+            String::decode(&mut bytes.as_slice()),
+            Err(SnapshotError::Truncated)
+        ));
+        assert!(matches!(
+            u64::decode(&mut [1u8, 2].as_slice()),
+            Err(SnapshotError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn crc_matches_reference() {
+        assert_eq!(update_crc(0, b"123456789"), 0xCBF4_3926);
+        assert_eq!(update_crc(update_crc(0, b"12345"), b"6789"), 0xCBF4_3926);
+    }
+}
@@ -0,0 +1,52 @@
+//! Structural statistics of the segment trees
+use std::ops::RangeInclusive;
+
+use super::node::NodeView;
+
+/// The shape and (approximate) memory footprint of a tree
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TreeStats {
+    /// The number of nodes (segments) in the tree
+    pub node_count: usize,
+    /// The number of nodes that cover a single element
+    pub leaf_count: usize,
+    /// The depth of the deepest node, the root is at depth 0
+    pub max_depth: usize,
+    /// The number of nodes with an update that is yet to be applied to their children
+    pub pending_updates: usize,
+    /// The bytes allocated for the nodes.
+    /// Memory owned by the values themselves (e.g. the contents of a `String`) is not included.
+    pub heap_bytes: usize,
+}
+
+impl TreeStats {
+    /// The heap bytes per element of the given range
+    pub fn bytes_per_element(&self, range: &RangeInclusive<i64>) -> f64 {
+        self.heap_bytes as f64 / ((range.end() - range.start()) as f64 + 1.0)
+    }
+}
+
+/// Walks the tree rooted at `root`, `heap_bytes` is left for the tree to fill in
+pub(crate) fn collect<T, N: NodeView<T>>(root: N) -> TreeStats {
+    fn visit<T, N: NodeView<T>>(node: N, depth: usize, stats: &mut TreeStats) {
+        stats.node_count += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        let range = node.range();
+        if range.start() == range.end() {
+            stats.leaf_count += 1;
+        }
+        if node.pending_update().is_some() {
+            stats.pending_updates += 1;
+        }
+        let (left_child, right_child) = node.children();
+        if let Some(left_child) = left_child {
+            visit(left_child, depth + 1, stats);
+        }
+        if let Some(right_child) = right_child {
+            visit(right_child, depth + 1, stats);
+        }
+    }
+    let mut stats = TreeStats::default();
+    visit(root, 0, &mut stats);
+    stats
+}
@@ -0,0 +1,130 @@
+//! Invariant checks for the segment trees, useful when writing custom merge functions
+use std::{fmt::Debug, fmt::Display, ops::RangeInclusive};
+
+use super::{
+    merge, midpoint,
+    node::{NodeView, PendingUpdate},
+    range_len, repeat,
+};
+
+/// The first invariant found broken, along with the range of the offending node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError<T> {
+    /// The value of the node is not the merge of its children (or of its pending update)
+    InconsistentValue {
+        range: RangeInclusive<i64>,
+        expected: T,
+        actual: T,
+    },
+    /// The children do not split the range of the node into its two halves
+    InvalidChildRange {
+        range: RangeInclusive<i64>,
+        child: RangeInclusive<i64>,
+    },
+    /// A leaf has no children to apply the pending update to
+    PendingUpdateOnLeaf { range: RangeInclusive<i64> },
+}
+
+impl<T> ValidationError<T> {
+    /// The range of the node that broke the invariant
+    pub fn range(&self) -> &RangeInclusive<i64> {
+        match self {
+            ValidationError::InconsistentValue { range, .. } => range,
+            ValidationError::InvalidChildRange { range, .. } => range,
+            ValidationError::PendingUpdateOnLeaf { range } => range,
+        }
+    }
+}
+
+impl<T: Debug> Display for ValidationError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl<T: Debug> std::error::Error for ValidationError<T> {}
+
+/// Checks the invariants of every node in the tree rooted at `root`:
+/// 1. The value of a node is the merge of its children, with the pending update applied over its range if it has one.
+///    A node that was never expanded (no children) holds the default value before the pending update.
+/// 2. The children split the range of the node into the same halves as the tree does.
+/// 3. Leaves do not have pending updates.
+pub(crate) fn validate<T, N>(
+    root: N,
+    merge_fn: &dyn Fn(T, T) -> T,
+) -> Result<(), ValidationError<T>>
+where
+    T: Clone + PartialEq + Default,
+    N: NodeView<T>,
+{
+    validate_node(&root, merge_fn)
+}
+
+fn validate_node<T, N>(root: &N, merge_fn: &dyn Fn(T, T) -> T) -> Result<(), ValidationError<T>>
+where
+    T: Clone + PartialEq + Default,
+    N: NodeView<T>,
+{
+    let range = root.range();
+    let (start, end) = (*range.start(), *range.end());
+    let pending_update = root.pending_update().cloned();
+    if start == end && pending_update.is_some() {
+        return Err(ValidationError::PendingUpdateOnLeaf { range });
+    }
+    let (left_child, right_child) = root.children();
+    let mid = midpoint(start, end);
+    let expected_ranges = [start..=mid, mid + 1..=end];
+    for (child, expected_range) in [left_child.as_ref(), right_child.as_ref()]
+        .iter()
+        .zip(expected_ranges.iter())
+    {
+        if let Some(child) = child {
+            if start == end || child.range() != *expected_range {
+                return Err(ValidationError::InvalidChildRange {
+                    range,
+                    child: child.range(),
+                });
+            }
+        }
+    }
+    // The children are checked first, so that the deepest offending node is reported
+    if let Some(left_child) = left_child.as_ref() {
+        validate_node(left_child, merge_fn)?;
+    }
+    if let Some(right_child) = right_child.as_ref() {
+        validate_node(right_child, merge_fn)?;
+    }
+    let len = range_len(start, end);
+    let children = merge(
+        left_child.as_ref().map(|c| c.value().clone()),
+        right_child.as_ref().map(|c| c.value().clone()),
+        merge_fn,
+    );
+    // A leaf can hold any value, a node that was never expanded holds the default value
+    let children = match children {
+        Some(children) => Some(children),
+        None if start < end => Some(T::default()),
+        None => None,
+    };
+    let expected = match pending_update {
+        Some(PendingUpdate::Assign(v)) => Some(repeat(v, len, merge_fn)),
+        // The children have not received the addition yet
+        Some(PendingUpdate::Add(delta)) => {
+            let add_fn = root
+                .add_fn()
+                .expect("a tree with additions has an add function");
+            children.map(|children| add_fn(children, repeat(delta, len, merge_fn)))
+        }
+        None => children,
+    };
+    if let Some(expected) = expected {
+        if expected != *root.value() {
+            return Err(ValidationError::InconsistentValue {
+                range,
+                expected,
+                actual: root.value().clone(),
+            });
+        }
+    }
+    Ok(())
+}
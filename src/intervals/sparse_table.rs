@@ -0,0 +1,121 @@
+//! Sparse table, O(1) range queries over values that never change.
+//! The level `k` holds the aggregate of every range of length `2^k`, so any range is covered by the two
+//! (overlapping) ranges of the largest power of 2 that fits. Counting the overlap twice is only correct for an
+//! [`Idempotent`] operation such as `Min`, `Max` or `Gcd`.
+//! O(n log n) time and space to build.
+//! https://cp-algorithms.com/data_structures/sparse-table.html
+use std::ops::RangeInclusive;
+
+use super::ops::Idempotent;
+
+/// Range queries of an idempotent operation over a fixed array.
+/// ```
+/// # use datastructures_in_rust::intervals::{ops::Min, sparse_table::SparseTable};
+/// let table = SparseTable::new(&[Min(5), Min(3), Min(8), Min(6)]);
+/// assert_eq!(table.query(2..=3), Some(Min(6)));
+/// assert_eq!(table.query(0..=3), Some(Min(3)));
+/// assert_eq!(table.query(2..=4), None);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseTable<M> {
+    /// `levels[k][i]` is the aggregate of `i..i + 2^k`
+    levels: Vec<Vec<M>>,
+}
+
+impl<M: Idempotent> SparseTable<M> {
+    pub fn new(values: &[M]) -> Self {
+        let mut levels = vec![values.to_vec()];
+        let mut length = 1;
+        while 2 * length <= values.len() {
+            let previous = levels.last().expect("the first level is the values");
+            let level = (0..=values.len() - 2 * length)
+                .map(|i| previous[i].clone().combine(previous[i + length].clone()))
+                .collect();
+            levels.push(level);
+            length *= 2;
+        }
+        SparseTable { levels }
+    }
+
+    /// The number of values
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// The aggregate of the values of the range, O(1).
+    /// None if the range is empty or goes past the values.
+    pub fn query(&self, range: RangeInclusive<usize>) -> Option<M> {
+        let (start, end) = (*range.start(), *range.end());
+        if start > end || end >= self.len() {
+            return None;
+        }
+        let level = (end - start + 1).ilog2() as usize;
+        let values = &self.levels[level];
+        Some(
+            values[start]
+                .clone()
+                .combine(values[end + 1 - (1 << level)].clone()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+
+    use super::SparseTable;
+    use crate::intervals::ops::{Gcd, MaxWithIndex, Min, Monoid};
+
+    #[test]
+    fn queries_match_brute_force() {
+        let mut rng = thread_rng();
+        for len in [0, 1, 2, 3, 7, 8, 9, 100] {
+            let values: Vec<i64> = (0..len).map(|_| rng.gen_range(-50..50)).collect();
+            let min = SparseTable::new(&values.iter().map(|v| Min(*v)).collect::<Vec<_>>());
+            let max = SparseTable::new(
+                &values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| MaxWithIndex::new(*v, i as i64))
+                    .collect::<Vec<_>>(),
+            );
+            let gcd = SparseTable::new(
+                &values
+                    .iter()
+                    .map(|v| Gcd(v.unsigned_abs()))
+                    .collect::<Vec<_>>(),
+            );
+            for start in 0..len {
+                for end in start..len {
+                    let range = &values[start..=end];
+                    assert_eq!(min.query(start..=end), range.iter().min().map(|v| Min(*v)));
+                    let (index, value) = range
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .max_by_key(|(_, v)| **v)
+                        .unwrap();
+                    // the leftmost of the largest values
+                    assert_eq!(
+                        max.query(start..=end),
+                        Some(MaxWithIndex::new(*value, (start + index) as i64))
+                    );
+                    assert_eq!(
+                        gcd.query(start..=end),
+                        Some(
+                            range
+                                .iter()
+                                .map(|v| Gcd(v.unsigned_abs()))
+                                .fold(Gcd::identity(), Gcd::combine)
+                        )
+                    );
+                }
+            }
+            assert_eq!(min.query(0..=len), None);
+        }
+    }
+}
@@ -1,6 +1,6 @@
 //! Parser, a utility for parsing leet code inputs
 use atoi::FromRadix10SignedChecked;
-use std::{collections::VecDeque, fmt::Display, fs::File, io::Read};
+use std::{collections::VecDeque, fmt::Display, fs::File, io::Read, str::FromStr};
 
 /// A parser to consume inputs from Leet code
 #[derive(Default)]
@@ -122,6 +122,13 @@ impl Item {
         atoi::atoi::<B>(self.as_bytes()?).ok_or(ItemConversionError::FailedToConvert)
     }
 
+    /// Converts this item into any type parsed from a string, e.g. a floating point number
+    pub fn into_parsed<B: FromStr>(self) -> Result<B, ItemConversionError> {
+        self.into_string()?
+            .parse()
+            .map_err(|_| ItemConversionError::FailedToConvert)
+    }
+
     /// Converts the item using the supplied mapper function
     pub fn map<F, B>(self, mut mapper: F) -> Result<B, ItemConversionError>
     where
@@ -200,11 +207,14 @@ impl LeetCodeParser {
                 .expect("Open file");
         let mut contents = String::new();
         file.read_to_string(&mut contents).expect("Read");
-        let arguments: Result<VecDeque<Item>, _> = contents
-            .split('\n')
-            .into_iter()
-            .map(Item::from_str)
-            .collect();
+        let arguments: Result<VecDeque<Item>, _> =
+            contents.split('\n').map(Item::from_str).collect();
+        Ok(LeetCodeParser::new_with_arguments(arguments?))
+    }
+
+    /// Creates a parser over the given input, an argument per line
+    pub fn from_lines(input: &str) -> Result<Self, ItemConversionError> {
+        let arguments: Result<VecDeque<Item>, _> = input.lines().map(Item::from_str).collect();
         Ok(LeetCodeParser::new_with_arguments(arguments?))
     }
 
@@ -219,7 +229,7 @@ impl LeetCodeParser {
 
 #[cfg(test)]
 mod tests {
-    use crate::leetcode::parser::Item;
+    use crate::leetcode::parser::{Item, LeetCodeParser};
 
     use super::ItemConversionError;
 
@@ -307,6 +317,27 @@ mod tests {
         assert_eq!(v, 1234);
         let item: Item = Item::from_str("1234")?;
         assert_eq!(item.into_string()?, "1234".to_string());
+        let item: Item = Item::from_str("[2.5,-1.0]")?;
+        let v: Vec<f64> = item.into_vec(Item::into_parsed)?;
+        assert_eq!(v, vec![2.5, -1.0]);
+        assert_eq!(
+            Item::from_str("[1]")?.into_parsed::<f64>(),
+            Err(ItemConversionError::FailedToConvert)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parser_reads_an_argument_per_line() -> Result<(), ItemConversionError> {
+        let mut parser = LeetCodeParser::from_lines("[1,2]\n3\n")?;
+        assert_eq!(
+            parser
+                .next_item()
+                .map(|item| item.into_vec(Item::into_num::<i32>)),
+            Some(Ok(vec![1, 2]))
+        );
+        assert_eq!(parser.next_item().map(Item::into_num::<i32>), Some(Ok(3)));
+        assert_eq!(parser.next_item(), None);
         Ok(())
     }
 }
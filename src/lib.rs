@@ -1,4 +1,7 @@
 //! My attempt to implement advanced data structures in Rust
 //! There is a leetcode module to test the implementations against leet code problems
+pub mod bloom_filter;
+pub mod disjoint_set;
 pub mod intervals;
 pub mod leetcode;
+pub mod suffix_array;
@@ -0,0 +1,73 @@
+//! Kasai's LCP array construction in O(n).
+//! Going through the suffixes in text order, the common prefix of the suffix `i + 1` with its predecessor
+//! is at least the one of the suffix `i` minus 1, so the comparisons only move forward.
+//! https://doi.org/10.1007/3-540-48194-X_17
+
+/// The LCP array of the text given its suffix array: the length of the longest common prefix of each suffix
+/// (in order) and the previous one, 0 for the first suffix.
+/// ```
+/// # use datastructures_in_rust::suffix_array::{kasai::lcp_array, sa_is::suffix_array};
+/// let text = b"banana";
+/// assert_eq!(lcp_array(text, &suffix_array(text)), vec![0, 1, 3, 0, 0, 2]);
+/// ```
+pub fn lcp_array<T: Eq>(text: &[T], suffixes: &[usize]) -> Vec<usize> {
+    let mut ranks = vec![0; suffixes.len()];
+    for (rank, suffix) in suffixes.iter().enumerate() {
+        ranks[*suffix] = rank;
+    }
+    lcp_array_with_ranks(text, suffixes, &ranks)
+}
+
+/// The LCP array given the rank of each suffix as well
+pub(crate) fn lcp_array_with_ranks<T: Eq>(
+    text: &[T],
+    suffixes: &[usize],
+    ranks: &[usize],
+) -> Vec<usize> {
+    let n = text.len();
+    let mut lcp = vec![0; n];
+    let mut common = 0;
+    for i in 0..n {
+        if ranks[i] == 0 {
+            common = 0;
+            continue;
+        }
+        let previous = suffixes[ranks[i] - 1];
+        while i + common < n && previous + common < n && text[i + common] == text[previous + common]
+        {
+            common += 1;
+        }
+        lcp[ranks[i]] = common;
+        common = common.saturating_sub(1);
+    }
+    lcp
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+
+    use super::lcp_array;
+    use crate::suffix_array::sa_is::suffix_array;
+
+    #[test]
+    fn matches_naive() {
+        let mut rng = thread_rng();
+        for _ in 0..300 {
+            let len = rng.gen_range(0..300);
+            let alphabet = rng.gen_range(1..=3u8);
+            let text: Vec<u8> = (0..len).map(|_| rng.gen_range(0..alphabet)).collect();
+            let suffixes = suffix_array(&text);
+            let expected: Vec<usize> = (0..len)
+                .map(|i| {
+                    if i == 0 {
+                        return 0;
+                    }
+                    let (a, b) = (&text[suffixes[i - 1]..], &text[suffixes[i]..]);
+                    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+                })
+                .collect();
+            assert_eq!(lcp_array(&text, &suffixes), expected);
+        }
+    }
+}
@@ -0,0 +1,467 @@
+//! Suffix array module
+//! The suffix array of a text is the start of each of its suffixes, in lexicographic order of the suffixes.
+//! It is built in O(n) by [`sa_is`], and the LCP array (the longest common prefix of consecutive suffixes)
+//! in O(n) by [`kasai`].
+//! The occurrences of a pattern are the suffixes it prefixes, a contiguous range of the suffix array,
+//! and the substrings shared by several suffixes are the common prefixes of consecutive suffixes.
+//! The longest common prefix of any two suffixes is the minimum of the LCP array between their ranks,
+//! a range minimum query answered in O(1) by a [`SparseTable`].
+//! https://en.wikipedia.org/wiki/Suffix_array
+use std::{cmp::Ordering, ops::Range, sync::OnceLock};
+
+use crate::intervals::{ops::Min, sparse_table::SparseTable};
+
+pub mod kasai;
+pub mod sa_is;
+pub mod suffix_automaton;
+
+/// The suffix array of a text with its LCP array.
+/// ```
+/// # use datastructures_in_rust::suffix_array::SuffixArray;
+/// let suffix_array = SuffixArray::from_bytes(b"banana");
+/// // a, ana, anana, banana, na, nana
+/// assert_eq!(suffix_array.suffixes(), &[5, 3, 1, 0, 4, 2]);
+/// assert_eq!(suffix_array.lcp_array(), &[0, 1, 3, 0, 0, 2]);
+/// assert_eq!(suffix_array.rank(0), 3);
+/// // ana is a prefix of anana
+/// assert_eq!(suffix_array.lcp(3, 1), 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SuffixArray<T> {
+    text: Vec<T>,
+    /// The starts of the suffixes in lexicographic order
+    suffixes: Vec<usize>,
+    /// The position of each suffix in `suffixes`
+    ranks: Vec<usize>,
+    /// The length of the longest common prefix of each suffix (in order) and the previous one, 0 for the first
+    lcp: Vec<usize>,
+    /// The range minimums of `lcp`, built by the first query of the common prefix of two suffixes
+    lcp_minimums: OnceLock<SparseTable<Min<usize>>>,
+}
+
+/// Suffix arrays are equal if their texts are, whether their range minimums were built or not
+impl<T: PartialEq> PartialEq for SuffixArray<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl SuffixArray<u8> {
+    /// Builds the suffix array of the bytes in O(n)
+    pub fn from_bytes(text: &[u8]) -> Self {
+        SuffixArray::with_suffixes(text.to_vec(), sa_is::suffix_array(text))
+    }
+}
+
+impl<T: Ord + Clone> SuffixArray<T> {
+    /// Builds the suffix array of the symbols in O(n log n), sorting the distinct symbols, see [`sa_is::suffix_array_of`]
+    pub fn new(text: &[T]) -> Self {
+        SuffixArray::with_suffixes(text.to_vec(), sa_is::suffix_array_of(text))
+    }
+}
+
+impl<T: Eq> SuffixArray<T> {
+    fn with_suffixes(text: Vec<T>, suffixes: Vec<usize>) -> Self {
+        let mut ranks = vec![0; suffixes.len()];
+        for (rank, suffix) in suffixes.iter().enumerate() {
+            ranks[*suffix] = rank;
+        }
+        let lcp = kasai::lcp_array_with_ranks(&text, &suffixes, &ranks);
+        SuffixArray {
+            text,
+            suffixes,
+            ranks,
+            lcp,
+            lcp_minimums: OnceLock::new(),
+        }
+    }
+
+    pub fn text(&self) -> &[T] {
+        &self.text
+    }
+
+    /// The length of the text
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The starts of the suffixes in lexicographic order
+    pub fn suffixes(&self) -> &[usize] {
+        &self.suffixes
+    }
+
+    /// The position of the suffix starting at `start` in [`SuffixArray::suffixes`].
+    /// Panics if the start is not within the text.
+    pub fn rank(&self, start: usize) -> usize {
+        self.ranks[start]
+    }
+
+    /// The LCP array: the length of the longest common prefix of each suffix (in order) and the previous one,
+    /// 0 for the first suffix
+    pub fn lcp_array(&self) -> &[usize] {
+        &self.lcp
+    }
+
+    /// The length of the longest common prefix of the suffixes starting at `i` and `j`, O(1).
+    /// The first call builds a sparse table of the LCP array, O(n log n) time and space.
+    /// Panics if a start is not within the text.
+    pub fn lcp(&self, i: usize, j: usize) -> usize {
+        if i == j {
+            assert!(i < self.text.len(), "{} is not within the text", i);
+            return self.text.len() - i;
+        }
+        let (first, last) = (
+            self.ranks[i].min(self.ranks[j]),
+            self.ranks[i].max(self.ranks[j]),
+        );
+        let minimums = self.lcp_minimums.get_or_init(|| {
+            SparseTable::new(
+                &self
+                    .lcp
+                    .iter()
+                    .map(|length| Min(*length))
+                    .collect::<Vec<_>>(),
+            )
+        });
+        minimums
+            .query(first + 1..=last)
+            .expect("the ranks are within the LCP array")
+            .0
+    }
+}
+
+impl<T: Ord> SuffixArray<T> {
+    /// Compares two substrings of the text in O(1), like comparing the slices `text[a]` and `text[b]`.
+    /// Panics if a range is not within the text.
+    /// ```
+    /// # use datastructures_in_rust::suffix_array::SuffixArray;
+    /// # use std::cmp::Ordering;
+    /// let suffix_array = SuffixArray::from_bytes(b"banana");
+    /// assert_eq!(suffix_array.compare_substrings(1..4, 3..6), Ordering::Equal);
+    /// assert_eq!(suffix_array.compare_substrings(1..3, 1..4), Ordering::Less);
+    /// assert_eq!(suffix_array.compare_substrings(0..1, 1..2), Ordering::Greater);
+    /// ```
+    pub fn compare_substrings(&self, a: Range<usize>, b: Range<usize>) -> Ordering {
+        for range in [&a, &b] {
+            assert!(
+                range.start <= range.end && range.end <= self.text.len(),
+                "{:?} is not within the text",
+                range
+            );
+        }
+        let shortest = a.len().min(b.len());
+        let common = if shortest == 0 {
+            0
+        } else {
+            self.lcp(a.start, b.start).min(shortest)
+        };
+        if common == shortest {
+            a.len().cmp(&b.len())
+        } else {
+            self.text[a.start + common].cmp(&self.text[b.start + common])
+        }
+    }
+}
+
+impl<T: Ord> SuffixArray<T> {
+    /// The start of every occurrence of the pattern in the text, in increasing order.
+    /// O(m log n) to find them for a pattern of length m, plus sorting them.
+    /// ```
+    /// # use datastructures_in_rust::suffix_array::SuffixArray;
+    /// let suffix_array = SuffixArray::from_bytes(b"abracadabra");
+    /// assert_eq!(suffix_array.find_all(b"abra"), vec![0, 7]);
+    /// assert_eq!(suffix_array.count(b"a"), 5);
+    /// ```
+    pub fn find_all(&self, pattern: &[T]) -> Vec<usize> {
+        let mut starts = self.suffixes[self.suffix_range(pattern)].to_vec();
+        starts.sort_unstable();
+        starts
+    }
+
+    /// The number of occurrences of the pattern in the text, O(m log n) for a pattern of length m.
+    /// The empty pattern occurs at every suffix.
+    pub fn count(&self, pattern: &[T]) -> usize {
+        self.suffix_range(pattern).len()
+    }
+
+    /// The positions in the suffix array of the suffixes starting with the pattern
+    fn suffix_range(&self, pattern: &[T]) -> Range<usize> {
+        let prefix = |start: usize| {
+            let end = (start + pattern.len()).min(self.text.len());
+            self.text[start..end].cmp(pattern)
+        };
+        let first = self
+            .suffixes
+            .partition_point(|start| prefix(*start) == Ordering::Less);
+        let last = first
+            + self.suffixes[first..].partition_point(|start| prefix(*start) == Ordering::Equal);
+        first..last
+    }
+}
+
+impl<T: Eq> SuffixArray<T> {
+    /// The range of the text of the longest substring occurring at least twice (the occurrences may overlap),
+    /// the first one in text order among the longest. None if no symbol repeats.
+    /// ```
+    /// # use datastructures_in_rust::suffix_array::SuffixArray;
+    /// let suffix_array = SuffixArray::from_bytes(b"banana");
+    /// assert_eq!(suffix_array.longest_repeated_substring(), Some(1..4));
+    /// ```
+    pub fn longest_repeated_substring(&self) -> Option<Range<usize>> {
+        // the longest common prefix of consecutive suffixes, the earliest start of the two on ties
+        (1..self.lcp.len())
+            .filter(|rank| self.lcp[*rank] > 0)
+            .map(|rank| {
+                let start = self.suffixes[rank].min(self.suffixes[rank - 1]);
+                (self.lcp[rank], start)
+            })
+            .max_by(|(a, a_start), (b, b_start)| a.cmp(b).then(b_start.cmp(a_start)))
+            .map(|(length, start)| start..start + length)
+    }
+
+    /// The number of distinct non empty substrings: each suffix adds its prefixes that are longer
+    /// than its common prefix with the previous suffix, `n (n + 1) / 2 - Σ lcp`.
+    /// ```
+    /// # use datastructures_in_rust::suffix_array::SuffixArray;
+    /// // a, b, ab, ba, aba
+    /// assert_eq!(SuffixArray::from_bytes(b"aba").distinct_substring_count(), 5);
+    /// ```
+    pub fn distinct_substring_count(&self) -> usize {
+        let n = self.text.len();
+        n * (n + 1) / 2 - self.lcp.iter().sum::<usize>()
+    }
+}
+
+/// The ranges of `a` and `b` of their longest common substring, the first one in the order of `a` among the longest.
+/// None if they have no symbol in common.
+/// Built over the generalized suffix array of `a`, a separator and `b`: a common substring is a common prefix
+/// of a suffix of each, and the longest one is between two suffixes adjacent in order, one from each.
+/// O((n + m) log(n + m)) for ranking the symbols, then O(n + m).
+/// ```
+/// # use datastructures_in_rust::suffix_array::longest_common_substring;
+/// assert_eq!(longest_common_substring(b"xabcdey", b"zzbcdz"), Some((2..5, 2..5)));
+/// assert_eq!(longest_common_substring(b"ab", b"cd"), None);
+/// ```
+pub fn longest_common_substring<T: Ord>(a: &[T], b: &[T]) -> Option<(Range<usize>, Range<usize>)> {
+    let joined: Vec<&T> = a.iter().chain(b.iter()).collect();
+    let (ranks, upper) = sa_is::compress(&joined);
+    // the separator is 0, smaller than every symbol and never part of a common prefix
+    let text: Vec<usize> = ranks[..a.len()]
+        .iter()
+        .map(|rank| rank + 1)
+        .chain([0])
+        .chain(ranks[a.len()..].iter().map(|rank| rank + 1))
+        .collect();
+    let suffixes = sa_is::sa_is(&text, upper + 1);
+    let lcp = kasai::lcp_array(&text, &suffixes);
+    let in_a = |start: usize| start < a.len();
+    let length = (1..text.len())
+        .filter(|rank| in_a(suffixes[rank - 1]) != in_a(suffixes[*rank]))
+        .map(|rank| lcp[rank])
+        .max()
+        .filter(|length| *length > 0)?;
+    // the suffixes sharing a prefix of that length are consecutive, keep the earliest suffix of `a`
+    // among the groups that also have a suffix of `b`
+    let mut found: Option<(usize, usize)> = None;
+    let mut group_start = 0;
+    for end in 1..=text.len() {
+        if end < text.len() && lcp[end] >= length {
+            continue;
+        }
+        let group = &suffixes[group_start..end];
+        let start_a = group.iter().copied().filter(|start| in_a(*start)).min();
+        let start_b = group.iter().copied().find(|start| !in_a(*start));
+        if let (Some(start_a), Some(start_b)) = (start_a, start_b) {
+            if found.is_none_or(|(first, _)| start_a < first) {
+                found = Some((start_a, start_b - a.len() - 1));
+            }
+        }
+        group_start = end;
+    }
+    found.map(|(start_a, start_b)| (start_a..start_a + length, start_b..start_b + length))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::{thread_rng, Rng};
+
+    use super::{longest_common_substring, SuffixArray};
+
+    /// A text of `len` letters drawn from the first `alphabet` letters
+    pub(crate) fn random_text(len: usize, alphabet: u8) -> Vec<u8> {
+        let mut rng = thread_rng();
+        (0..len)
+            .map(|_| b'a' + rng.gen_range(0..alphabet))
+            .collect()
+    }
+
+    /// The substrings of the text with their starts, shortest first
+    fn substrings(text: &[u8]) -> Vec<(&[u8], usize)> {
+        let mut substrings: Vec<(&[u8], usize)> = (1..=text.len())
+            .flat_map(|len| {
+                (0..=text.len() - len).map(move |start| (&text[start..start + len], start))
+            })
+            .collect();
+        substrings.sort_by_key(|(substring, start)| (substring.len(), *start));
+        substrings
+    }
+
+    #[test]
+    fn find_all_matches_naive() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let text = random_text(rng.gen_range(0..60), rng.gen_range(1..=3));
+            let suffix_array = SuffixArray::from_bytes(&text);
+            for _ in 0..10 {
+                let pattern = random_text(rng.gen_range(0..4), 3);
+                let expected: Vec<usize> = (0..text.len())
+                    .filter(|start| text[*start..].starts_with(&pattern))
+                    .collect();
+                assert_eq!(suffix_array.count(&pattern), expected.len());
+                assert_eq!(suffix_array.find_all(&pattern), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn longest_repeated_substring_matches_naive() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let text = random_text(rng.gen_range(0..40), rng.gen_range(1..=3));
+            let suffix_array = SuffixArray::from_bytes(&text);
+            let substrings = substrings(&text);
+            // the longest substring occurring twice, first in text order
+            let expected = substrings
+                .iter()
+                .filter(|(substring, _)| {
+                    substrings
+                        .iter()
+                        .filter(|(other, _)| other == substring)
+                        .count()
+                        > 1
+                })
+                .max_by(|(a, a_start), (b, b_start)| {
+                    a.len().cmp(&b.len()).then(b_start.cmp(a_start))
+                })
+                .map(|(substring, start)| *start..start + substring.len());
+            assert_eq!(
+                suffix_array.longest_repeated_substring(),
+                expected,
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn distinct_substring_count_matches_naive() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let text = random_text(rng.gen_range(0..40), rng.gen_range(1..=3));
+            let expected = substrings(&text)
+                .into_iter()
+                .map(|(substring, _)| substring)
+                .collect::<HashSet<_>>()
+                .len();
+            assert_eq!(
+                SuffixArray::from_bytes(&text).distinct_substring_count(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn longest_common_substring_matches_naive() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let a = random_text(rng.gen_range(0..30), rng.gen_range(1..=3));
+            let b = random_text(rng.gen_range(0..30), rng.gen_range(1..=3));
+            let in_b: HashSet<&[u8]> = substrings(&b).into_iter().map(|(s, _)| s).collect();
+            let length = substrings(&a)
+                .into_iter()
+                .filter(|(substring, _)| in_b.contains(substring))
+                .map(|(substring, _)| substring.len())
+                .max();
+            let expected_start = length.map(|length| {
+                (0..=a.len() - length)
+                    .find(|start| in_b.contains(&a[*start..start + length]))
+                    .unwrap()
+            });
+            let found = longest_common_substring(&a, &b);
+            assert_eq!(found.as_ref().map(|(in_a, _)| in_a.start), expected_start);
+            assert_eq!(found.as_ref().map(|(in_a, _)| in_a.len()), length);
+            if let Some((in_a, in_b)) = found {
+                assert_eq!(a[in_a], b[in_b]);
+            }
+        }
+    }
+
+    #[test]
+    fn generic_and_byte_suffix_arrays_agree() {
+        let text = "mississippi";
+        let bytes = SuffixArray::from_bytes(text.as_bytes());
+        let chars = SuffixArray::new(&text.chars().collect::<Vec<_>>());
+        assert_eq!(bytes.suffixes(), chars.suffixes());
+        assert_eq!(bytes.lcp_array(), chars.lcp_array());
+        assert_eq!(bytes.lcp_array(), &[0, 1, 1, 4, 0, 0, 1, 0, 2, 1, 3]);
+        assert!((0..text.len()).all(|i| bytes.suffixes()[bytes.rank(i)] == i));
+        assert!(SuffixArray::<u8>::from_bytes(b"").is_empty());
+    }
+
+    #[test]
+    fn lcp_of_any_suffixes_matches_naive() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let text = random_text(rng.gen_range(1..50), rng.gen_range(1..=3));
+            let suffix_array = SuffixArray::from_bytes(&text);
+            for i in 0..text.len() {
+                for j in 0..text.len() {
+                    let expected = text[i..]
+                        .iter()
+                        .zip(&text[j..])
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    assert_eq!(suffix_array.lcp(i, j), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compare_substrings_matches_naive() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let text = random_text(rng.gen_range(1..40), rng.gen_range(1..=3));
+            let suffix_array = SuffixArray::from_bytes(&text);
+            let before = suffix_array.clone();
+            for _ in 0..200 {
+                let a = rng.gen_range(0..=text.len());
+                let a = a..rng.gen_range(a..=text.len());
+                let b = rng.gen_range(0..=text.len());
+                let b = b..rng.gen_range(b..=text.len());
+                assert_eq!(
+                    suffix_array.compare_substrings(a.clone(), b.clone()),
+                    text[a.clone()].cmp(&text[b.clone()]),
+                    "{:?} {:?} {:?}",
+                    text,
+                    a,
+                    b
+                );
+            }
+            // building the range minimums doesn't change the suffix array
+            assert_eq!(suffix_array, before);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn comparing_substrings_past_the_text_panics() {
+        SuffixArray::from_bytes(b"abc").compare_substrings(0..1, 2..4);
+    }
+}
@@ -0,0 +1,238 @@
+//! SA-IS, suffix array construction by induced sorting in O(n + alphabet).
+//! Every suffix is S type if it is smaller than the next suffix and L type otherwise, and the leftmost S
+//! suffixes of the runs (LMS) split the text into substrings. Once the LMS suffixes are sorted, the other suffixes
+//! are placed by two scans of the buckets of their first symbol: the L suffixes left to right, the S suffixes
+//! right to left. The LMS substrings are sorted by the same induction, and if some are equal the LMS suffixes
+//! are sorted recursively on the text of their substring ranks, at most half as long.
+//! https://doi.org/10.1109/TC.2010.188
+//! The layout of the buckets follows the AtCoder Library.
+
+/// Marks an empty slot of the suffix array while inducing
+const EMPTY: usize = usize::MAX;
+
+/// Below this length the suffixes are sorted by comparison
+const NAIVE_THRESHOLD: usize = 10;
+
+/// The suffix array of the bytes: the start of each suffix, in lexicographic order of the suffixes
+/// ```
+/// # use datastructures_in_rust::suffix_array::sa_is::suffix_array;
+/// assert_eq!(suffix_array(b"banana"), vec![5, 3, 1, 0, 4, 2]);
+/// ```
+pub fn suffix_array(text: &[u8]) -> Vec<usize> {
+    let symbols: Vec<usize> = text.iter().map(|b| *b as usize).collect();
+    sa_is(&symbols, u8::MAX as usize)
+}
+
+/// The suffix array of any ordered symbols.
+/// The symbols are first replaced by their rank among the distinct symbols, which takes a sort: O(n log n).
+/// ```
+/// # use datastructures_in_rust::suffix_array::sa_is::suffix_array_of;
+/// assert_eq!(suffix_array_of(&[3, -1, 3, -1]), vec![3, 1, 2, 0]);
+/// ```
+pub fn suffix_array_of<T: Ord>(text: &[T]) -> Vec<usize> {
+    let (symbols, upper) = compress(text);
+    sa_is(&symbols, upper)
+}
+
+/// The rank of each symbol among the distinct symbols of the text, and the largest rank
+pub(crate) fn compress<T: Ord>(text: &[T]) -> (Vec<usize>, usize) {
+    let mut distinct: Vec<&T> = text.iter().collect();
+    distinct.sort_unstable();
+    distinct.dedup();
+    let symbols = text
+        .iter()
+        .map(|symbol| {
+            distinct
+                .binary_search(&symbol)
+                .expect("every symbol is in the distinct symbols")
+        })
+        .collect();
+    (symbols, distinct.len().saturating_sub(1))
+}
+
+/// The suffix array of the symbols, all of them in `0..=upper`
+pub(crate) fn sa_is(text: &[usize], upper: usize) -> Vec<usize> {
+    let n = text.len();
+    if n < NAIVE_THRESHOLD {
+        let mut suffixes: Vec<usize> = (0..n).collect();
+        suffixes.sort_unstable_by(|a, b| text[*a..].cmp(&text[*b..]));
+        return suffixes;
+    }
+    // is_s[i]: the suffix i is smaller than the suffix i + 1, the last suffix is L type
+    let mut is_s = vec![false; n];
+    for i in (0..n - 1).rev() {
+        is_s[i] = if text[i] == text[i + 1] {
+            is_s[i + 1]
+        } else {
+            text[i] < text[i + 1]
+        };
+    }
+    // the bucket of the symbol c starts with its L suffixes at l_starts[c], then its S suffixes at s_starts[c]
+    let mut l_starts = vec![0; upper + 2];
+    let mut s_starts = vec![0; upper + 1];
+    for i in 0..n {
+        if is_s[i] {
+            l_starts[text[i] + 1] += 1;
+        } else {
+            s_starts[text[i]] += 1;
+        }
+    }
+    for c in 0..=upper {
+        s_starts[c] += l_starts[c];
+        l_starts[c + 1] += s_starts[c];
+    }
+    l_starts.truncate(upper + 1);
+
+    let is_lms = |i: usize| i > 0 && !is_s[i - 1] && is_s[i];
+    let lms: Vec<usize> = (1..n).filter(|i| is_lms(*i)).collect();
+    // the index of each LMS suffix in `lms`
+    let mut lms_index = vec![EMPTY; n];
+    for (index, i) in lms.iter().enumerate() {
+        lms_index[*i] = index;
+    }
+
+    let mut suffixes = vec![EMPTY; n];
+    induce(text, &is_s, &lms, &l_starts, &s_starts, &mut suffixes);
+
+    if !lms.is_empty() {
+        // the LMS suffixes ordered by their LMS substring
+        let sorted_lms: Vec<usize> = suffixes
+            .iter()
+            .copied()
+            .filter(|i| lms_index[*i] != EMPTY)
+            .collect();
+        // the end (exclusive) of the LMS substring starting at the LMS suffix
+        let end = |i: usize| lms.get(lms_index[i] + 1).map_or(n, |next| *next + 1);
+        let mut reduced = vec![0; lms.len()];
+        let mut rank = 0;
+        for pair in sorted_lms.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            // the substring ending the text is unique, it is shorter than the others
+            if end(a) == n || end(b) == n || text[a..end(a)] != text[b..end(b)] {
+                rank += 1;
+            }
+            reduced[lms_index[b]] = rank;
+        }
+        let sorted_lms: Vec<usize> = sa_is(&reduced, rank)
+            .into_iter()
+            .map(|index| lms[index])
+            .collect();
+        induce(
+            text,
+            &is_s,
+            &sorted_lms,
+            &l_starts,
+            &s_starts,
+            &mut suffixes,
+        );
+    }
+    suffixes
+}
+
+/// Places the LMS suffixes (in order) in the S part of their bucket, then induces the L suffixes and the S suffixes
+fn induce(
+    text: &[usize],
+    is_s: &[bool],
+    lms: &[usize],
+    l_starts: &[usize],
+    s_starts: &[usize],
+    suffixes: &mut [usize],
+) {
+    let n = text.len();
+    suffixes.iter_mut().for_each(|suffix| *suffix = EMPTY);
+    // the S part of the buckets is filled left to right, it will be refilled by the S suffixes anyway
+    let mut next = s_starts.to_vec();
+    for i in lms {
+        suffixes[next[text[*i]]] = *i;
+        next[text[*i]] += 1;
+    }
+    // the L suffixes, left to right: the suffix before an L suffix already placed
+    let mut next = l_starts.to_vec();
+    suffixes[next[text[n - 1]]] = n - 1;
+    next[text[n - 1]] += 1;
+    for index in 0..n {
+        let i = suffixes[index];
+        if i != EMPTY && i > 0 && !is_s[i - 1] {
+            suffixes[next[text[i - 1]]] = i - 1;
+            next[text[i - 1]] += 1;
+        }
+    }
+    // the S suffixes, right to left from the end of each bucket, the start of the next bucket
+    let mut next: Vec<usize> = l_starts[1..].iter().copied().chain([n]).collect();
+    for index in (0..n).rev() {
+        let i = suffixes[index];
+        if i != EMPTY && i > 0 && is_s[i - 1] {
+            next[text[i - 1]] -= 1;
+            suffixes[next[text[i - 1]]] = i - 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+
+    use super::{suffix_array, suffix_array_of};
+
+    fn naive<T: Ord>(text: &[T]) -> Vec<usize> {
+        let mut suffixes: Vec<usize> = (0..text.len()).collect();
+        suffixes.sort_by(|a, b| text[*a..].cmp(&text[*b..]));
+        suffixes
+    }
+
+    #[test]
+    fn small_texts() {
+        assert_eq!(suffix_array(b""), Vec::<usize>::new());
+        assert_eq!(suffix_array(b"a"), vec![0]);
+        assert_eq!(suffix_array(b"mississippi"), naive(b"mississippi"));
+        assert_eq!(
+            suffix_array(b"abracadabra abracadabra"),
+            naive(b"abracadabra abracadabra")
+        );
+        let runs = [b'a'; 100];
+        assert_eq!(suffix_array(&runs), (0..100).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn matches_naive_on_random_texts() {
+        let mut rng = thread_rng();
+        for _ in 0..500 {
+            let len = rng.gen_range(0..300);
+            let alphabet = rng.gen_range(1..=4u8);
+            let text: Vec<u8> = (0..len)
+                .map(|_| b'a' + rng.gen_range(0..alphabet))
+                .collect();
+            assert_eq!(suffix_array(&text), naive(&text), "{:?}", text);
+        }
+        let text: Vec<u8> = (0..100_000).map(|_| rng.gen()).collect();
+        assert_eq!(suffix_array(&text), naive(&text));
+    }
+
+    #[test]
+    fn matches_naive_on_generic_symbols() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let len = rng.gen_range(0..200);
+            let text: Vec<i64> = (0..len)
+                .map(|_| rng.gen_range(-3..3) * 1_000_000_007)
+                .collect();
+            assert_eq!(suffix_array_of(&text), naive(&text));
+            let words: Vec<String> = text.iter().map(|v| v.to_string()).collect();
+            assert_eq!(suffix_array_of(&words), naive(&words));
+        }
+    }
+
+    #[test]
+    fn periodic_texts_recurse() {
+        // the LMS substrings of periodic texts are all equal, so the reduced texts are periodic too
+        for period in [b"ab".to_vec(), b"aab".to_vec(), b"abaabaab".to_vec()] {
+            let text = period.repeat(1_000);
+            assert_eq!(suffix_array(&text), naive(&text));
+        }
+        let fibonacci = (0..20).fold((b"a".to_vec(), b"ab".to_vec()), |(a, b), _| {
+            let next = [b.clone(), a].concat();
+            (b, next)
+        });
+        assert_eq!(suffix_array(&fibonacci.1), naive(&fibonacci.1));
+    }
+}
@@ -0,0 +1,314 @@
+//! Suffix automaton, the smallest automaton accepting the suffixes of a text, built online.
+//! Each state is a class of substrings with the same set of end positions, the longest of them is `length`
+//! long and the others are its suffixes down to (excluding) the longest string of the suffix link.
+//! Appending a symbol adds a state, and at most one clone when a class splits: at most `2n - 1` states.
+//! Unlike a [`SuffixArray`](super::SuffixArray) it doesn't have to be rebuilt as the text grows.
+//! https://cp-algorithms.com/string/suffix-automaton.html
+use std::{collections::BTreeMap, iter::FromIterator, ops::Range, sync::OnceLock};
+
+/// A state of the automaton
+#[derive(Debug, Clone, PartialEq)]
+struct State<T> {
+    /// The length of the longest substring of the state
+    length: usize,
+    /// The state of the longest suffix of that substring that ends at more positions, None for the root
+    link: Option<usize>,
+    next: BTreeMap<T, usize>,
+    /// The end of the first occurrence of the substrings of the state (exclusive)
+    first_end: usize,
+    /// True if the state was split off another one, it doesn't add an end position of its own
+    is_clone: bool,
+}
+
+impl<T> State<T> {
+    fn new(length: usize, first_end: usize) -> Self {
+        State {
+            length,
+            link: None,
+            next: BTreeMap::new(),
+            first_end,
+            is_clone: false,
+        }
+    }
+}
+
+/// The suffix automaton of a text that grows one symbol at a time.
+/// ```
+/// # use datastructures_in_rust::suffix_array::suffix_automaton::SuffixAutomaton;
+/// let mut automaton: SuffixAutomaton<char> = "abab".chars().collect();
+/// assert!(automaton.contains(&['b', 'a']));
+/// assert_eq!(automaton.occurrences(&['a', 'b']), 2);
+/// // a, b, ab, ba, aba, bab, abab
+/// assert_eq!(automaton.distinct_substrings(), 7);
+/// automaton.extend('c');
+/// assert!(automaton.contains(&['b', 'c']));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SuffixAutomaton<T> {
+    /// The root, the class of the empty string, is the state 0
+    states: Vec<State<T>>,
+    /// The state of the whole text
+    last: usize,
+    len: usize,
+    distinct_substrings: usize,
+    /// The number of end positions of each state, computed again after the text grows
+    occurrences: OnceLock<Vec<usize>>,
+}
+
+impl<T: Ord + Clone> Default for SuffixAutomaton<T> {
+    fn default() -> Self {
+        SuffixAutomaton::new()
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<T> for SuffixAutomaton<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(text: I) -> Self {
+        let mut automaton = SuffixAutomaton::new();
+        text.into_iter().for_each(|symbol| automaton.extend(symbol));
+        automaton
+    }
+}
+
+impl<T: Ord + Clone> SuffixAutomaton<T> {
+    /// Creates the automaton of the empty text
+    pub fn new() -> Self {
+        SuffixAutomaton {
+            states: vec![State::new(0, 0)],
+            last: 0,
+            len: 0,
+            distinct_substrings: 0,
+            occurrences: OnceLock::new(),
+        }
+    }
+
+    /// The length of the text
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of states with the root, at most `2n - 1` for a text of length `n > 1`
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Appends a symbol to the text, O(1) amortized (times the cost of the transition maps)
+    pub fn extend(&mut self, symbol: T) {
+        self.len += 1;
+        let current = self.states.len();
+        self.states.push(State::new(self.len, self.len));
+        // the suffixes that can't be followed by the symbol yet now lead to the new state
+        let mut suffix = Some(self.last);
+        while let Some(state) = suffix {
+            if self.states[state].next.contains_key(&symbol) {
+                break;
+            }
+            self.states[state].next.insert(symbol.clone(), current);
+            suffix = self.states[state].link;
+        }
+        let link = match suffix {
+            None => 0,
+            Some(state) => {
+                let next = self.states[state].next[&symbol];
+                if self.states[state].length + 1 == self.states[next].length {
+                    next
+                } else {
+                    // the class of `next` ends at the new position only for its strings up to this length, split it
+                    let clone = self.states.len();
+                    self.states.push(State {
+                        length: self.states[state].length + 1,
+                        is_clone: true,
+                        ..self.states[next].clone()
+                    });
+                    let mut suffix = Some(state);
+                    while let Some(state) = suffix {
+                        match self.states[state].next.get_mut(&symbol) {
+                            Some(target) if *target == next => *target = clone,
+                            _ => break,
+                        }
+                        suffix = self.states[state].link;
+                    }
+                    self.states[next].link = Some(clone);
+                    clone
+                }
+            }
+        };
+        self.states[current].link = Some(link);
+        self.last = current;
+        // the suffixes of the text that occur for the first time
+        self.distinct_substrings += self.len - self.states[link].length;
+        self.occurrences = OnceLock::new();
+    }
+
+    /// True if the pattern is a substring of the text, O(m) for a pattern of length m
+    pub fn contains(&self, pattern: &[T]) -> bool {
+        self.state_of(pattern).is_some()
+    }
+
+    /// The number of (possibly overlapping) occurrences of the pattern, O(m) for a pattern of length m.
+    /// The first call after the text grew counts the end positions of every state, O(n).
+    /// The empty pattern occurs once per symbol, like it prefixes every suffix of a suffix array.
+    pub fn occurrences(&self, pattern: &[T]) -> usize {
+        match self.state_of(pattern) {
+            Some(state) => self.occurrences.get_or_init(|| self.count_occurrences())[state],
+            None => 0,
+        }
+    }
+
+    /// The number of distinct non empty substrings of the text, O(1)
+    pub fn distinct_substrings(&self) -> usize {
+        self.distinct_substrings
+    }
+
+    /// The ranges of the text and of `other` of their longest common substring, the first one in the order of `other`
+    /// among the longest, and its first occurrence in the text. None if they have no symbol in common.
+    /// O(m) for `other` of length m: `other` is matched while the match can be extended, and the match is shortened
+    /// to its suffix link when it can't.
+    /// ```
+    /// # use datastructures_in_rust::suffix_array::suffix_automaton::SuffixAutomaton;
+    /// let automaton: SuffixAutomaton<u8> = b"xabcdey".iter().copied().collect();
+    /// assert_eq!(automaton.longest_common_substring(b"zzbcdz"), Some((2..5, 2..5)));
+    /// ```
+    pub fn longest_common_substring(&self, other: &[T]) -> Option<(Range<usize>, Range<usize>)> {
+        // the longest match ending at each position of other, with its state
+        let (mut state, mut length) = (0, 0);
+        let mut longest: Option<(usize, usize, usize)> = None;
+        for (end, symbol) in other.iter().enumerate() {
+            while state != 0 && !self.states[state].next.contains_key(symbol) {
+                state = self.states[state].link.expect("only the root has no link");
+                length = self.states[state].length;
+            }
+            match self.states[state].next.get(symbol) {
+                Some(next) => {
+                    state = *next;
+                    length += 1;
+                }
+                None => continue,
+            }
+            if longest.is_none_or(|(longest, _, _)| length > longest) {
+                longest = Some((length, state, end + 1));
+            }
+        }
+        longest.map(|(length, state, end)| {
+            let first_end = self.states[state].first_end;
+            (first_end - length..first_end, end - length..end)
+        })
+    }
+
+    /// The state reached by the pattern from the root
+    fn state_of(&self, pattern: &[T]) -> Option<usize> {
+        pattern.iter().try_fold(0, |state, symbol| {
+            self.states[state].next.get(symbol).copied()
+        })
+    }
+
+    /// The number of end positions of each state: one for each state that is not a clone,
+    /// added to its suffix links, longest states first. The lengths are at most `n`, so the states are
+    /// sorted by a counting sort, O(n).
+    fn count_occurrences(&self) -> Vec<usize> {
+        let mut occurrences: Vec<usize> = self
+            .states
+            .iter()
+            .enumerate()
+            .map(|(index, state)| (index != 0 && !state.is_clone) as usize)
+            .collect();
+        // the number of states of each length, then the end of the states of each length
+        let mut ends = vec![0; self.len + 1];
+        self.states.iter().for_each(|state| ends[state.length] += 1);
+        for length in 1..ends.len() {
+            ends[length] += ends[length - 1];
+        }
+        let mut by_length = vec![0; self.states.len()];
+        for (index, state) in self.states.iter().enumerate() {
+            ends[state.length] -= 1;
+            by_length[ends[state.length]] = index;
+        }
+        for state in by_length.into_iter().rev() {
+            if let Some(link) = self.states[state].link {
+                occurrences[link] += occurrences[state];
+            }
+        }
+        occurrences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::{thread_rng, Rng};
+
+    use super::SuffixAutomaton;
+    use crate::suffix_array::{longest_common_substring, tests::random_text};
+
+    #[test]
+    fn queries_match_naive_as_the_text_grows() {
+        let mut rng = thread_rng();
+        for _ in 0..50 {
+            let text = random_text(rng.gen_range(0..40), rng.gen_range(1..=3));
+            let mut automaton = SuffixAutomaton::new();
+            for (end, symbol) in text.iter().enumerate() {
+                automaton.extend(*symbol);
+                let text = &text[..=end];
+                let distinct: HashSet<&[u8]> = (0..text.len())
+                    .flat_map(|start| (start + 1..=text.len()).map(move |end| &text[start..end]))
+                    .collect();
+                assert_eq!(automaton.distinct_substrings(), distinct.len());
+                assert!(automaton.state_count() <= (2 * text.len() - 1).max(2));
+                for _ in 0..10 {
+                    let pattern = random_text(rng.gen_range(0..5), 3);
+                    let expected = (0..=text.len().saturating_sub(pattern.len()))
+                        .filter(|start| text[*start..].starts_with(&pattern))
+                        .count();
+                    let expected = if pattern.is_empty() {
+                        text.len()
+                    } else {
+                        expected
+                    };
+                    assert_eq!(
+                        automaton.contains(&pattern),
+                        expected > 0 || pattern.is_empty()
+                    );
+                    assert_eq!(automaton.occurrences(&pattern), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn longest_common_substring_matches_the_suffix_array() {
+        let mut rng = thread_rng();
+        for _ in 0..300 {
+            let a = random_text(rng.gen_range(0..30), rng.gen_range(1..=3));
+            let b = random_text(rng.gen_range(0..30), rng.gen_range(1..=3));
+            let automaton: SuffixAutomaton<u8> = a.iter().copied().collect();
+            let found = automaton.longest_common_substring(&b);
+            // the suffix array picks the first occurrence in the order of its first argument
+            let expected = longest_common_substring(&b, &a);
+            assert_eq!(
+                found.as_ref().map(|(_, in_b)| in_b.clone()),
+                expected.as_ref().map(|(in_b, _)| in_b.clone())
+            );
+            if let Some((in_a, in_b)) = found {
+                assert_eq!(a[in_a.clone()], b[in_b]);
+                // the first occurrence in a
+                assert!(!a[..in_a.end - 1]
+                    .windows(in_a.len())
+                    .any(|w| w == &a[in_a.clone()]));
+            }
+        }
+    }
+
+    #[test]
+    fn texts_of_any_symbols() {
+        let words = ["to", "be", "or", "not", "to", "be"];
+        let automaton: SuffixAutomaton<&str> = words.iter().copied().collect();
+        assert_eq!(automaton.occurrences(&["to", "be"]), 2);
+        assert!(!automaton.contains(&["be", "to"]));
+        assert_eq!(automaton.len(), 6);
+        assert!(SuffixAutomaton::<u8>::default().is_empty());
+    }
+}
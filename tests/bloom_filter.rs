@@ -0,0 +1,73 @@
+use datastructures_in_rust::bloom_filter::{
+    counting_bloom_filter::CountingBloomFilter,
+    cuckoo_filter::CuckooFilter,
+    scalable_bloom_filter::ScalableBloomFilter,
+    standard_bloom_filter::{BloomFilter, BloomFilterError},
+    xor_filter::XorFilter,
+    ApproximateMembership,
+};
+
+/// Every shard builds the filter of its items and ships it as bytes, the filters are merged on arrival
+#[test]
+fn shards_merge_into_the_filter_of_all_the_items() {
+    let shards = 8u64;
+    let items = 10_000u64;
+    let payloads: Vec<Vec<u8>> = (0..shards)
+        .map(|shard| {
+            let mut filter = BloomFilter::new_with_seed(items as usize, 0.01, 7);
+            (0..items).filter(|i| i % shards == shard).for_each(|i| {
+                filter.insert(&i);
+            });
+            filter.to_bytes()
+        })
+        .collect();
+    let mut merged = BloomFilter::new_with_seed(items as usize, 0.01, 7);
+    for payload in &payloads {
+        merged
+            .union(&BloomFilter::from_bytes(payload).unwrap())
+            .unwrap();
+    }
+    assert!((0..items).all(|i| merged.contains(&i)));
+    let estimated = merged.estimated_len();
+    assert!((9_500.0..10_500.0).contains(&estimated), "{}", estimated);
+    let false_positives = (items..2 * items).filter(|i| merged.contains(i)).count();
+    assert!(false_positives < 150, "{}", false_positives);
+
+    // a shard configured differently is rejected
+    let other = BloomFilter::new_with_seed(items as usize, 0.01, 8);
+    let other = BloomFilter::from_bytes(&other.to_bytes()).unwrap();
+    assert!(matches!(
+        merged.union(&other),
+        Err(BloomFilterError::IncompatibleParameters { .. })
+    ));
+}
+
+/// The filters are picked at runtime behind the shared trait
+#[test]
+fn filters_are_interchangeable_as_trait_objects() {
+    let keys = 1_000u64;
+    let mut bloom = BloomFilter::new(keys as usize, 0.01);
+    let mut counting: CountingBloomFilter = CountingBloomFilter::new(keys as usize, 0.01);
+    let mut scalable = ScalableBloomFilter::new(100, 0.01);
+    let mut cuckoo: CuckooFilter = CuckooFilter::new(keys as usize);
+    for key in 0..keys {
+        bloom.insert(&key);
+        counting.insert(&key).unwrap();
+        scalable.insert(&key);
+        cuckoo.insert(&key).unwrap();
+    }
+    let xor: XorFilter = XorFilter::new(0..keys);
+    let filters: Vec<Box<dyn ApproximateMembership<u64>>> = vec![
+        Box::new(bloom),
+        Box::new(counting),
+        Box::new(scalable),
+        Box::new(cuckoo),
+        Box::new(xor),
+    ];
+    for filter in &filters {
+        assert!((0..keys).all(|key| filter.contains(&key)));
+        let false_positives = (keys..2 * keys).filter(|key| filter.contains(key)).count();
+        assert!(false_positives < 50, "{}", false_positives);
+        assert!(filter.bit_count() > 0);
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use datastructures_in_rust::{
+    disjoint_set::weighted_disjoint_set::WeightedDisjointSet,
+    intervals::ops::Product,
+    leetcode::parser::{Item, ItemConversionError, LeetCodeParser},
+};
+
+/// LeetCode 399, every equation `a / b = v` is the relative value `value(a) = value(b) * v`
+fn calc_equation(
+    equations: Vec<Vec<String>>,
+    values: Vec<f64>,
+    queries: Vec<Vec<String>>,
+) -> Vec<f64> {
+    let mut set = WeightedDisjointSet::new(0);
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    for (equation, value) in equations.into_iter().zip(values) {
+        let [a, b] = [&equation[0], &equation[1]]
+            .map(|name| *indices.entry(name.clone()).or_insert_with(|| set.add()));
+        set.union(b, a, Product(value))
+            .expect("the equations do not contradict each other");
+    }
+    queries
+        .iter()
+        .map(
+            |query| match (indices.get(&query[0]), indices.get(&query[1])) {
+                (Some(a), Some(b)) => set.diff(*b, *a).map_or(-1.0, |ratio| ratio.0),
+                _ => -1.0,
+            },
+        )
+        .collect()
+}
+
+fn solve(input: &str) -> Result<Vec<f64>, ItemConversionError> {
+    let mut parser = LeetCodeParser::from_lines(input)?;
+    let mut next = || {
+        parser
+            .next_item()
+            .ok_or(ItemConversionError::FailedToConvert)
+    };
+    let equations = next()?.into_vec(|e| e.into_vec(Item::into_string))?;
+    let values = next()?.into_vec(Item::into_parsed)?;
+    let queries = next()?.into_vec(|q| q.into_vec(Item::into_string))?;
+    Ok(calc_equation(equations, values, queries))
+}
+
+#[test]
+fn evaluate_division_works() -> Result<(), ItemConversionError> {
+    let answers = solve(concat!(
+        r#"[["a","b"],["b","c"]]"#,
+        "\n",
+        "[2.0,3.0]\n",
+        r#"[["a","c"],["b","a"],["a","e"],["a","a"],["x","x"]]"#,
+    ))?;
+    assert_eq!(answers, vec![6.0, 0.5, -1.0, 1.0, -1.0]);
+
+    let answers = solve(concat!(
+        r#"[["a","b"],["b","c"],["bc","cd"]]"#,
+        "\n",
+        "[1.5,2.5,5.0]\n",
+        r#"[["a","c"],["c","b"],["bc","cd"],["cd","bc"]]"#,
+    ))?;
+    assert_eq!(answers, vec![3.75, 0.4, 5.0, 0.2]);
+    Ok(())
+}
+
+/// Redundant equations whose ratios are only equal up to rounding
+#[test]
+fn redundant_inexact_equations_work() -> Result<(), ItemConversionError> {
+    let answers = solve(concat!(
+        r#"[["a","b"],["b","c"],["a","c"],["c","d"],["a","d"]]"#,
+        "\n",
+        "[0.1,0.7,0.07,1.1,0.077]\n",
+        r#"[["a","d"],["d","b"],["c","a"]]"#,
+    ))?;
+    let expected = [0.077, 1.0 / 0.77, 1.0 / 0.07];
+    for (answer, expected) in answers.iter().zip(expected) {
+        assert!((answer - expected).abs() < 1e-9, "{} {}", answer, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn contradicting_equations_are_rejected() {
+    let mut set = WeightedDisjointSet::new(3);
+    assert_eq!(set.union(0, 1, Product(2.0)), Ok(true));
+    assert_eq!(set.union(1, 2, Product(4.0)), Ok(true));
+    assert_eq!(set.union(0, 2, Product(8.0)), Ok(false));
+    let error = set.union(2, 0, Product(8.0)).unwrap_err();
+    assert_eq!(error.known, Product(0.125));
+    assert_eq!(error.requested, Product(8.0));
+}
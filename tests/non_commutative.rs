@@ -0,0 +1,143 @@
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use datastructures_in_rust::intervals::{
+    brute_force::BruteForce,
+    segment_tree::{
+        array_based_segment_tree::ArrayBasedSegmentTree, dynamic_segment_tree::DynamicSegmentTree,
+        dynamic_segment_tree_with_range_updates::DynamicSegmentTreeWithRangeUpdates,
+    },
+};
+use rand::{seq::SliceRandom, thread_rng, Rng};
+
+const MODULUS: u64 = 1_000_000_007;
+
+/// A 2x2 matrix modulo a prime, matrix multiplication is associative but not commutative.
+/// The default is the identity matrix, the value of the elements that were never set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Matrix([[u64; 2]; 2]);
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Matrix([[1, 0], [0, 1]])
+    }
+}
+
+fn multiply(a: Matrix, b: Matrix) -> Matrix {
+    let mut result = [[0; 2]; 2];
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, cell) in row.iter_mut().enumerate() {
+            *cell = (a.0[i][0] * b.0[0][j] + a.0[i][1] * b.0[1][j]) % MODULUS;
+        }
+    }
+    Matrix(result)
+}
+
+fn random_matrix<R: Rng>(rng: &mut R) -> Matrix {
+    Matrix([
+        [rng.gen_range(0..MODULUS), rng.gen_range(0..MODULUS)],
+        [rng.gen_range(0..MODULUS), rng.gen_range(0..MODULUS)],
+    ])
+}
+
+fn random_range<R: Rng>(rng: &mut R, len: usize) -> RangeInclusive<usize> {
+    let start = rng.gen_range(0..len);
+    start..=rng.gen_range(start..len)
+}
+
+fn to_i64(range: &RangeInclusive<usize>) -> RangeInclusive<i64> {
+    *range.start() as i64..=*range.end() as i64
+}
+
+#[test]
+fn matrices_are_multiplied_in_index_order() {
+    let mut rng = thread_rng();
+    let len = 200;
+    let values: Vec<Matrix> = (0..len).map(|_| random_matrix(&mut rng)).collect();
+    // the product of a few matrices differs from the product in any other order
+    assert_ne!(
+        multiply(values[0], values[1]),
+        multiply(values[1], values[0])
+    );
+    let mut st = ArrayBasedSegmentTree::new(&values, Box::new(multiply));
+    let mut dst = DynamicSegmentTree::new_with_values(&values, Arc::new(multiply));
+    let mut dst_r =
+        DynamicSegmentTreeWithRangeUpdates::new_with_values(&values, Arc::new(multiply));
+    let built_st = ArrayBasedSegmentTree::build(&values, Box::new(multiply));
+    let built_dst = DynamicSegmentTree::build(&values, Arc::new(multiply));
+    let mut bt = BruteForce::new(&values, Box::new(multiply));
+    for q in (0..500).map(|_| random_range(&mut rng, len)) {
+        assert_eq!(built_st.query(q.clone()), bt.query(q.clone()));
+        assert_eq!(built_dst.query(to_i64(&q)), bt.query(q.clone()));
+    }
+    for _ in 0..1_000 {
+        let value = random_matrix(&mut rng);
+        let range = if rng.gen_bool(0.5) {
+            let index = rng.gen_range(0..len);
+            index..=index
+        } else {
+            random_range(&mut rng, len)
+        };
+        bt.update(range.clone(), value);
+        st.update(range.clone(), value);
+        // the dynamic segment tree has point updates only
+        range.clone().for_each(|index| dst.set(index as i64, value));
+        dst_r.update(to_i64(&range), value);
+        let q = random_range(&mut rng, len);
+        let expected = bt.query(q.clone());
+        assert_eq!(st.query(q.clone()), expected);
+        assert_eq!(dst.query(to_i64(&q)), expected);
+        assert_eq!(dst_r.query(to_i64(&q)), expected);
+    }
+    assert_eq!(st.validate(), Ok(()));
+    assert_eq!(dst.validate(), Ok(()));
+    assert_eq!(dst_r.validate(), Ok(()));
+}
+
+#[test]
+fn sparse_insertions_in_random_order_are_merged_in_index_order() {
+    let mut rng = thread_rng();
+    let mut entries: Vec<(i64, Matrix)> = (0..300)
+        .map(|i| (i * 3_333_331, random_matrix(&mut rng)))
+        .collect();
+    entries.shuffle(&mut rng);
+    let mut dst = DynamicSegmentTree::new(0..=1_000_000_000, Arc::new(multiply));
+    for (index, value) in entries.iter() {
+        dst.insert(*index, *value);
+    }
+    assert_eq!(dst.validate(), Ok(()));
+    entries.sort_by_key(|(index, _)| *index);
+    let indices: Vec<i64> = entries.iter().map(|(index, _)| *index).collect();
+    let values: Vec<Matrix> = entries.iter().map(|(_, value)| *value).collect();
+    let bt = BruteForce::new(&values, Box::new(multiply));
+    for _ in 0..500 {
+        let start = rng.gen_range(0..=1_000_000_000);
+        let end = rng.gen_range(start..=1_000_000_000);
+        let first = indices.partition_point(|index| *index < start);
+        let last = indices.partition_point(|index| *index <= end);
+        let expected = if first < last {
+            bt.query(first..=last - 1)
+        } else {
+            None
+        };
+        assert_eq!(dst.query(start..=end), expected);
+    }
+}
+
+#[test]
+fn strings_are_concatenated_in_index_order() {
+    let words: Vec<String> = "the quick brown fox jumps over the lazy dog"
+        .split(' ')
+        .map(|w| w.to_string())
+        .collect();
+    let concat = |a: String, b: String| a + &b;
+    let st = ArrayBasedSegmentTree::new(&words, Box::new(concat));
+    let dst = DynamicSegmentTree::new_with_values(&words, Arc::new(concat));
+    let mut dst_r = DynamicSegmentTreeWithRangeUpdates::new_with_values(&words, Arc::new(concat));
+    assert_eq!(st.query(1..=3), Some("quickbrownfox".to_string()));
+    assert_eq!(dst.query(1..=3), Some("quickbrownfox".to_string()));
+    assert_eq!(dst_r.query(1..=3), Some("quickbrownfox".to_string()));
+    dst_r.update(2..=4, "ab".to_string());
+    assert_eq!(dst_r.query(0..=5), Some("thequickabababover".to_string()));
+    assert_eq!(dst_r.validate(), Ok(()));
+}
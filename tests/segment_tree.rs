@@ -1,8 +1,13 @@
 use std::ops::RangeInclusive;
-use std::rc::Rc;
+use std::sync::Arc;
+use std::thread;
 
 use datastructures_in_rust::intervals::{
     brute_force::BruteForce,
+    ops::{
+        self, Checked, CheckedSum, MaxSubarraySum, MaxWithIndex, Min, Overflow, SaturatingSum,
+        WrappingSum,
+    },
     segment_tree::{
         array_based_segment_tree::ArrayBasedSegmentTree, dynamic_segment_tree::DynamicSegmentTree,
         dynamic_segment_tree_with_range_updates::DynamicSegmentTreeWithRangeUpdates,
@@ -14,9 +19,9 @@ fn query_works() {
     let values = (1..=1000).collect::<Vec<i32>>();
     let st = ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
     let dst: DynamicSegmentTree<i32> =
-        DynamicSegmentTree::new_with_values(&values, Rc::new(|a, b| a + b));
-    let mut dst_r: DynamicSegmentTreeWithRangeUpdates<i32> =
-        DynamicSegmentTreeWithRangeUpdates::new_with_values(&values, Rc::new(|a, b| a + b));
+        DynamicSegmentTree::new_with_values(&values, Arc::new(|a, b| a + b));
+    let dst_r: DynamicSegmentTreeWithRangeUpdates<i32> =
+        DynamicSegmentTreeWithRangeUpdates::new_with_values(&values, Arc::new(|a, b| a + b));
     let bt = BruteForce::new(&values, Box::new(|a, b| a + b));
     let queries = query_range(values.len(), 1000);
     queries.iter().for_each(|q| {
@@ -31,19 +36,96 @@ fn query_works() {
 fn update_works() {
     let values = (1..=10).collect::<Vec<i32>>();
     let mut dst_r: DynamicSegmentTreeWithRangeUpdates<i32> =
-        DynamicSegmentTreeWithRangeUpdates::new_with_values(&values, Rc::new(|a, b| a + b));
+        DynamicSegmentTreeWithRangeUpdates::new_with_values(&values, Arc::new(|a, b| a + b));
     let mut bt = BruteForce::new(&values, Box::new(|a, b| a + b));
     let queries = query_range(values.len(), 10);
     queries.iter().for_each(|q| {
         let (s, e) = (*q.start(), *q.end());
         bt.update(s..=e, 10);
         dst_r.update(s as i64..=e as i64, 10);
+        assert_eq!(dst_r.validate(), Ok(()));
         print!("bt:{:?}", bt.values);
         dst_r.pretty_print();
         assert_eq!(dst_r.query(s as i64..=e as i64), bt.query(q.clone()));
     });
 }
 
+/// A range update assigns the value to each element, the query merges it once per element
+#[test]
+fn range_update_assigns_every_element() {
+    let mut dst_r: DynamicSegmentTreeWithRangeUpdates<i64> =
+        DynamicSegmentTreeWithRangeUpdates::new(0..=9, Arc::new(|a, b| a + b));
+    dst_r.update(0..=9, 1);
+    assert_eq!(dst_r.query(0..=9), Some(10));
+    assert_eq!(dst_r.query(0..=4), Some(5));
+    dst_r.update(3..=5, 2);
+    assert_eq!(dst_r.query(0..=9), Some(13));
+}
+
+#[test]
+fn trees_are_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ArrayBasedSegmentTree<i64>>();
+    assert_send_sync::<DynamicSegmentTree<i64>>();
+    assert_send_sync::<DynamicSegmentTreeWithRangeUpdates<i64>>();
+}
+
+#[test]
+fn trees_can_be_shared_across_threads() {
+    let values = (1..=1000).collect::<Vec<i64>>();
+    let st = ArrayBasedSegmentTree::build(&values, Box::new(|a, b| a + b));
+    let dst = DynamicSegmentTree::build(&values, Arc::new(|a, b| a + b));
+    let dst_r = DynamicSegmentTreeWithRangeUpdates::build(&values, Arc::new(|a, b| a + b));
+    let bt = BruteForce::new(&values, Box::new(|a, b| a + b));
+    let queries = query_range(values.len(), 1000);
+    let (st, dst, dst_r, bt) = (&st, &dst, &dst_r, &bt);
+    thread::scope(|s| {
+        for chunk in queries.chunks(100) {
+            s.spawn(move || {
+                chunk.iter().for_each(|q| {
+                    let (s, e) = (*q.start(), *q.end());
+                    assert_eq!(st.query(q.clone()), bt.query(q.clone()));
+                    assert_eq!(dst.query(s as i64..=e as i64), bt.query(q.clone()));
+                    assert_eq!(dst_r.query(s as i64..=e as i64), bt.query(q.clone()));
+                });
+            });
+        }
+    });
+}
+
+#[test]
+fn parallel_build_and_query_batch_work() {
+    let mut values = (1..=100_000).collect::<Vec<i64>>();
+    let st = ArrayBasedSegmentTree::build(&values, Box::new(|a, b| a + b));
+    let dst = DynamicSegmentTree::build(&values, Arc::new(|a, b| a + b));
+    let mut dst_r = DynamicSegmentTreeWithRangeUpdates::build(&values, Arc::new(|a, b| a + b));
+    let queries = query_range(50_000, 100_000);
+    let queries_i64: Vec<RangeInclusive<i64>> = queries
+        .iter()
+        .map(|q| *q.start() as i64..=*q.end() as i64)
+        .collect();
+    let expected = prefix_sum_queries(&values, &queries);
+    assert_eq!(st.query_batch(&queries), expected);
+    assert_eq!(dst.query_batch(&queries_i64), expected);
+    assert_eq!(dst_r.query_batch(&queries_i64), expected);
+    // pending range updates are accounted for by the batch queries
+    dst_r.update(100..=90_000, 3);
+    values[100..=90_000].iter_mut().for_each(|v| *v = 3);
+    let expected = prefix_sum_queries(&values, &queries);
+    assert_eq!(dst_r.query_batch(&queries_i64), expected);
+}
+
+fn prefix_sum_queries(values: &[i64], queries: &[RangeInclusive<usize>]) -> Vec<Option<i64>> {
+    let mut prefix_sums = vec![0];
+    values
+        .iter()
+        .for_each(|v| prefix_sums.push(prefix_sums.last().unwrap() + v));
+    queries
+        .iter()
+        .map(|q| Some(prefix_sums[q.end() + 1] - prefix_sums[*q.start()]))
+        .collect()
+}
+
 fn query_range(size: usize, max: i32) -> Vec<RangeInclusive<usize>> {
     let mut result = Vec::new();
     let max: usize = max as usize;
@@ -59,3 +141,99 @@ fn query_range(size: usize, max: i32) -> Vec<RangeInclusive<usize>> {
     }
     result
 }
+
+#[test]
+fn ops_work_with_all_trees() {
+    let mut rng = thread_rng();
+    let raw: Vec<i64> = (0..500).map(|_| rng.gen_range(-100..100)).collect();
+    let queries = query_range(raw.len(), 500);
+
+    let values: Vec<MaxSubarraySum<i64>> = raw.iter().map(|v| MaxSubarraySum::new(*v)).collect();
+    let st = ArrayBasedSegmentTree::new(&values, ops::boxed());
+    let dst = DynamicSegmentTree::new_with_values(&values, ops::shared());
+    let dst_r = DynamicSegmentTreeWithRangeUpdates::new_with_values(&values, ops::shared());
+    let bt = BruteForce::new(&values, ops::boxed());
+    queries.iter().for_each(|q| {
+        let expected = bt.query(q.clone());
+        assert_eq!(st.query(q.clone()), expected);
+        assert_eq!(dst.query(*q.start() as i64..=*q.end() as i64), expected);
+        assert_eq!(dst_r.query(*q.start() as i64..=*q.end() as i64), expected);
+    });
+
+    let values: Vec<MaxWithIndex<i64>> = raw
+        .iter()
+        .enumerate()
+        .map(|(i, v)| MaxWithIndex::new(*v, i as i64))
+        .collect();
+    let st = ArrayBasedSegmentTree::new(&values, ops::boxed());
+    let dst = DynamicSegmentTree::new_with_values(&values, ops::shared());
+    let bt = BruteForce::new(&values, ops::boxed());
+    queries.iter().for_each(|q| {
+        let expected = bt.query(q.clone());
+        assert_eq!(st.query(q.clone()), expected);
+        assert_eq!(dst.query(*q.start() as i64..=*q.end() as i64), expected);
+    });
+
+    // the elements the range tree has not seen hold the identity
+    let mut dst_r: DynamicSegmentTreeWithRangeUpdates<Min<i64>> =
+        DynamicSegmentTreeWithRangeUpdates::new(-1_000_000..=1_000_000, ops::shared());
+    dst_r.update(-10..=10, Min(5));
+    dst_r.update(0..=3, Min(-2));
+    assert_eq!(dst_r.query(-1_000_000..=-11), Some(Min(i64::MAX)));
+    assert_eq!(dst_r.query(-20..=20), Some(Min(-2)));
+    assert_eq!(dst_r.query(4..=20), Some(Min(5)));
+    assert_eq!(dst_r.validate(), Ok(()));
+}
+
+#[test]
+fn checked_sums_surface_overflow() {
+    // the sum of 1..=100_000 does not fit an i32
+    let values: Vec<Checked<CheckedSum<i32>, Overflow>> = (1..=100_000)
+        .map(|v| Checked::from(CheckedSum(v)))
+        .collect();
+    let mut st = ArrayBasedSegmentTree::new(&values, ops::boxed());
+    let mut dst = DynamicSegmentTree::new_with_values(&values, ops::shared());
+    let mut dst_r = DynamicSegmentTreeWithRangeUpdates::new_with_values(&values, ops::shared());
+    assert_eq!(st.try_query(0..=99_999), Err(Overflow));
+    assert_eq!(dst.try_query(0..=99_999), Err(Overflow));
+    assert_eq!(dst_r.try_query(0..=99_999), Err(Overflow));
+    // the ranges that fit are still answered
+    assert_eq!(st.try_query(0..=999), Ok(Some(CheckedSum(500_500))));
+    assert_eq!(dst.try_query(0..=999), Ok(Some(CheckedSum(500_500))));
+    assert_eq!(dst_r.try_query(0..=999), Ok(Some(CheckedSum(500_500))));
+
+    assert_eq!(st.try_update(0..=99_999, CheckedSum(1)), Ok(()));
+    assert_eq!(st.try_query(0..=99_999), Ok(Some(CheckedSum(100_000))));
+    assert_eq!(dst_r.try_update(0..=99_999, CheckedSum(1)), Ok(()));
+    assert_eq!(dst_r.try_query(10..=99_999), Ok(Some(CheckedSum(99_990))));
+    assert_eq!(
+        dst_r.try_update(50_000..=50_001, CheckedSum(i32::MAX)),
+        Err(Overflow)
+    );
+    assert_eq!(dst_r.try_query(50_001..=60_000), Err(Overflow));
+    assert_eq!(
+        dst_r.try_query(50_002..=60_000),
+        Ok(Some(CheckedSum(9_999)))
+    );
+    assert_eq!(dst_r.validate(), Ok(()));
+
+    let mut total: i64 = 5_000_050_000;
+    for index in 0..100_000 {
+        let result = dst.try_set(index, CheckedSum(1));
+        // the tree fits once enough of the elements are 1
+        total -= index;
+        assert_eq!(result.is_ok(), total <= i32::MAX as i64, "{}", index);
+    }
+    assert_eq!(dst.try_insert(7, CheckedSum(i32::MAX)), Err(Overflow));
+    assert_eq!(dst.try_query(8..=99_999), Ok(Some(CheckedSum(99_992))));
+
+    let values: Vec<SaturatingSum<i32>> = (1..=100_000).map(SaturatingSum).collect();
+    let st = ArrayBasedSegmentTree::new(&values, ops::boxed());
+    assert_eq!(st.query(0..=99_999), Some(SaturatingSum(i32::MAX)));
+    let values: Vec<WrappingSum<i32>> = (1..=100_000).map(WrappingSum).collect();
+    let st = ArrayBasedSegmentTree::new(&values, ops::boxed());
+    assert_eq!(
+        st.query(0..=99_999),
+        Some(WrappingSum(5_000_050_000i64 as i32))
+    );
+}
@@ -0,0 +1,230 @@
+use std::cmp;
+use std::sync::Arc;
+
+use datastructures_in_rust::intervals::segment_tree::{
+    array_based_segment_tree::ArrayBasedSegmentTree,
+    dynamic_segment_tree::DynamicSegmentTree,
+    dynamic_segment_tree_with_range_updates::DynamicSegmentTreeWithRangeUpdates,
+    snapshot::{SnapshotError, TreeKind},
+};
+
+#[test]
+fn array_based_segment_tree_round_trips() {
+    let values = (1..=1000).collect::<Vec<i64>>();
+    let mut st = ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
+    st.update(10..=20, 7);
+    let mut bytes = vec![];
+    st.save_to(&mut bytes).expect("save");
+    let loaded = ArrayBasedSegmentTree::load_from(bytes.as_slice(), Box::new(|a: i64, b| a + b))
+        .expect("load");
+    for start in (0..1000).step_by(37) {
+        for end in (start..1000).step_by(53) {
+            assert_eq!(loaded.query(start..=end), st.query(start..=end));
+        }
+    }
+}
+
+#[test]
+fn dynamic_segment_tree_round_trips_sparse_shape() {
+    let mut dst: DynamicSegmentTree<u64> =
+        DynamicSegmentTree::new(0..=1_000_000_000, Arc::new(|a, b| a + b));
+    for (i, index) in [5, 1_000, 999_999_999, 123_456_789].iter().enumerate() {
+        dst.insert(*index, i as u64 + 1);
+    }
+    let mut bytes = vec![];
+    dst.save_to(&mut bytes).expect("save");
+    let loaded: DynamicSegmentTree<u64> =
+        DynamicSegmentTree::load_from(bytes.as_slice(), Arc::new(|a, b| a + b)).expect("load");
+    // only the created nodes are saved, a dense tree over the range would not fit in memory
+    assert!(bytes.len() < 4 * 64 * 32);
+    for range in [
+        0..=1_000_000_000,
+        0..=5,
+        6..=1_000,
+        1_001..=999_999_999,
+        7..=8,
+    ] {
+        assert_eq!(loaded.query(range.clone()), dst.query(range));
+    }
+    let mut resaved = vec![];
+    loaded.save_to(&mut resaved).expect("save");
+    assert_eq!(resaved, bytes);
+}
+
+#[test]
+fn dynamic_segment_tree_with_range_updates_round_trips_pending_updates() {
+    let mut dst: DynamicSegmentTreeWithRangeUpdates<i64> =
+        DynamicSegmentTreeWithRangeUpdates::new(0..=1_000_000, Arc::new(cmp::max));
+    dst.update(0..=500_000, 3);
+    dst.update(10..=20, 5);
+    dst.update(250_000..=750_000, 4);
+    let mut bytes = vec![];
+    dst.save_to(&mut bytes).expect("save");
+    let mut loaded: DynamicSegmentTreeWithRangeUpdates<i64> =
+        DynamicSegmentTreeWithRangeUpdates::load_from(bytes.as_slice(), Arc::new(cmp::max))
+            .expect("load");
+    let mut resaved = vec![];
+    loaded.save_to(&mut resaved).expect("save");
+    assert_eq!(resaved, bytes);
+    // the pending updates are applied when the loaded tree is updated
+    dst.update(100..=300_000, 1);
+    loaded.update(100..=300_000, 1);
+    for range in [
+        0..=9,
+        10..=20,
+        21..=99,
+        100..=400_000,
+        400_000..=800_000,
+        0..=1_000_000,
+    ] {
+        assert_eq!(loaded.query(range.clone()), dst.query(range));
+    }
+}
+
+#[test]
+fn dynamic_segment_tree_with_range_updates_round_trips_pending_additions() {
+    let mut dst: DynamicSegmentTreeWithRangeUpdates<i64> =
+        DynamicSegmentTreeWithRangeUpdates::new_with_add(
+            0..=1_000_000,
+            Arc::new(cmp::max),
+            Arc::new(|a, b| a + b),
+        );
+    dst.update(0..=500_000, 3);
+    dst.add(10..=20, 5);
+    dst.add(250_000..=750_000, 4);
+    let mut bytes = vec![];
+    dst.save_to(&mut bytes).expect("save");
+    // the additions can not be applied without the add function
+    let result: Result<DynamicSegmentTreeWithRangeUpdates<i64>, _> =
+        DynamicSegmentTreeWithRangeUpdates::load_from(bytes.as_slice(), Arc::new(cmp::max));
+    assert!(matches!(result, Err(SnapshotError::InvalidData(_))));
+    let mut loaded: DynamicSegmentTreeWithRangeUpdates<i64> =
+        DynamicSegmentTreeWithRangeUpdates::load_from_with_add(
+            bytes.as_slice(),
+            Arc::new(cmp::max),
+            Arc::new(|a, b| a + b),
+        )
+        .expect("load");
+    let mut resaved = vec![];
+    loaded.save_to(&mut resaved).expect("save");
+    assert_eq!(resaved, bytes);
+    dst.add(100..=300_000, 1);
+    loaded.add(100..=300_000, 1);
+    for range in [
+        0..=9,
+        10..=20,
+        21..=99,
+        100..=400_000,
+        400_000..=800_000,
+        0..=1_000_000,
+    ] {
+        assert_eq!(loaded.query(range.clone()), dst.query(range));
+    }
+    assert_eq!(loaded.validate(), Ok(()));
+}
+
+#[test]
+fn truncated_snapshots_are_rejected() {
+    let values = (1..=100).collect::<Vec<i64>>();
+    let st = ArrayBasedSegmentTree::new(&values, Box::new(|a, b| a + b));
+    let mut bytes = vec![];
+    st.save_to(&mut bytes).expect("save");
+    for len in [0, 3, 10, bytes.len() / 2, bytes.len() - 1] {
+        let result = ArrayBasedSegmentTree::load_from(&bytes[..len], Box::new(|a: i64, b| a + b));
+        assert!(
+            matches!(result, Err(SnapshotError::Truncated)),
+            "length {}",
+            len
+        );
+    }
+}
+
+#[test]
+fn snapshots_claiming_too_many_elements_are_rejected() {
+    let st = ArrayBasedSegmentTree::new(&[1i64, 2, 3], Box::new(|a, b| a + b));
+    let mut bytes = vec![];
+    st.save_to(&mut bytes).expect("save");
+    // the size is followed by the 5 nodes and the checksum
+    let size_index = bytes.len() - 4 - 5 * 8 - 8;
+    assert_eq!(bytes[size_index..size_index + 8], 3u64.to_le_bytes());
+    let size = (usize::MAX / 8) as u64;
+    bytes[size_index..size_index + 8].copy_from_slice(&size.to_le_bytes());
+    let result = ArrayBasedSegmentTree::load_from(bytes.as_slice(), Box::new(|a: i64, b| a + b));
+    assert!(matches!(result, Err(SnapshotError::Truncated)));
+}
+
+#[test]
+fn corrupt_snapshots_are_rejected() {
+    let values = (1..=100).collect::<Vec<i64>>();
+    let dst = DynamicSegmentTree::new_with_values(&values, Arc::new(|a, b| a + b));
+    let mut bytes = vec![];
+    dst.save_to(&mut bytes).expect("save");
+    // flip a bit in the value of the last leaf, just before the checksum
+    let last_value_index = bytes.len() - 4 - 8;
+    bytes[last_value_index] ^= 1;
+    let result = DynamicSegmentTree::load_from(bytes.as_slice(), Arc::new(|a: i64, b| a + b));
+    assert!(matches!(
+        result,
+        Err(SnapshotError::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn mismatched_snapshots_are_rejected() {
+    let values = (1..=100).collect::<Vec<i64>>();
+    let dst = DynamicSegmentTree::new_with_values(&values, Arc::new(|a, b| a + b));
+    let mut bytes = vec![];
+    dst.save_to(&mut bytes).expect("save");
+    let result = DynamicSegmentTreeWithRangeUpdates::load_from(
+        bytes.as_slice(),
+        Arc::new(|a: i64, b| a + b),
+    );
+    assert!(matches!(
+        result,
+        Err(SnapshotError::KindMismatch {
+            expected: TreeKind::DynamicWithRangeUpdates,
+            found: 2
+        })
+    ));
+    let result = DynamicSegmentTree::load_from(bytes.as_slice(), Arc::new(|a: u64, b| a + b));
+    assert!(matches!(result, Err(SnapshotError::TypeMismatch { .. })));
+    let result = DynamicSegmentTree::load_from(&b"not a snapshot"[..], Arc::new(|a: i64, b| a + b));
+    assert!(matches!(result, Err(SnapshotError::InvalidMagic)));
+}
+
+/// A sum tree over 0..=7 updated with 1 over 0..=7 then 5 over 2..=3, saved by a version 1 writer
+const VERSION_1_SNAPSHOT: &[u8] = &[
+    68, 83, 83, 84, 1, 0, 3, 3, 0, 0, 0, 0, 0, 0, 0, 105, 54, 52, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0,
+    0, 0, 0, 0, 0, 3, 16, 0, 0, 0, 0, 0, 0, 0, 3, 12, 0, 0, 0, 0, 0, 0, 0, 4, 2, 0, 0, 0, 0, 0, 0,
+    0, 1, 0, 0, 0, 0, 0, 0, 0, 4, 10, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 4, 4, 0, 0, 0,
+    0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 185, 39, 110, 64,
+];
+
+#[test]
+fn version_1_snapshots_are_still_read() {
+    let mut loaded: DynamicSegmentTreeWithRangeUpdates<i64> =
+        DynamicSegmentTreeWithRangeUpdates::load_from(VERSION_1_SNAPSHOT, Arc::new(|a, b| a + b))
+            .expect("load");
+    assert_eq!(loaded.query(0..=7), Some(16));
+    assert_eq!(loaded.query(2..=4), Some(11));
+    assert_eq!(loaded.validate(), Ok(()));
+    loaded.update(3..=6, 2);
+    assert_eq!(loaded.query(0..=7), Some(16));
+    assert_eq!(loaded.query(2..=3), Some(7));
+    // saved again with the current version
+    let mut bytes = vec![];
+    loaded.save_to(&mut bytes).expect("save");
+    assert_eq!(&bytes[4..6], &2u16.to_le_bytes());
+}
+
+#[test]
+fn newer_versions_are_rejected() {
+    let dst: DynamicSegmentTreeWithRangeUpdates<i64> =
+        DynamicSegmentTreeWithRangeUpdates::new(0..=7, Arc::new(|a, b| a + b));
+    let mut bytes = vec![];
+    dst.save_to(&mut bytes).expect("save");
+    bytes[4..6].copy_from_slice(&3u16.to_le_bytes());
+    let result: Result<DynamicSegmentTreeWithRangeUpdates<i64>, _> =
+        DynamicSegmentTreeWithRangeUpdates::load_from(bytes.as_slice(), Arc::new(|a, b| a + b));
+    assert!(matches!(result, Err(SnapshotError::UnsupportedVersion(3))));
+}
@@ -0,0 +1,58 @@
+use datastructures_in_rust::suffix_array::{longest_common_substring, SuffixArray};
+
+/// LeetCode 1044, the longest substring occurring at least twice, "" if there is none
+fn longest_dup_substring(s: String) -> String {
+    let suffix_array = SuffixArray::from_bytes(s.as_bytes());
+    suffix_array
+        .longest_repeated_substring()
+        .map_or(String::new(), |range| s[range].to_string())
+}
+
+/// LeetCode 1062, the length of the longest substring occurring at least twice
+fn longest_repeating_substring(s: String) -> i32 {
+    let suffix_array = SuffixArray::from_bytes(s.as_bytes());
+    suffix_array
+        .longest_repeated_substring()
+        .map_or(0, |range| range.len() as i32)
+}
+
+/// LeetCode 718, the length of the longest subarray common to both arrays
+fn find_length(nums1: Vec<i32>, nums2: Vec<i32>) -> i32 {
+    longest_common_substring(&nums1, &nums2).map_or(0, |(range, _)| range.len() as i32)
+}
+
+#[test]
+fn longest_dup_substring_works() {
+    assert_eq!(longest_dup_substring("banana".to_string()), "ana");
+    assert_eq!(longest_dup_substring("abcd".to_string()), "");
+    assert_eq!(longest_dup_substring("aa".to_string()), "a");
+    let long = "ab".repeat(15_000);
+    assert_eq!(longest_dup_substring(long.clone()).len(), long.len() - 2);
+}
+
+#[test]
+fn longest_repeating_substring_works() {
+    assert_eq!(longest_repeating_substring("abcd".to_string()), 0);
+    assert_eq!(longest_repeating_substring("abbaba".to_string()), 2);
+    assert_eq!(longest_repeating_substring("aabcaabdaab".to_string()), 3);
+}
+
+#[test]
+fn find_length_works() {
+    assert_eq!(find_length(vec![1, 2, 3, 2, 1], vec![3, 2, 1, 4, 7]), 3);
+    assert_eq!(find_length(vec![0, 0, 0, 0, 0], vec![0, 0, 0, 0, 0]), 5);
+    assert_eq!(find_length(vec![1, 2], vec![3, 4]), 0);
+}
+
+/// The repeated requests of a log
+#[test]
+fn suffix_array_counts_and_finds_log_lines() {
+    let log = "GET /a 200\nGET /b 404\nGET /a 200\nPOST /a 500\n";
+    let suffix_array = SuffixArray::from_bytes(log.as_bytes());
+    assert_eq!(suffix_array.count(b"GET /a 200\n"), 2);
+    assert_eq!(suffix_array.find_all(b" /a"), vec![3, 25, 37]);
+    assert_eq!(
+        suffix_array.longest_repeated_substring().map(|r| &log[r]),
+        Some("GET /a 200\n")
+    );
+}